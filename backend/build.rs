@@ -19,7 +19,7 @@ async fn create_test_save() -> Result<()> {
     }
 
     // Create the save file
-    let save = Save::create(TEST_SAVE_NAME, TEST_SAVE_DESCRIPTION, TEST_SAVE_PASSWORD).await?;
+    let (save, _) = Save::create(TEST_SAVE_NAME, TEST_SAVE_DESCRIPTION, TEST_SAVE_PASSWORD).await?;
 
     // Add accounts
 
@@ -0,0 +1,88 @@
+use crate::autosave::sleep_until;
+use backend_common::*;
+use common::{AutoLockConfig, ExpectedCommandError};
+use db::Save;
+use log::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify};
+use tokio::time::Instant;
+
+/// Locks the open save file by saving and closing it, remembering its path
+/// so that it can be reopened with its password. If the save cannot be
+/// written, it is kept open so that no changes are lost, and the error is
+/// returned.
+pub async fn lock(
+    save_option: &mut Option<Save>,
+    locked_save: &Mutex<Option<String>>,
+) -> Result<()> {
+    let Some(save) = save_option.as_mut() else {
+        Err(ExpectedCommandError::NoSaveOpen)?
+    };
+
+    save.save().await?;
+
+    // Once the save is written, closing it cannot lose any changes, so it is
+    // forgotten even if closing fails and is never left decrypted
+    let save = save_option.take().unwrap();
+    *locked_save.lock().await = Some(save.path().to_owned());
+
+    save.close().await
+}
+
+/// Locks the open save file in the background once it has gone unused for
+/// the configured idle timeout. The last activity should be updated whenever
+/// a command is run, and the notifier woken whenever the configuration
+/// changes. Runs until the save file is closed or locked.
+pub async fn auto_lock(
+    save: Arc<Mutex<Option<Save>>>,
+    locked_save: Arc<Mutex<Option<String>>>,
+    config: Arc<Mutex<AutoLockConfig>>,
+    last_activity: Arc<Mutex<Instant>>,
+    notify: Arc<Notify>,
+) {
+    loop {
+        let config = *config.lock().await;
+        let idle_timeout =
+            (config.idle_timeout_secs > 0).then(|| Duration::from_secs(config.idle_timeout_secs));
+        let deadline = match idle_timeout {
+            Some(idle_timeout) => Some(*last_activity.lock().await + idle_timeout),
+            None => None,
+        };
+
+        tokio::select! {
+            _ = sleep_until(deadline) => {}
+            _ = notify.notified() => {}
+        }
+
+        let mut save_option = save.lock().await;
+
+        if save_option.is_none() {
+            break;
+        }
+
+        // Commands run while waiting push the deadline back
+        let Some(idle_timeout) = idle_timeout else {
+            continue;
+        };
+
+        if Instant::now() < *last_activity.lock().await + idle_timeout {
+            continue;
+        }
+
+        info!(
+            "Locking the save file after {} seconds without activity",
+            config.idle_timeout_secs
+        );
+
+        match lock(&mut save_option, &locked_save).await {
+            Ok(()) => break,
+            Err(err) => {
+                // The save is kept open, and locking it is tried again once
+                // it has gone unused for another idle timeout
+                error!("Error while locking the save file: {}", err);
+                *last_activity.lock().await = Instant::now();
+            }
+        }
+    }
+}
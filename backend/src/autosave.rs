@@ -0,0 +1,74 @@
+use common::AutosaveConfig;
+use db::Save;
+use log::{error, info};
+use std::future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify};
+use tokio::time::{self, Instant};
+
+/// Waits until the deadline, or forever if there is none.
+pub async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => time::sleep_until(deadline).await,
+        None => future::pending().await,
+    }
+}
+
+/// Saves the open save file in the background, whenever the configured
+/// interval passes with changes unsaved or enough changes build up. The
+/// notifier should be woken whenever the database or the configuration
+/// changes. Runs until the save file is closed.
+pub async fn autosave(
+    save: Arc<Mutex<Option<Save>>>,
+    config: Arc<Mutex<AutosaveConfig>>,
+    notify: Arc<Notify>,
+) {
+    let mut interval_start = Instant::now();
+
+    loop {
+        let config = *config.lock().await;
+        let deadline = (config.interval_secs > 0)
+            .then(|| interval_start + Duration::from_secs(config.interval_secs));
+
+        tokio::select! {
+            _ = sleep_until(deadline) => {}
+            _ = notify.notified() => {}
+        }
+
+        let mut save_option = save.lock().await;
+        let Some(save) = save_option.as_mut() else {
+            break;
+        };
+
+        let interval_passed = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+        if interval_passed {
+            interval_start = Instant::now();
+        }
+
+        let unsaved_changes = match save.unsaved_changes().await {
+            Ok(unsaved_changes) => unsaved_changes,
+            Err(err) => {
+                error!("Error while checking for unsaved changes: {}", err);
+                continue;
+            }
+        };
+        let too_many_changes =
+            config.max_unsaved_changes > 0 && unsaved_changes >= config.max_unsaved_changes;
+
+        if unsaved_changes > 0 && (interval_passed || too_many_changes) {
+            match save.save().await {
+                Ok(()) => {
+                    info!("Automatically saved {} changes", unsaved_changes);
+                    interval_start = Instant::now();
+
+                    if let Err(err) = save.backup().await {
+                        error!("Error while backing up the save file: {}", err);
+                    }
+                }
+                Err(err) => error!("Error while automatically saving: {}", err),
+            }
+        }
+    }
+}
@@ -1,24 +1,24 @@
+use backend_common::{logs_dir, migrate_legacy_dir};
 use chrono::Local;
 use common::format_log_message;
 use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-/// The directory where log files are stored.
-pub(crate) const LOGS_DIR: &str = "logs";
+/// The directory older versions of the application stored log files in.
+pub(crate) const LEGACY_LOGS_DIR: &str = "logs";
 
 /// The file extension for log files.
 pub(crate) const LOG_FILE_EXT: &str = "log";
 
 /// Creates the logs directory if it does not already exist.
 pub(crate) fn init_logs_dir() -> io::Result<()> {
-    let root_path = project_root::get_project_root()?;
-    let logs_dir = root_path.join(LOGS_DIR);
+    let logs_dir = logs_dir();
 
     if !logs_dir.exists() {
-        fs::create_dir(&logs_dir)?;
+        fs::create_dir_all(logs_dir)?;
     }
 
     Ok(())
@@ -26,11 +26,19 @@ pub(crate) fn init_logs_dir() -> io::Result<()> {
 
 /// Gets the path to the logs directory.
 pub(crate) fn get_logs_path() -> String {
-    let root_path = project_root::get_project_root().unwrap();
-    let logs_path = format!("{}/{}", root_path.display(), LOGS_DIR);
+    let logs_path = logs_dir().display().to_string();
     logs_path
 }
 
+/// Moves the log files older versions of the application kept under the
+/// given directory into the logs directory. Returns the new paths of the
+/// moved files.
+pub(crate) fn migrate_legacy_logs(legacy_root: &Path) -> io::Result<Vec<PathBuf>> {
+    migrate_legacy_dir(&legacy_root.join(LEGACY_LOGS_DIR), logs_dir(), |path| {
+        path.extension().is_some_and(|ext| ext == LOG_FILE_EXT)
+    })
+}
+
 /// The logger for the app backend.
 #[derive(Debug, Clone)]
 struct AppLogger {
@@ -7,20 +7,66 @@
     windows_subsystem = "windows"
 )]
 
+mod autolock;
+mod autosave;
 mod logger;
 mod state;
 
-use log::info;
+use backend_common::{data_dir, Result};
+use db::Save;
+use log::{error, info};
 pub use state::{command, State};
+use std::path::PathBuf;
 use tauri::Manager;
 
+/// Moves the files older versions of the application kept in the project
+/// directory into the data, cache and logs directories. Returns the new
+/// paths of the moved files.
+fn migrate_legacy_data() -> Result<Vec<PathBuf>> {
+    let Ok(legacy_root) = project_root::get_project_root() else {
+        return Ok(Vec::new());
+    };
+
+    let mut moved = logger::migrate_legacy_logs(&legacy_root)?;
+    moved.extend(Save::migrate_legacy_dirs(&legacy_root)?);
+
+    Ok(moved)
+}
+
 /// Start the backend Tauri application.
 #[tokio::main]
 async fn main() {
+    let migrated = migrate_legacy_data();
+
     logger::init().expect("Error setting up logger");
     info!("Starting up the application");
+    info!("Using data directory {}", data_dir().display());
+
+    match migrated {
+        Ok(moved) if !moved.is_empty() => {
+            info!("Moved {} files into the data directories", moved.len());
+        }
+        Ok(_) => {}
+        Err(err) => error!(
+            "Error while moving files into the data directories: {}",
+            err
+        ),
+    }
+
+    match Save::migrate_legacy_names().await {
+        Ok(migrated) if !migrated.is_empty() => {
+            info!("Renamed {} save files to their identifiers", migrated.len());
+        }
+        Ok(_) => {}
+        Err(err) => error!(
+            "Error while renaming save files to their identifiers: {}",
+            err
+        ),
+    }
 
     let state = State::new();
+    state.check_leftover_saves().await;
+    state.load_password_throttle().await;
 
     tauri::async_runtime::set(tokio::runtime::Handle::current());
 
@@ -1,7 +1,10 @@
+use crate::autolock::{auto_lock, lock};
+use crate::autosave::autosave;
 use backend_common::*;
 use chrono::NaiveDate;
 use commands::BackendCommands;
 use common::*;
+use crypto::SecretString;
 use db::*;
 use log::{error, info};
 use std::collections::HashMap;
@@ -10,12 +13,40 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use tauri::WindowEvent;
-use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Commands the frontend runs in the background, which do not count as
+/// activity when deciding whether to lock the open save.
+const BACKGROUND_COMMANDS: &[&str] = &["demo_mode", "autosave_status", "save_locked"];
 
 /// The backend application state.
 pub struct State {
     /// The backend database.
     save: Arc<Mutex<Option<Save>>>,
+    /// When the open save is saved automatically.
+    autosave_config: Arc<Mutex<AutosaveConfig>>,
+    /// Wakes the autosave task when the database or its configuration
+    /// changes.
+    autosave_notify: Arc<Notify>,
+    /// The task saving the open save automatically.
+    autosave_task: Mutex<Option<JoinHandle<()>>>,
+    /// How many backups of saves are kept, and for how long.
+    backup_policy: Mutex<BackupPolicy>,
+    /// The path to the locked save, if the open save has been locked.
+    locked_save: Arc<Mutex<Option<String>>>,
+    /// When the open save is locked after going unused.
+    auto_lock_config: Arc<Mutex<AutoLockConfig>>,
+    /// When the last command other than those run in the background was
+    /// run.
+    last_activity: Arc<Mutex<Instant>>,
+    /// Wakes the auto-lock task when its configuration changes.
+    auto_lock_notify: Arc<Notify>,
+    /// The task locking the open save once it goes unused.
+    auto_lock_task: Mutex<Option<JoinHandle<()>>>,
+    /// The incorrect attempts to unlock each save.
+    password_throttle: Mutex<PasswordThrottle>,
 }
 
 impl State {
@@ -23,6 +54,16 @@ impl State {
     pub fn new() -> Self {
         Self {
             save: Arc::new(Mutex::new(None)),
+            autosave_config: Arc::new(Mutex::new(AutosaveConfig::default())),
+            autosave_notify: Arc::new(Notify::new()),
+            autosave_task: Mutex::new(None),
+            backup_policy: Mutex::new(BackupPolicy::default()),
+            locked_save: Arc::new(Mutex::new(None)),
+            auto_lock_config: Arc::new(Mutex::new(AutoLockConfig::default())),
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+            auto_lock_notify: Arc::new(Notify::new()),
+            auto_lock_task: Mutex::new(None),
+            password_throttle: Mutex::new(PasswordThrottle::default()),
         }
     }
 
@@ -39,9 +80,9 @@ impl State {
         Ok(())
     }
 
-    /// Checks if a save with the given name exists.
-    pub fn save_exists(&self, save_name: &str) -> bool {
-        Save::exists(save_name)
+    /// Checks if a save with the given identifier exists.
+    pub fn save_exists(&self, save_id: &str) -> bool {
+        Save::exists(save_id)
     }
 
     /// Checks if a save is currently open.
@@ -49,53 +90,354 @@ impl State {
         self.save.lock().await.is_some()
     }
 
-    /// Creates and opens a new save file.
+    /// Checks if the open save has been locked.
+    pub async fn is_save_locked(&self) -> bool {
+        self.locked_save.lock().await.is_some()
+    }
+
+    /// Notes that a command was run, putting off locking the open save
+    /// unless the command is one the frontend runs in the background.
+    pub async fn record_activity(&self, command: &str) {
+        if !BACKGROUND_COMMANDS.contains(&command) {
+            *self.last_activity.lock().await = Instant::now();
+        }
+    }
+
+    /// Creates and opens a new save file, returning its recovery phrase. The
+    /// save file is created at the given path, or in the saves directory if
+    /// none is given.
     pub async fn create_save(
         &self,
+        save_path: Option<&str>,
         save_name: &str,
         save_description: &str,
-        save_password: &str,
-    ) -> Result<()> {
+        save_password: &SecretString,
+        encrypt_metadata: bool,
+    ) -> Result<String> {
         let mut save_option = self.save.lock().await;
 
         if save_option.is_some() {
             Err(ExpectedCommandError::SaveAlreadyOpen)?;
         }
 
-        let save = Save::create(save_name, save_description, save_password).await?;
+        let (mut save, recovery_phrase) = match save_path {
+            Some(save_path) => {
+                Save::create_at(
+                    save_path,
+                    save_name,
+                    save_description,
+                    save_password,
+                    encrypt_metadata,
+                )
+                .await?
+            }
+            None => {
+                Save::create(save_name, save_description, save_password, encrypt_metadata).await?
+            }
+        };
+        Self::add_recent_save(save.path()).await;
+        save.set_backup_policy(*self.backup_policy.lock().await);
         *save_option = Some(save);
+        *self.locked_save.lock().await = None;
+        self.start_autosave().await;
+        self.start_auto_lock().await;
 
-        Ok(())
+        Ok(recovery_phrase)
+    }
+
+    /// Logs any data left behind by save files that were not closed
+    /// cleanly, which the user is offered to recover from the open view.
+    pub async fn check_leftover_saves(&self) {
+        match Save::leftovers().await {
+            Ok(leftovers) => {
+                for leftover in leftovers {
+                    info!(
+                        "Found leftover data for save file {}: {:?}",
+                        &leftover.name, leftover.kind
+                    );
+                }
+            }
+            Err(err) => error!("Error while looking for leftover save data: {}", err),
+        }
+    }
+
+    /// Recovers leftover data into its save file. No save may be open while
+    /// this happens, as it could be the one being replaced.
+    pub async fn recover_leftover_save(
+        &self,
+        leftover: &LeftoverSave,
+        save_password: &SecretString,
+    ) -> Result<()> {
+        let save_option = self.save.lock().await;
+
+        if save_option.is_some() {
+            Err(ExpectedCommandError::SaveAlreadyOpen)?;
+        }
+
+        let throttle_key = Save::password_throttle_key_at(&leftover.path).await?;
+        self.throttle_password(
+            &throttle_key,
+            Save::recover_leftover(leftover, save_password),
+        )
+        .await
     }
 
-    /// Opens a save file.
-    pub async fn open_save(&self, save_name: &str, save_password: &str) -> Result<()> {
+    /// Discards leftover data from a save file that was not closed cleanly.
+    pub async fn discard_leftover_save(&self, leftover: &LeftoverSave) -> Result<()> {
+        let save_option = self.save.lock().await;
+
+        if save_option.is_some() {
+            Err(ExpectedCommandError::SaveAlreadyOpen)?;
+        }
+
+        Save::discard_leftover(leftover).await
+    }
+
+    /// Loads the persisted incorrect attempts to unlock saves. Failing to do
+    /// so is logged, and no attempts are throttled.
+    pub async fn load_password_throttle(&self) {
+        match PasswordThrottle::load().await {
+            Ok(password_throttle) => *self.password_throttle.lock().await = password_throttle,
+            Err(err) => error!("Error while loading incorrect password attempts: {}", err),
+        }
+    }
+
+    /// Runs an attempt to unlock the save throttled by the given key using a
+    /// password, refusing it while too many incorrect passwords have been
+    /// tried. Attempts are run one at a time, so that they cannot be raced.
+    async fn throttle_password<F, R>(&self, throttle_key: &str, attempt: F) -> Result<R>
+    where
+        F: Future<Output = Result<R>>,
+    {
+        let mut password_throttle = self.password_throttle.lock().await;
+        password_throttle.check(throttle_key)?;
+
+        let result = attempt.await;
+        let recorded = match &result {
+            Ok(_) => password_throttle.record_success(throttle_key).await,
+            Err(Error::Expected(err)) if **err == ExpectedCommandError::InvalidSavePassword => {
+                password_throttle.record_failure(throttle_key).await
+            }
+            Err(_) => Ok(()),
+        };
+
+        if let Err(err) = recorded {
+            error!("Error while recording an attempt to unlock a save: {}", err);
+        }
+
+        result
+    }
+
+    /// Adds a save file to the recently opened saves. Failing to do so is
+    /// logged rather than stopping the save from being used.
+    async fn add_recent_save(save_path: &str) {
+        if let Err(err) = Save::add_recent(save_path).await {
+            error!("Error while adding to the recently opened saves: {}", err);
+        }
+    }
+
+    /// Opens the save file at the given path.
+    pub async fn open_save(&self, save_path: &str, save_password: &SecretString) -> Result<()> {
         let mut save_option = self.save.lock().await;
 
         if save_option.is_some() {
             Err(ExpectedCommandError::SaveAlreadyOpen)?;
         }
 
-        let save = Save::open(save_name, save_password).await?;
+        let throttle_key = Save::password_throttle_key_at(save_path).await?;
+        let mut save = self
+            .throttle_password(&throttle_key, Save::open_at(save_path, save_password))
+            .await?;
+        Self::add_recent_save(save.path()).await;
+        save.set_backup_policy(*self.backup_policy.lock().await);
         *save_option = Some(save);
+        *self.locked_save.lock().await = None;
+        self.start_autosave().await;
+        self.start_auto_lock().await;
 
         Ok(())
     }
 
-    /// Closes the open save file.
+    /// Closes the open save file. Its data encryption key is wiped from
+    /// memory as it is dropped, whether or not closing succeeds. A locked
+    /// save is closed by forgetting it.
     pub async fn close_save(&self) -> Result<()> {
         let mut save_option = self.save.lock().await;
 
         match save_option.take() {
             Some(save) => {
+                self.stop_autosave().await;
+                self.stop_auto_lock().await;
                 save.close().await?;
 
                 Ok(())
             }
-            None => Err(ExpectedCommandError::NoSaveOpen)?,
+            None => match self.locked_save.lock().await.take() {
+                Some(_) => Ok(()),
+                None => Err(ExpectedCommandError::NoSaveOpen)?,
+            },
+        }
+    }
+
+    /// Locks the open save file, saving and closing it until it is unlocked
+    /// with its password again. If the save cannot be written, it is kept
+    /// open and the error is returned.
+    pub async fn lock_save(&self) -> Result<()> {
+        let mut save_option = self.save.lock().await;
+
+        if save_option.is_none() {
+            Err(ExpectedCommandError::NoSaveOpen)?;
+        }
+
+        self.stop_autosave().await;
+        self.stop_auto_lock().await;
+
+        let locked = lock(&mut save_option, &self.locked_save).await;
+
+        // A save kept open goes back to being saved and locked automatically
+        if let Err(err) = &locked {
+            if save_option.is_some() {
+                error!("Error while locking the save file: {}", err);
+                self.start_autosave().await;
+                self.start_auto_lock().await;
+            }
+        }
+
+        locked
+    }
+
+    /// Unlocks the locked save file by reopening it with its password.
+    pub async fn unlock_save(&self, save_password: &SecretString) -> Result<()> {
+        let save_path = self
+            .locked_save
+            .lock()
+            .await
+            .clone()
+            .ok_or(ExpectedCommandError::SaveNotLocked)?;
+
+        self.open_save(&save_path, save_password).await
+    }
+
+    /// Restores a save file from one of its backups. No save may be open
+    /// while this happens, as it could be the one being replaced.
+    pub async fn restore_save_backup(
+        &self,
+        backup: &SaveBackup,
+        save_password: &SecretString,
+    ) -> Result<()> {
+        let save_option = self.save.lock().await;
+
+        if save_option.is_some() {
+            Err(ExpectedCommandError::SaveAlreadyOpen)?;
+        }
+
+        self.throttle_password(&backup.save_id, Save::restore_backup(backup, save_password))
+            .await
+    }
+
+    /// Compares the open save with one of its backups.
+    pub async fn compare_save_backup(
+        &self,
+        backup: &SaveBackup,
+        save_password: &SecretString,
+    ) -> Result<Vec<BackupComparison>> {
+        let mut handle = self.save_handle().await?;
+
+        if backup.save_id != handle.this_metadata().id {
+            Err(ExpectedCommandError::BackupNotFound)?;
+        }
+
+        let mut backup_save = self
+            .throttle_password(&backup.save_id, Save::open_backup(backup, save_password))
+            .await?;
+        let comparisons = handle.compare_with(&mut backup_save).await;
+        backup_save.close().await?;
+
+        comparisons
+    }
+
+    /// Sets how many backups of saves are kept, and for how long.
+    pub async fn set_backup_policy(&self, policy: BackupPolicy) {
+        *self.backup_policy.lock().await = policy;
+
+        if let Some(save) = self.save.lock().await.as_mut() {
+            save.set_backup_policy(policy);
         }
     }
 
+    /// Starts saving the open save automatically in the background.
+    async fn start_autosave(&self) {
+        let task = tokio::spawn(autosave(
+            Arc::clone(&self.save),
+            Arc::clone(&self.autosave_config),
+            Arc::clone(&self.autosave_notify),
+        ));
+
+        if let Some(old_task) = self.autosave_task.lock().await.replace(task) {
+            old_task.abort();
+        }
+    }
+
+    /// Stops saving the open save automatically. This should be called while
+    /// holding the save lock, so that the task is never stopped partway
+    /// through a save.
+    async fn stop_autosave(&self) {
+        if let Some(task) = self.autosave_task.lock().await.take() {
+            task.abort();
+        }
+    }
+
+    /// Starts locking the open save once it goes unused, in the background.
+    async fn start_auto_lock(&self) {
+        *self.last_activity.lock().await = Instant::now();
+
+        let task = tokio::spawn(auto_lock(
+            Arc::clone(&self.save),
+            Arc::clone(&self.locked_save),
+            Arc::clone(&self.auto_lock_config),
+            Arc::clone(&self.last_activity),
+            Arc::clone(&self.auto_lock_notify),
+        ));
+
+        if let Some(old_task) = self.auto_lock_task.lock().await.replace(task) {
+            old_task.abort();
+        }
+    }
+
+    /// Stops locking the open save once it goes unused. This should be
+    /// called while holding the save lock, so that the task is never stopped
+    /// partway through locking.
+    async fn stop_auto_lock(&self) {
+        if let Some(task) = self.auto_lock_task.lock().await.take() {
+            task.abort();
+        }
+    }
+
+    /// Sets how long open saves can go unused before they are locked.
+    pub async fn set_auto_lock_config(&self, config: AutoLockConfig) {
+        *self.auto_lock_config.lock().await = config;
+        self.auto_lock_notify.notify_one();
+    }
+
+    /// Reports when the open save was last saved, and how it is saved
+    /// automatically.
+    pub async fn autosave_status(&self) -> Result<AutosaveStatus> {
+        let config = *self.autosave_config.lock().await;
+        let mut handle = self.save_handle().await?;
+
+        Ok(AutosaveStatus {
+            config,
+            saved_at: handle.saved_at(),
+            unsaved_changes: handle.unsaved_changes().await?,
+        })
+    }
+
+    /// Sets when open saves are saved automatically.
+    pub async fn set_autosave_config(&self, config: AutosaveConfig) {
+        *self.autosave_config.lock().await = config;
+        self.autosave_notify.notify_one();
+    }
+
     /// Returns a handle to the inner save instance.
     pub async fn save_handle(&self) -> Result<MappedMutexGuard<Save>> {
         let save_option = self.save.lock().await;
@@ -104,6 +446,7 @@ impl State {
             Some(_) => Ok(MutexGuard::map(save_option, |guard| {
                 guard.as_mut().unwrap()
             })),
+            None if self.is_save_locked().await => Err(ExpectedCommandError::SaveLocked)?,
             None => Err(ExpectedCommandError::NoSaveOpen)?,
         }
     }
@@ -135,7 +478,10 @@ impl State {
     {
         Box::pin(async move {
             let mut handle = self.save_handle().await?;
-            handle.transaction(f).await
+            let value = handle.transaction(f).await?;
+            self.autosave_notify.notify_one();
+
+            Ok(value)
         })
     }
 
@@ -210,10 +556,15 @@ impl BackendCommands for State {
         self.with_result(Save::list()).await
     }
 
-    async fn open_save_file(&self, save_name: String, save_password: String) -> CommandResult<()> {
-        info!("Attempting to open save file: {}", &save_name);
+    async fn recent_save_files(&self) -> CommandResult<Vec<RecentSave>> {
+        self.with_result(Save::recent()).await
+    }
+
+    async fn open_save_file(&self, save_path: String, save_password: String) -> CommandResult<()> {
+        info!("Attempting to open save file: {}", &save_path);
+        let save_password = SecretString::new(save_password);
 
-        self.with_result(self.open_save(&save_name, &save_password))
+        self.with_result(self.open_save(&save_path, &save_password))
             .await
     }
 
@@ -223,15 +574,113 @@ impl BackendCommands for State {
         self.with_result(self.close_save()).await
     }
 
+    async fn lock_save(&self) -> CommandResult<()> {
+        info!("Locking the save file");
+
+        self.with_result(self.lock_save()).await
+    }
+
+    async fn unlock_save(&self, save_password: String) -> CommandResult<()> {
+        info!("Attempting to unlock the save file");
+        let save_password = SecretString::new(save_password);
+
+        self.with_result(self.unlock_save(&save_password)).await
+    }
+
+    async fn save_locked(&self) -> bool {
+        self.is_save_locked().await
+    }
+
     async fn create_save_file(
         &self,
+        save_path: Option<String>,
         save_name: String,
         save_description: String,
         save_password: String,
+        encrypt_metadata: bool,
+    ) -> CommandResult<String> {
+        let save_password = SecretString::new(save_password);
+
+        // An encrypted name is kept out of the logs too
+        if encrypt_metadata {
+            info!("Attempting to create save file with encrypted metadata");
+        } else {
+            info!("Attempting to create save file: {}", &save_name);
+        }
+
+        self.with_result(self.create_save(
+            save_path.as_deref(),
+            &save_name,
+            &save_description,
+            &save_password,
+            encrypt_metadata,
+        ))
+        .await
+    }
+
+    async fn save_backups(&self, save_id: String) -> CommandResult<Vec<SaveBackup>> {
+        self.with_result(Save::backups(&save_id)).await
+    }
+
+    async fn restore_save_backup(
+        &self,
+        backup: SaveBackup,
+        save_password: String,
     ) -> CommandResult<()> {
-        info!("Attempting to create save file: {}", &save_name);
+        info!(
+            "Attempting to restore save file {} from backup {}",
+            &backup.name, &backup.id
+        );
+        let save_password = SecretString::new(save_password);
 
-        self.with_result(self.create_save(&save_name, &save_description, &save_password))
+        self.with_result(self.restore_save_backup(&backup, &save_password))
+            .await
+    }
+
+    async fn compare_save_backup(
+        &self,
+        backup: SaveBackup,
+        save_password: String,
+    ) -> CommandResult<Vec<BackupComparison>> {
+        let save_password = SecretString::new(save_password);
+
+        self.with_result(self.compare_save_backup(&backup, &save_password))
+            .await
+    }
+
+    async fn set_backup_policy(&self, policy: BackupPolicy) -> CommandResult<()> {
+        info!("Setting the backup policy: {:?}", policy);
+
+        self.with_result(async {
+            self.set_backup_policy(policy).await;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn leftover_saves(&self) -> CommandResult<Vec<LeftoverSave>> {
+        self.with_result(Save::leftovers()).await
+    }
+
+    async fn recover_leftover_save(
+        &self,
+        leftover: LeftoverSave,
+        save_password: String,
+    ) -> CommandResult<()> {
+        info!(
+            "Attempting to recover leftover data for save file: {}",
+            &leftover.name
+        );
+        let save_password = SecretString::new(save_password);
+
+        self.with_result(self.recover_leftover_save(&leftover, &save_password))
+            .await
+    }
+
+    async fn discard_leftover_save(&self, leftover: LeftoverSave) -> CommandResult<()> {
+        info!("Discarding leftover data for save file: {}", &leftover.name);
+
+        self.with_result(self.discard_leftover_save(&leftover))
             .await
     }
 
@@ -243,6 +692,95 @@ impl BackendCommands for State {
         .await
     }
 
+    async fn autosave_status(&self) -> CommandResult<AutosaveStatus> {
+        self.with_result(self.autosave_status()).await
+    }
+
+    async fn set_autosave_config(&self, config: AutosaveConfig) -> CommandResult<()> {
+        info!("Setting the autosave configuration: {:?}", config);
+
+        self.with_result(async {
+            self.set_autosave_config(config).await;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_auto_lock_config(&self, config: AutoLockConfig) -> CommandResult<()> {
+        info!("Setting the auto-lock configuration: {:?}", config);
+
+        self.with_result(async {
+            self.set_auto_lock_config(config).await;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn key_slots(&self) -> CommandResult<Vec<KeySlot>> {
+        self.with_result(async {
+            let handle = self.save_handle().await?;
+            Ok(handle.key_slots())
+        })
+        .await
+    }
+
+    async fn add_password_key_slot(&self, password: String) -> CommandResult<KeySlot> {
+        info!("Adding a password key slot");
+        let password = SecretString::new(password);
+
+        self.with_result(async {
+            let mut handle = self.save_handle().await?;
+            handle.add_password_key_slot(&password).await
+        })
+        .await
+    }
+
+    async fn add_recovery_key_slot(&self) -> CommandResult<(KeySlot, String)> {
+        info!("Adding a recovery key slot");
+
+        self.with_result(async {
+            let mut handle = self.save_handle().await?;
+            handle.add_recovery_key_slot().await
+        })
+        .await
+    }
+
+    async fn remove_key_slot(&self, key_slot: KeySlot) -> CommandResult<()> {
+        info!("Removing key slot {}", key_slot.id);
+
+        self.with_result(async {
+            let mut handle = self.save_handle().await?;
+            handle.remove_key_slot(key_slot.id).await
+        })
+        .await
+    }
+
+    async fn set_save_metadata_encrypted(&self, encrypted: bool) -> CommandResult<()> {
+        info!(
+            "Setting whether the save metadata is encrypted to {}",
+            encrypted
+        );
+
+        self.with_result(async {
+            let mut handle = self.save_handle().await?;
+            handle.set_metadata_encrypted(encrypted).await
+        })
+        .await
+    }
+
+    async fn set_save_compressed(&self, compressed: bool) -> CommandResult<()> {
+        info!(
+            "Setting whether the save data is compressed to {}",
+            compressed
+        );
+
+        self.with_result(async {
+            let mut handle = self.save_handle().await?;
+            handle.set_compressed(compressed).await
+        })
+        .await
+    }
+
     async fn accounts(&self) -> CommandResult<Vec<Account>> {
         self.with(|db| Account::list(db)).await
     }
@@ -252,9 +790,12 @@ impl BackendCommands for State {
         account_type: AccountType,
         name: String,
         description: String,
+        currency: Currency,
     ) -> CommandResult<Account> {
         self.with(|db| {
-            Box::pin(async move { Account::create(db, account_type, &name, &description).await })
+            Box::pin(async move {
+                Account::create(db, account_type, &name, &description, &currency).await
+            })
         })
         .await
     }
@@ -304,46 +845,114 @@ impl BackendCommands for State {
     async fn create_transaction(
         &self,
         mut account: Account,
+        new_transaction: NewAccountTransaction,
+    ) -> CommandResult<(AccountTransaction, Vec<AccountTransactionTag>)> {
+        self.with(|db| {
+            Box::pin(async move {
+                let mut transaction = AccountTransaction::create(
+                    db,
+                    &mut account,
+                    &new_transaction.name,
+                    &new_transaction.description,
+                    new_transaction.amount,
+                    new_transaction.transaction_type,
+                    &new_transaction.institution,
+                    new_transaction.date,
+                    &new_transaction.category,
+                    new_transaction.subcategory.as_ref(),
+                )
+                .await?;
+
+                let mut transaction_tags = Vec::new();
+
+                for tag in new_transaction.tags.iter() {
+                    let transaction_tag =
+                        AccountTransactionTag::create(db, &transaction, tag).await?;
+                    transaction_tags.push(transaction_tag);
+                }
+
+                if !new_transaction.splits.is_empty() {
+                    transaction.set_splits(db, &new_transaction.splits).await?;
+                }
+
+                Ok((transaction, transaction_tags))
+            })
+        })
+        .await
+    }
+
+    async fn transfers(&self) -> CommandResult<Vec<AccountTransfer>> {
+        self.with(|db| AccountTransfer::list(db)).await
+    }
+
+    async fn create_transfer(
+        &self,
+        mut from_account: Account,
+        mut to_account: Account,
         name: String,
         description: String,
-        amount: f64,
-        transaction_type: TransactionType,
+        amount: Money,
         institution: Institution,
         date: NaiveDate,
         category: Category,
         subcategory: Option<Subcategory>,
-        tags: Vec<Tag>,
-    ) -> CommandResult<(AccountTransaction, Vec<AccountTransactionTag>)> {
+    ) -> CommandResult<(AccountTransfer, AccountTransaction, AccountTransaction)> {
         self.with(|db| {
             Box::pin(async move {
-                let transaction = AccountTransaction::create(
+                let transfer = AccountTransfer::create(
                     db,
-                    &mut account,
+                    &mut from_account,
+                    &mut to_account,
                     &name,
                     &description,
                     amount,
-                    transaction_type,
                     &institution,
                     date,
                     &category,
                     subcategory.as_ref(),
                 )
                 .await?;
+                let debit_transaction = transfer.get_debit_transaction(db).await?;
+                let credit_transaction = transfer.get_credit_transaction(db).await?;
 
-                let mut transaction_tags = Vec::new();
-
-                for tag in tags.iter() {
-                    let transaction_tag =
-                        AccountTransactionTag::create(db, &transaction, tag).await?;
-                    transaction_tags.push(transaction_tag);
-                }
+                Ok((transfer, debit_transaction, credit_transaction))
+            })
+        })
+        .await
+    }
 
-                Ok((transaction, transaction_tags))
+    async fn update_transfer(
+        &self,
+        transfer: AccountTransfer,
+        name: String,
+        description: String,
+        amount: Money,
+        date: NaiveDate,
+        category: Category,
+        subcategory: Option<Subcategory>,
+    ) -> CommandResult<(AccountTransaction, AccountTransaction)> {
+        self.with(|db| {
+            Box::pin(async move {
+                transfer
+                    .update(
+                        db,
+                        &name,
+                        &description,
+                        amount,
+                        date,
+                        &category,
+                        subcategory.as_ref(),
+                    )
+                    .await
             })
         })
         .await
     }
 
+    async fn delete_transfer(&self, transfer: AccountTransfer) -> CommandResult<()> {
+        self.with(|db| transfer.delete(db)).await
+    }
+
     async fn institutions(&self) -> CommandResult<Vec<Institution>> {
         self.with(|db| Institution::list(db)).await
     }
@@ -475,4 +1084,44 @@ impl BackendCommands for State {
     async fn delete_tag(&self, tag: Tag) -> CommandResult<()> {
         self.with(|db| tag.delete(db)).await
     }
+
+    async fn exchange_rates(&self) -> CommandResult<Vec<ExchangeRate>> {
+        self.with(|db| ExchangeRate::list(db)).await
+    }
+
+    async fn set_exchange_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        rate_date: NaiveDate,
+        rate: Rate,
+    ) -> CommandResult<ExchangeRate> {
+        self.with(|db| {
+            Box::pin(async move { ExchangeRate::set(db, &from, &to, rate_date, rate).await })
+        })
+        .await
+    }
+
+    async fn import_exchange_rates(&self, csv: String) -> CommandResult<Vec<ExchangeRate>> {
+        self.with(|db| Box::pin(async move { ExchangeRate::import_csv(db, &csv).await }))
+            .await
+    }
+
+    async fn delete_exchange_rate(&self, exchange_rate: ExchangeRate) -> CommandResult<()> {
+        self.with(|db| exchange_rate.delete(db)).await
+    }
+
+    async fn report(
+        &self,
+        base_currency: Currency,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> CommandResult<Report> {
+        self.with(|db| {
+            Box::pin(
+                async move { Report::generate(db, &base_currency, start_date, end_date).await },
+            )
+        })
+        .await
+    }
 }
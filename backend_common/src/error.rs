@@ -25,6 +25,13 @@ impl From<aes_gcm::Error> for ExpectedError {
         Self(ExpectedCommandError::InvalidSavePassword)
     }
 }
+
+impl From<argon2::Error> for ExpectedError {
+    fn from(_: argon2::Error) -> Self {
+        Self(ExpectedCommandError::InvalidKeyDerivation)
+    }
+}
+
 /// An unexpected application error.
 #[derive(Debug, Error)]
 pub enum UnexpectedError {
@@ -62,6 +69,12 @@ impl<E> From<aes_gcm::Error> for Error<E> {
     }
 }
 
+impl<E> From<argon2::Error> for Error<E> {
+    fn from(value: argon2::Error) -> Self {
+        ExpectedError::from(value).into()
+    }
+}
+
 impl<E> From<io::Error> for Error<E> {
     fn from(value: io::Error) -> Self {
         UnexpectedError::from(value).into()
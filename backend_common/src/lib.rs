@@ -4,13 +4,16 @@
 #![deny(missing_docs)]
 
 mod error;
+mod paths;
 
 use std::io;
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::path::Path;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 pub use backend_macros::*;
 pub use error::*;
+pub use paths::*;
 
 /// The length of the size portion of each chunk of data.
 pub const LEN_SIZE: usize = 5;
@@ -42,8 +45,11 @@ pub fn decode_section_size(encoded_size: &[u8; LEN_SIZE]) -> usize {
     size
 }
 
-/// Reads a section of data from a file.
-pub async fn read_section(file: &mut File) -> io::Result<Option<Vec<u8>>> {
+/// Reads a section of data from a file or buffer.
+pub async fn read_section<R>(file: &mut R) -> io::Result<Option<Vec<u8>>>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
     let mut size_buffer = [0u8; LEN_SIZE];
 
     let n = file.read(&mut size_buffer).await?;
@@ -67,8 +73,11 @@ pub async fn read_section(file: &mut File) -> io::Result<Option<Vec<u8>>> {
     Ok(Some(buffer))
 }
 
-/// Writes a section of data to a file.
-pub async fn write_section(file: &mut File, data: &[u8]) -> io::Result<()> {
+/// Writes a section of data to a file or buffer.
+pub async fn write_section<W>(file: &mut W, data: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
     let encoded_size = encode_section_size(data.len());
 
     file.write_all(&encoded_size).await?;
@@ -78,7 +87,11 @@ pub async fn write_section(file: &mut File, data: &[u8]) -> io::Result<()> {
 }
 
 /// Copy the contents of one file to another in chunks.
-pub async fn copy_file_in_chunks(src: &mut File, dest: &mut File) -> io::Result<()> {
+pub async fn copy_file_in_chunks<R, W>(src: &mut R, dest: &mut W) -> io::Result<()>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin + ?Sized,
+{
     let mut buffer = [0u8; READER_CAPACITY];
 
     loop {
@@ -95,3 +108,26 @@ pub async fn copy_file_in_chunks(src: &mut File, dest: &mut File) -> io::Result<
 
     Ok(())
 }
+
+/// Overwrites the contents of a file with zeros before removing it, so that
+/// sensitive data does not linger on disk. This is best effort, as some
+/// filesystems and drives may still keep copies of the original data.
+pub async fn secure_remove_file(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+
+    {
+        let mut file = OpenOptions::new().write(true).open(path).await?;
+        let mut remaining = file.metadata().await?.len();
+        let buffer = [0u8; READER_CAPACITY];
+
+        while remaining > 0 {
+            let n = remaining.min(READER_CAPACITY as u64) as usize;
+            file.write_all(&buffer[..n]).await?;
+            remaining -= n as u64;
+        }
+
+        file.sync_all().await?;
+    }
+
+    fs::remove_file(path).await
+}
@@ -0,0 +1,197 @@
+use directories::ProjectDirs;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The command line flag used to override the data directory.
+pub const DATA_DIR_FLAG: &str = "--data-dir";
+
+/// The environment variable used to override the data directory. The
+/// command line flag takes precedence over it.
+pub const DATA_DIR_ENV: &str = "LEVEL_DATA_DIR";
+
+/// The name of the application, as used in platform directory paths.
+const APP_NAME: &str = "level";
+
+/// The name of the cache directory within an overridden data directory.
+const CACHE_DIR: &str = "cache";
+
+/// The name of the logs directory within an overridden or platform data
+/// directory.
+const LOGS_DIR: &str = "logs";
+
+/// The resolved application directories. These are resolved once, the first
+/// time any of them is needed.
+static APP_DIRS: OnceLock<AppDirs> = OnceLock::new();
+
+/// The directories the application keeps its files in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AppDirs {
+    /// Where save files and their backups are kept.
+    data: PathBuf,
+    /// Where files that can be safely regenerated or discarded are kept.
+    cache: PathBuf,
+    /// Where log files are kept.
+    logs: PathBuf,
+}
+
+impl AppDirs {
+    /// Keeps everything under a single directory.
+    fn within(data_dir: PathBuf) -> Self {
+        Self {
+            cache: data_dir.join(CACHE_DIR),
+            logs: data_dir.join(LOGS_DIR),
+            data: data_dir,
+        }
+    }
+
+    /// Resolves the directories, using the overridden data directory if
+    /// there is one, or the platform directories otherwise.
+    fn resolve() -> Self {
+        if let Some(data_dir) = data_dir_override() {
+            return Self::within(data_dir);
+        }
+
+        match ProjectDirs::from("", "", APP_NAME) {
+            Some(project_dirs) => Self {
+                data: project_dirs.data_dir().to_owned(),
+                cache: project_dirs.cache_dir().to_owned(),
+                logs: project_dirs
+                    .state_dir()
+                    .unwrap_or_else(|| project_dirs.data_local_dir())
+                    .join(LOGS_DIR),
+            },
+            // Without a home directory there are no platform directories, so
+            // fall back to the working directory
+            None => Self::within(env::current_dir().unwrap_or_default().join(APP_NAME)),
+        }
+    }
+}
+
+/// Gets the overridden data directory, from either the command line or the
+/// environment.
+fn data_dir_override() -> Option<PathBuf> {
+    let mut args = env::args_os().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == DATA_DIR_FLAG {
+            return args.next().map(PathBuf::from);
+        }
+
+        if let Some(data_dir) = arg
+            .to_str()
+            .and_then(|arg| arg.strip_prefix(DATA_DIR_FLAG)?.strip_prefix('='))
+        {
+            return Some(PathBuf::from(data_dir));
+        }
+    }
+
+    env::var_os(DATA_DIR_ENV)
+        .filter(|data_dir| !data_dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Gets the application directories, resolving them if necessary.
+fn app_dirs() -> &'static AppDirs {
+    APP_DIRS.get_or_init(AppDirs::resolve)
+}
+
+/// Uses the given data directory instead of resolving one, keeping
+/// everything under it. This only has an effect before any of the
+/// directories have been resolved, and returns whether it did.
+pub fn set_data_dir(data_dir: impl Into<PathBuf>) -> bool {
+    APP_DIRS.set(AppDirs::within(data_dir.into())).is_ok()
+}
+
+/// Gets the directory save files and their backups are kept in.
+pub fn data_dir() -> &'static Path {
+    &app_dirs().data
+}
+
+/// Gets the directory files that can be safely regenerated or discarded are
+/// kept in.
+pub fn cache_dir() -> &'static Path {
+    &app_dirs().cache
+}
+
+/// Gets the directory log files are kept in.
+pub fn logs_dir() -> &'static Path {
+    &app_dirs().logs
+}
+
+/// Moves the files in a directory used by an older version of the
+/// application into their new directory, skipping any the filter rejects or
+/// that already exist in the new directory. The old directory is removed
+/// once nothing is left in it. Returns the new paths of the moved files.
+pub fn migrate_legacy_dir<F>(legacy_dir: &Path, dir: &Path, filter: F) -> io::Result<Vec<PathBuf>>
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut moved = Vec::new();
+
+    if !legacy_dir.is_dir() || same_dir(legacy_dir, dir) {
+        return Ok(moved);
+    }
+
+    fs::create_dir_all(dir)?;
+
+    for entry in fs::read_dir(legacy_dir)? {
+        let legacy_path = entry?.path();
+        let path = dir.join(legacy_path.file_name().unwrap_or_default());
+
+        if !filter(&legacy_path) || path.exists() {
+            continue;
+        }
+
+        move_path(&legacy_path, &path)?;
+        moved.push(path);
+    }
+
+    if fs::read_dir(legacy_dir)?.next().is_none() {
+        fs::remove_dir(legacy_dir)?;
+    }
+
+    Ok(moved)
+}
+
+/// Checks whether two paths refer to the same directory.
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Moves a file or directory, copying it across if it cannot simply be
+/// renamed, as is the case when moving between file systems.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_path(from, to)?;
+
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+/// Recursively copies a file or directory.
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(from, to)?;
+    }
+
+    Ok(())
+}
@@ -14,35 +14,125 @@ pub trait Commands {
     /// Checks whether the app is in demo mode.
     async fn demo_mode(&self) -> bool;
 
-    /// Lists all existing save files.
+    /// Lists all existing save files in the saves directory.
     async fn list_save_files(&self) -> CommandResult<Vec<SaveMetadata>>;
 
-    /// Attempts to open an existing save file.
-    async fn open_save_file(&self, save_name: String, save_password: String) -> CommandResult<()>;
+    /// Lists the recently opened save files, most recent first. Save files
+    /// that no longer exist are pruned from the list.
+    async fn recent_save_files(&self) -> CommandResult<Vec<RecentSave>>;
+
+    /// Attempts to open the save file at the given path.
+    async fn open_save_file(&self, save_path: String, save_password: String) -> CommandResult<()>;
 
     /// Attempts to close the currently open save file.
     async fn close_save_file(&self) -> CommandResult<()>;
 
-    /// Attempts to create a new save file.
+    /// Locks the currently open save file, saving and closing it until it
+    /// is unlocked with its password again.
+    async fn lock_save(&self) -> CommandResult<()>;
+
+    /// Attempts to unlock the locked save file, reopening it with the given
+    /// password.
+    async fn unlock_save(&self, save_password: String) -> CommandResult<()>;
+
+    /// Checks whether the open save file is locked.
+    async fn save_locked(&self) -> bool;
+
+    /// Attempts to create a new save file at the given path, or in the saves
+    /// directory if none is given, returning its recovery phrase. The
+    /// recovery phrase cannot be retrieved again. The name and description
+    /// can be kept encrypted until the save file is unlocked.
     async fn create_save_file(
         &self,
+        save_path: Option<String>,
         save_name: String,
         save_description: String,
         save_password: String,
+        encrypt_metadata: bool,
+    ) -> CommandResult<String>;
+
+    /// Lists the backups of the save file with the given identifier, most
+    /// recent first.
+    async fn save_backups(&self, save_id: String) -> CommandResult<Vec<SaveBackup>>;
+
+    /// Attempts to restore a save file from one of its backups, which must be
+    /// unlockable with the given password.
+    async fn restore_save_backup(
+        &self,
+        backup: SaveBackup,
+        save_password: String,
+    ) -> CommandResult<()>;
+
+    /// Opens a backup of the currently open save file read-only, and
+    /// compares it with the save file table by table.
+    async fn compare_save_backup(
+        &self,
+        backup: SaveBackup,
+        save_password: String,
+    ) -> CommandResult<Vec<BackupComparison>>;
+
+    /// Sets how many backups of save files are kept, and for how long.
+    async fn set_backup_policy(&self, policy: BackupPolicy) -> CommandResult<()>;
+
+    /// Finds data left behind by save files that were not closed cleanly.
+    async fn leftover_saves(&self) -> CommandResult<Vec<LeftoverSave>>;
+
+    /// Attempts to recover leftover data into its save file, replacing what
+    /// the save file held before.
+    async fn recover_leftover_save(
+        &self,
+        leftover: LeftoverSave,
+        save_password: String,
     ) -> CommandResult<()>;
 
+    /// Discards leftover data from a save file that was not closed cleanly.
+    async fn discard_leftover_save(&self, leftover: LeftoverSave) -> CommandResult<()>;
+
     /// Gets information on the currently open save file.
     async fn save_info(&self) -> CommandResult<SaveMetadata>;
 
+    /// Reports when the currently open save file was last saved, and how
+    /// it is saved automatically.
+    async fn autosave_status(&self) -> CommandResult<AutosaveStatus>;
+
+    /// Sets when open save files are saved automatically.
+    async fn set_autosave_config(&self, config: AutosaveConfig) -> CommandResult<()>;
+
+    /// Sets how long open save files can go unused before they are locked.
+    async fn set_auto_lock_config(&self, config: AutoLockConfig) -> CommandResult<()>;
+
+    /// Retrieves the key slots that can unlock the currently open save file.
+    async fn key_slots(&self) -> CommandResult<Vec<KeySlot>>;
+
+    /// Adds a password that can unlock the currently open save file.
+    async fn add_password_key_slot(&self, password: String) -> CommandResult<KeySlot>;
+
+    /// Adds a generated recovery phrase that can unlock the currently open
+    /// save file. The recovery phrase cannot be retrieved again.
+    async fn add_recovery_key_slot(&self) -> CommandResult<(KeySlot, String)>;
+
+    /// Revokes a key slot of the currently open save file.
+    async fn remove_key_slot(&self, key_slot: KeySlot) -> CommandResult<()>;
+
+    /// Sets whether the currently open save file keeps its name and
+    /// description encrypted, so that they cannot be read until it is
+    /// unlocked.
+    async fn set_save_metadata_encrypted(&self, encrypted: bool) -> CommandResult<()>;
+
+    /// Sets whether the currently open save file compresses its data before
+    /// encrypting it.
+    async fn set_save_compressed(&self, compressed: bool) -> CommandResult<()>;
+
     /// Retrieves the accounts within the save file.
     async fn accounts(&self) -> CommandResult<Vec<Account>>;
 
-    /// Creates an account.
+    /// Creates an account, holding money in the given currency.
     async fn create_account(
         &self,
         account_type: AccountType,
         name: String,
         description: String,
+        currency: Currency,
     ) -> CommandResult<Account>;
 
     /// Retrieves a batch of transactions within an account.
@@ -53,20 +143,47 @@ pub trait Commands {
         limit: usize,
     ) -> CommandResult<Vec<(AccountTransaction, Vec<AccountTransactionTag>)>>;
 
-    /// Creates a new transaction.
+    /// Creates a new transaction, split into the given lines if there are
+    /// any.
     async fn create_transaction(
         &self,
         account: Account,
+        transaction: NewAccountTransaction,
+    ) -> CommandResult<(AccountTransaction, Vec<AccountTransactionTag>)>;
+
+    /// Retrieves the transfers between accounts within the save file.
+    async fn transfers(&self) -> CommandResult<Vec<AccountTransfer>>;
+
+    /// Creates a new transfer between two accounts, returning it along with
+    /// its debit and credit transactions.
+    async fn create_transfer(
+        &self,
+        from_account: Account,
+        to_account: Account,
         name: String,
         description: String,
-        amount: f64,
-        transaction_type: TransactionType,
+        amount: Money,
         institution: Institution,
         date: NaiveDate,
         category: Category,
         subcategory: Option<Subcategory>,
-        tags: Vec<Tag>,
-    ) -> CommandResult<(AccountTransaction, Vec<AccountTransactionTag>)>;
+    ) -> CommandResult<(AccountTransfer, AccountTransaction, AccountTransaction)>;
+
+    /// Updates the details both sides of a transfer share, returning its
+    /// updated debit and credit transactions.
+    async fn update_transfer(
+        &self,
+        transfer: AccountTransfer,
+        name: String,
+        description: String,
+        amount: Money,
+        date: NaiveDate,
+        category: Category,
+        subcategory: Option<Subcategory>,
+    ) -> CommandResult<(AccountTransaction, AccountTransaction)>;
+
+    /// Deletes a transfer, along with both of its transactions.
+    async fn delete_transfer(&self, transfer: AccountTransfer) -> CommandResult<()>;
 
     /// Retrieves the institutions within the save file.
     async fn institutions(&self) -> CommandResult<Vec<Institution>>;
@@ -142,4 +259,34 @@ pub trait Commands {
 
     /// Deletes the tag.
     async fn delete_tag(&self, tag: Tag) -> CommandResult<()>;
+
+    /// Retrieves the exchange rates within the save file.
+    async fn exchange_rates(&self) -> CommandResult<Vec<ExchangeRate>>;
+
+    /// Sets the exchange rate between two currencies from the given date,
+    /// replacing any rate already set between them on that date.
+    async fn set_exchange_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        rate_date: NaiveDate,
+        rate: Rate,
+    ) -> CommandResult<ExchangeRate>;
+
+    /// Imports exchange rates from CSV data, with one rate per line in the
+    /// form `date,from,to,rate`. Nothing is imported if any line is invalid.
+    async fn import_exchange_rates(&self, csv: String) -> CommandResult<Vec<ExchangeRate>>;
+
+    /// Deletes the exchange rate.
+    async fn delete_exchange_rate(&self, exchange_rate: ExchangeRate) -> CommandResult<()>;
+
+    /// Generates a report of the transactions dated from the start date to
+    /// the end date, inclusive, with every amount converted into the base
+    /// currency as of its transaction's date.
+    async fn report(
+        &self,
+        base_currency: Currency,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> CommandResult<Report>;
 }
@@ -17,6 +17,9 @@ use thiserror::Error;
 /// Metadata associated with a database save file.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SaveMetadata {
+    /// The save's unique identifier. Saves in the saves directory are stored
+    /// in a file named after it, so that any name can be used.
+    pub id: String,
     /// The name of the save.
     pub name: String,
     /// A description of the save.
@@ -25,6 +28,171 @@ pub struct SaveMetadata {
     pub created_at: NaiveDateTime,
     /// When the save was last opened.
     pub last_opened_at: NaiveDateTime,
+    /// Whether the name and description are kept encrypted. Until the save
+    /// is unlocked, they are left empty.
+    pub encrypted: bool,
+}
+
+/// A save file that was recently opened or created.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentSave {
+    /// The path to the save file.
+    pub path: String,
+    /// When the save file was last opened or created.
+    pub opened_at: NaiveDateTime,
+    /// The save's metadata.
+    pub metadata: SaveMetadata,
+}
+
+/// The kind of secret that unlocks a save key slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeySlotKind {
+    /// A password chosen by the user.
+    Password,
+    /// A generated recovery phrase.
+    RecoveryPhrase,
+}
+
+/// One of the ways a save file can be unlocked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySlot {
+    /// The key slot's identifier, unique within the save.
+    pub id: u32,
+    /// The kind of secret that unlocks the key slot.
+    pub kind: KeySlotKind,
+}
+
+/// When an open save file is saved automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutosaveConfig {
+    /// How often to save unsaved changes, in seconds. Zero disables saving
+    /// on an interval.
+    pub interval_secs: u64,
+    /// How many changes can go unsaved before saving. Zero disables saving
+    /// after a number of changes.
+    pub max_unsaved_changes: u64,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 60,
+            max_unsaved_changes: 50,
+        }
+    }
+}
+
+/// When an open save file is locked after going unused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoLockConfig {
+    /// How long the save can go without any commands before it is locked, in
+    /// seconds. Zero disables locking automatically.
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for AutoLockConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: 300,
+        }
+    }
+}
+
+/// The state of automatic saving for the open save file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutosaveStatus {
+    /// When the save is saved automatically.
+    pub config: AutosaveConfig,
+    /// When the save file was last successfully written.
+    pub saved_at: NaiveDateTime,
+    /// The number of changes made since the save file was last written.
+    pub unsaved_changes: u64,
+}
+
+/// How many backups of a save file are kept, and for how long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupPolicy {
+    /// The most backups to keep. Zero disables backups.
+    pub max_backups: u64,
+    /// How long to keep backups for, in days. Zero keeps them regardless of
+    /// age. The most recent backup is always kept.
+    pub max_age_days: u64,
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        Self {
+            max_backups: 10,
+            max_age_days: 30,
+        }
+    }
+}
+
+/// A backup of a save file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveBackup {
+    /// The identifier of the save the backup was taken of.
+    pub save_id: String,
+    /// The name of the save the backup was taken of.
+    pub name: String,
+    /// The backup's identifier, unique within the save.
+    pub id: String,
+    /// When the backup was taken.
+    pub created_at: NaiveDateTime,
+}
+
+/// How a table in a save file differs from the same table in a backup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupComparison {
+    /// The name of the table.
+    pub table: String,
+    /// The number of rows in the save file.
+    pub rows: u64,
+    /// The number of rows in the backup.
+    pub backup_rows: u64,
+    /// The number of rows in the save file that are not in the backup,
+    /// either because they were added or changed since.
+    pub rows_not_in_backup: u64,
+    /// The number of rows in the backup that are not in the save file,
+    /// either because they were removed or changed since.
+    pub backup_rows_not_in_save: u64,
+}
+
+/// The kind of data left behind by a save that was not closed cleanly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeftoverKind {
+    /// A decrypted database, left behind by an older version of the
+    /// application that kept open saves on disk.
+    DecryptedDatabase,
+    /// A save file that was being written when the application stopped.
+    UnfinishedSave,
+}
+
+/// Data left behind by a save that was not closed cleanly, which may hold
+/// changes that never made it into the save file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeftoverSave {
+    /// The identifier of the save the data belongs to.
+    pub id: String,
+    /// The name of the save the data belongs to.
+    pub name: String,
+    /// The path to the save file the data belongs to, which may not exist
+    /// if the save was never finished being created.
+    pub path: String,
+    /// The kind of leftover data.
+    pub kind: LeftoverKind,
+    /// When the leftover data was last modified.
+    pub modified_at: NaiveDateTime,
+    /// When the matching save file was last modified, if there is one.
+    pub save_modified_at: Option<NaiveDateTime>,
+}
+
+impl LeftoverSave {
+    /// Checks whether the leftover data is newer than the save file.
+    pub fn is_newer(&self) -> bool {
+        self.save_modified_at
+            .is_none_or(|save_modified_at| self.modified_at > save_modified_at)
+    }
 }
 
 /// A generic error that only cares about the error message.
@@ -74,6 +242,16 @@ pub enum ExpectedCommandError {
     /// An attempt was made to open a save, but one was already open.
     #[error("A save file is already open")]
     SaveAlreadyOpen,
+    /// A save operation was attempted, but the open save was locked.
+    #[error("The save file is locked")]
+    SaveLocked,
+    /// An attempt was made to unlock a save, but none was locked.
+    #[error("No save file is locked")]
+    SaveNotLocked,
+    /// An attempt was made to open a save that is already open in another
+    /// instance of the application.
+    #[error("The save file is already open elsewhere")]
+    SaveOpenElsewhere,
     /// A save with the given name already exists.
     #[error("A save with the given name already exists")]
     SaveAlreadyExists,
@@ -83,6 +261,54 @@ pub enum ExpectedCommandError {
     /// The save file could not be unlocked with the provided password.
     #[error("The save file could not be unlocked with the provided password")]
     InvalidSavePassword,
+    /// Too many incorrect passwords were tried for the save file, so it
+    /// cannot be unlocked until the given number of seconds has passed.
+    #[error("Too many incorrect passwords were tried, try again in {remaining_secs} seconds")]
+    TooManyPasswordAttempts {
+        /// How many seconds remain until the save file can be unlocked.
+        remaining_secs: u64,
+    },
+    /// The save file's key derivation parameters are missing or invalid.
+    #[error("The save file's key derivation parameters are invalid")]
+    InvalidKeyDerivation,
+    /// The save file is corrupted or is not a save file at all.
+    #[error("The save file is corrupted or is not a valid save file")]
+    InvalidSaveFile,
+    /// The save file was written in a format newer than this version of the
+    /// application understands.
+    #[error("The save file was created by a newer version of the application")]
+    UnsupportedSaveVersion,
+    /// The save file's header does not match its authentication code, so it
+    /// has been tampered with or corrupted.
+    #[error("The save file's header has been tampered with or is corrupted")]
+    SaveHeaderTampered,
+    /// The specified key slot does not exist.
+    #[error("The specified key slot does not exist")]
+    KeySlotNotFound,
+    /// An attempt was made to remove the only remaining key slot.
+    #[error("A save file must keep at least one way to unlock it")]
+    LastKeySlot,
+    /// An attempt was made to add a key slot to a save that has no room for
+    /// more.
+    #[error("The save file cannot have any more ways to unlock it")]
+    TooManyKeySlots,
+    /// An attempt was made to change a save's password with its recovery
+    /// phrase, which would replace the recovery phrase.
+    #[error("The password cannot be changed using the recovery phrase")]
+    RecoveryPhraseNotPassword,
+    /// The specified backup does not exist.
+    #[error("The backup could not be found")]
+    BackupNotFound,
+    /// A change was attempted on a save file that was opened read-only.
+    #[error("The save file was opened read-only and cannot be changed")]
+    SaveReadOnly,
+    /// The specified leftover data no longer exists.
+    #[error("The leftover save data could not be found")]
+    LeftoverNotFound,
+    /// The leftover data is incomplete or corrupted, and cannot be
+    /// recovered.
+    #[error("The leftover save data is incomplete and cannot be recovered")]
+    LeftoverNotRecoverable,
     /// The specified subcategory is not within the specified category.
     #[error("The specified subcategory is not within the specified category")]
     InvalidSubcategory,
@@ -98,6 +324,45 @@ pub enum ExpectedCommandError {
     /// An invalid timeframe was specified.
     #[error("An invalid timeframe was specified")]
     InvalidTimeframe,
+    /// An amount of money could not be understood.
+    #[error("An invalid amount was specified")]
+    InvalidAmount,
+    /// An amount of money, or a sum of amounts, is too large to be
+    /// represented.
+    #[error("The amount is too large")]
+    AmountOutOfRange,
+    /// An invalid currency code was specified.
+    #[error("An invalid currency code was specified")]
+    InvalidCurrency,
+    /// A currency was specified whose minor unit is not a hundredth of its
+    /// major unit, which amounts of money cannot represent.
+    #[error("Only currencies with two decimal places are supported")]
+    UnsupportedCurrency,
+    /// An exchange rate was not positive, or was between a currency and
+    /// itself.
+    #[error("An invalid exchange rate was specified")]
+    InvalidExchangeRate,
+    /// Exchange rates could not be imported, as the given line of the CSV
+    /// data is not a valid rate.
+    #[error("The exchange rates could not be imported, line {line} is invalid")]
+    InvalidExchangeRateCsv {
+        /// The line number of the first invalid line, counting from 1.
+        line: usize,
+    },
+    /// An amount could not be converted between currencies, as there is no
+    /// exchange rate between them as of its date.
+    #[error("No exchange rate is known between the currencies as of the date")]
+    ExchangeRateNotFound,
+    /// The lines of a split transaction do not add up to its amount.
+    #[error("The split lines do not add up to the transaction amount")]
+    SplitLinesMismatch,
+    /// Transfers cannot be split into lines.
+    #[error("Transfers cannot be split")]
+    SplitTransfer,
+    /// A transfer is not between two different accounts in the same
+    /// currency.
+    #[error("Transfers must be between two different accounts in the same currency")]
+    InvalidTransfer,
 }
 
 /// An unexpected command error.
@@ -1,4 +1,4 @@
-use super::AccountType;
+use super::{AccountType, Currency};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +13,9 @@ pub struct Account {
     pub name: String,
     /// A description of the account.
     pub description: Option<String>,
+    /// The code of the currency the account holds money in. The account's
+    /// transactions are all in this currency.
+    pub currency: String,
     /// When the account was created.
     pub created_at: NaiveDateTime,
     /// When the account was last edited.
@@ -26,4 +29,9 @@ impl Account {
     pub fn get_account_type(&self) -> AccountType {
         AccountType::from_internal_name(&self.account_type).unwrap()
     }
+
+    /// Gets the currency the account holds money in.
+    pub fn get_currency(&self) -> Currency {
+        Currency::new(&self.currency).unwrap()
+    }
 }
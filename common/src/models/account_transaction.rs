@@ -1,4 +1,4 @@
-use super::TransactionType;
+use super::{Category, Institution, Money, SplitLine, Subcategory, Tag, TransactionType};
 use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
@@ -14,7 +14,7 @@ pub struct AccountTransaction {
     /// A description of the account.
     pub description: Option<String>,
     /// The monetary amount of the transaction.
-    pub amount: f64,
+    pub amount: Money,
     /// The type of transaction.
     pub transaction_type: String,
     /// The ID of the institution which the transaction is associated with.
@@ -46,3 +46,28 @@ impl AccountTransaction {
         self.transaction_date.date()
     }
 }
+
+/// A new account transaction, as it is given when creating the transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewAccountTransaction {
+    /// The name of the transaction.
+    pub name: String,
+    /// A description of the transaction.
+    pub description: String,
+    /// The monetary amount of the transaction.
+    pub amount: Money,
+    /// The type of transaction.
+    pub transaction_type: TransactionType,
+    /// The institution which the transaction is associated with.
+    pub institution: Institution,
+    /// The date of the transaction.
+    pub date: NaiveDate,
+    /// The category in which the transaction exists.
+    pub category: Category,
+    /// The subcategory in which the transaction exists.
+    pub subcategory: Option<Subcategory>,
+    /// The tags of the transaction.
+    pub tags: Vec<Tag>,
+    /// The lines the transaction is split into, if it is split.
+    pub splits: Vec<SplitLine>,
+}
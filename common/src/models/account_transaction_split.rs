@@ -0,0 +1,52 @@
+use super::{Category, Money, Subcategory, Tag};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// A representation of a line of a split transaction in the database. The
+/// lines of a split transaction add up to its amount, and each carries its
+/// own category, subcategory, memo and tags.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AccountTransactionSplit {
+    /// The split line's identifier.
+    pub id: String,
+    /// The ID of the account transaction the line is a part of.
+    pub account_transaction_id: String,
+    /// The monetary amount of the line.
+    pub amount: Money,
+    /// The ID of the category in which the line exists.
+    pub category_id: String,
+    /// The ID of the subcategory in which the line exists.
+    pub subcategory_id: Option<String>,
+    /// A memo describing the line.
+    pub memo: Option<String>,
+    /// When the split line was created.
+    pub created_at: NaiveDateTime,
+}
+
+/// A representation of a link between split transaction lines and tags in
+/// the database.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AccountTransactionSplitTag {
+    /// The ID of the split line.
+    pub account_transaction_split_id: String,
+    /// The ID of the tag.
+    pub tag_id: String,
+    /// When the split line tag was created.
+    pub created_at: NaiveDateTime,
+}
+
+/// A line of a split transaction, as it is given when splitting the
+/// transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitLine {
+    /// The monetary amount of the line.
+    pub amount: Money,
+    /// The category in which the line exists.
+    pub category: Category,
+    /// The subcategory in which the line exists.
+    pub subcategory: Option<Subcategory>,
+    /// A memo describing the line.
+    pub memo: String,
+    /// The tags of the line.
+    pub tags: Vec<Tag>,
+}
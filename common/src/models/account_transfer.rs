@@ -0,0 +1,17 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// A representation of a transfer between accounts in the database. A
+/// transfer links the debit taken from one account with the credit made to
+/// another, and edits to either side are made to both.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AccountTransfer {
+    /// The transfer's identifier.
+    pub id: String,
+    /// The ID of the transaction taking the money out of its account.
+    pub debit_transaction_id: String,
+    /// The ID of the transaction putting the money into its account.
+    pub credit_transaction_id: String,
+    /// When the transfer was created.
+    pub created_at: NaiveDateTime,
+}
@@ -1,4 +1,4 @@
-use super::Timeframe;
+use super::{Money, Timeframe};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
@@ -10,7 +10,7 @@ pub struct Budget {
     /// The note associated with the budget.
     pub note: Option<String>,
     /// The budget limit.
-    pub total_limit: f64,
+    pub total_limit: Money,
     /// The budget timeframe.
     pub timeframe: String,
     /// The time offset of the budget.
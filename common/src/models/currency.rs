@@ -0,0 +1,116 @@
+use crate::ExpectedCommandError as Error;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// The ISO 4217 currencies whose minor unit is not a hundredth of their
+/// major unit, such as `JPY`, which has none, and `KWD`, which has a
+/// thousandth.
+const UNSUPPORTED_CODES: &[&str] = &[
+    "BHD", "BIF", "CLF", "CLP", "DJF", "GNF", "IQD", "ISK", "JOD", "JPY", "KMF", "KRW", "KWD",
+    "LYD", "OMR", "PYG", "RWF", "TND", "UGX", "UYI", "UYW", "VND", "VUV", "XAF", "XOF", "XPF",
+];
+
+/// A currency, identified by its three letter ISO 4217 code, such as `USD`.
+/// Amounts of money always have [`Money::DECIMALS`] decimal places, so only
+/// currencies with that many are supported.
+///
+/// [`Money::DECIMALS`]: crate::Money::DECIMALS
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency(String);
+
+impl Currency {
+    /// The currency given to accounts from before accounts had currencies.
+    pub const DEFAULT_CODE: &'static str = "USD";
+
+    /// Creates a currency from its code, ignoring case. This can fail if the
+    /// code is not three letters long, or if the currency does not have two
+    /// decimal places.
+    pub fn new(code: &str) -> Result<Self, Error> {
+        let code = code.trim();
+
+        if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+            Err(Error::InvalidCurrency)?;
+        }
+
+        let code = code.to_ascii_uppercase();
+
+        if UNSUPPORTED_CODES.contains(&code.as_str()) {
+            Err(Error::UnsupportedCurrency)?;
+        }
+
+        Ok(Self(code))
+    }
+
+    /// Gets the currency's code.
+    pub fn code(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self(Self::DEFAULT_CODE.to_owned())
+    }
+}
+
+impl Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Currency {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = Error;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        Self::new(&code)
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> Self {
+        currency.0
+    }
+}
+
+/// Currency tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_currency() {
+        // Parse currencies
+        assert_eq!(Currency::new("USD").unwrap().code(), "USD");
+        assert_eq!(Currency::new(" eur ").unwrap().code(), "EUR");
+        assert_eq!("gbp".parse::<Currency>().unwrap().to_string(), "GBP");
+        assert_eq!(Currency::new(""), Err(Error::InvalidCurrency));
+        assert_eq!(Currency::new("US"), Err(Error::InvalidCurrency));
+        assert_eq!(Currency::new("USDT"), Err(Error::InvalidCurrency));
+        assert_eq!(Currency::new("U$D"), Err(Error::InvalidCurrency));
+        assert_eq!(Currency::new("ÜSD"), Err(Error::InvalidCurrency));
+        assert_eq!(Currency::default().code(), Currency::DEFAULT_CODE);
+
+        // Only currencies with two decimal places are supported
+        assert_eq!(Currency::new("jpy"), Err(Error::UnsupportedCurrency));
+        assert_eq!(Currency::new("KWD"), Err(Error::UnsupportedCurrency));
+        assert_eq!(Currency::new("CLF"), Err(Error::UnsupportedCurrency));
+
+        // Currencies are validated when deserialized
+        let currency = serde_json::from_str::<Currency>("\"chf\"").unwrap();
+        assert_eq!(currency.code(), "CHF");
+        assert_eq!(serde_json::to_string(&currency).unwrap(), "\"CHF\"");
+        assert!(serde_json::from_str::<Currency>("\"yens\"").is_err());
+        assert!(serde_json::from_str::<Currency>("\"JPY\"").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use super::money::{format_decimal, parse_decimal};
+use super::{Currency, Money};
+use crate::ExpectedCommandError as Error;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// An exact exchange rate, the amount of one currency that a single unit of
+/// another is worth, kept as a whole number of hundred-millionths.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Rate(i64);
+
+impl Rate {
+    /// The number of digits after the decimal point.
+    pub const DECIMALS: u16 = 8;
+
+    /// The number of units in a rate of one.
+    pub const SCALE: i64 = 10i64.pow(Self::DECIMALS as u32);
+
+    /// Creates a rate from a whole number of hundred-millionths. This can
+    /// fail if the rate is not positive.
+    pub fn from_units(units: i64) -> Result<Self, Error> {
+        if units <= 0 {
+            Err(Error::InvalidExchangeRate)?;
+        }
+
+        Ok(Self(units))
+    }
+
+    /// Gets the number of hundred-millionths in the rate.
+    pub const fn units(self) -> i64 {
+        self.0
+    }
+
+    /// Converts an amount at this rate, rounding to the nearest minor unit,
+    /// if the result can be represented.
+    pub fn convert(self, amount: Money) -> Option<Money> {
+        round_div(
+            amount.minor_units() as i128 * self.0 as i128,
+            Self::SCALE as i128,
+        )
+    }
+
+    /// Converts an amount back at this rate, as if by the inverse rate,
+    /// rounding to the nearest minor unit, if the result can be represented.
+    pub fn convert_inverse(self, amount: Money) -> Option<Money> {
+        round_div(
+            amount.minor_units() as i128 * Self::SCALE as i128,
+            self.0 as i128,
+        )
+    }
+}
+
+/// Divides one number by another, rounding half away from zero, if the
+/// quotient can be represented as an amount of money. The divisor must be
+/// positive.
+fn round_div(dividend: i128, divisor: i128) -> Option<Money> {
+    let half = divisor / 2;
+    let rounded = if dividend < 0 {
+        (dividend - half) / divisor
+    } else {
+        (dividend + half) / divisor
+    };
+
+    i64::try_from(rounded).ok().map(Money::from_minor_units)
+}
+
+impl From<i64> for Rate {
+    /// Creates a rate from a whole number of hundred-millionths, as rates
+    /// are stored in the database.
+    fn from(units: i64) -> Self {
+        Self(units)
+    }
+}
+
+impl Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rate_str = format_decimal(self.0, Self::DECIMALS);
+        let rate_str = rate_str.trim_end_matches('0').trim_end_matches('.');
+
+        f.write_str(rate_str)
+    }
+}
+
+impl FromStr for Rate {
+    type Err = Error;
+
+    /// Parses a positive decimal rate with up to eight digits after the
+    /// decimal point, such as `1.0825`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let units = parse_decimal(s, Self::DECIMALS).map_err(|_| Error::InvalidExchangeRate)?;
+
+        Self::from_units(units)
+    }
+}
+
+/// A representation of an exchange rate in the database. One unit of the
+/// `from` currency is worth `rate` units of the `to` currency, from the rate
+/// date until the date of the next rate between the two.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    /// The code of the currency being converted from.
+    pub from_currency: String,
+    /// The code of the currency being converted to.
+    pub to_currency: String,
+    /// The date the rate applies from.
+    pub rate_date: NaiveDate,
+    /// The exchange rate.
+    pub rate: Rate,
+    /// When the exchange rate was recorded.
+    pub created_at: NaiveDateTime,
+}
+
+impl ExchangeRate {
+    /// Gets the currency being converted from.
+    pub fn get_from_currency(&self) -> Currency {
+        Currency::new(&self.from_currency).unwrap()
+    }
+
+    /// Gets the currency being converted to.
+    pub fn get_to_currency(&self) -> Currency {
+        Currency::new(&self.to_currency).unwrap()
+    }
+}
+
+/// Exchange rate tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate() {
+        // Parse rates
+        assert_eq!("1.0825".parse::<Rate>().unwrap().units(), 108_250_000);
+        assert_eq!("0.00000001".parse::<Rate>().unwrap().units(), 1);
+        assert_eq!("150".parse::<Rate>().unwrap().units(), 15_000_000_000);
+        assert_eq!("0".parse::<Rate>(), Err(Error::InvalidExchangeRate));
+        assert_eq!("-1.5".parse::<Rate>(), Err(Error::InvalidExchangeRate));
+        assert_eq!(
+            "0.000000001".parse::<Rate>(),
+            Err(Error::InvalidExchangeRate)
+        );
+        assert_eq!("abc".parse::<Rate>(), Err(Error::InvalidExchangeRate));
+        assert_eq!(Rate::from_units(0), Err(Error::InvalidExchangeRate));
+
+        // Display rates
+        assert_eq!(Rate::from(108_250_000).to_string(), "1.0825");
+        assert_eq!(Rate::from(Rate::SCALE * 3).to_string(), "3");
+        assert_eq!(Rate::from(1).to_string(), "0.00000001");
+
+        // Convert amounts, rounding half away from zero
+        let rate = "1.0825".parse::<Rate>().unwrap();
+        let amount = Money::from_minor_units(1000);
+        assert_eq!(rate.convert(amount), Some(Money::from_minor_units(1083)));
+        assert_eq!(
+            rate.convert(Money::from_minor_units(-1000)),
+            Some(Money::from_minor_units(-1083))
+        );
+        assert_eq!(
+            rate.convert_inverse(amount),
+            Some(Money::from_minor_units(924))
+        );
+        let rate = "0.5".parse::<Rate>().unwrap();
+        assert_eq!(
+            rate.convert(Money::from_minor_units(1)),
+            Some(Money::from_minor_units(1))
+        );
+        assert_eq!(
+            rate.convert(Money::from_minor_units(-1)),
+            Some(Money::from_minor_units(-1))
+        );
+        assert_eq!(
+            rate.convert(Money::MAX),
+            Some(Money::from_minor_units(i64::MAX / 2 + 1))
+        );
+        assert_eq!(rate.convert_inverse(Money::MAX), None);
+        assert_eq!("2".parse::<Rate>().unwrap().convert(Money::MIN), None);
+    }
+}
@@ -1,11 +1,17 @@
 mod account;
 mod account_transaction;
+mod account_transaction_split;
 mod account_transaction_tag;
+mod account_transfer;
 mod account_type;
 mod budget;
 mod category;
+mod currency;
+mod exchange_rate;
 mod institution;
+mod money;
 mod reminder;
+mod report;
 mod report_template;
 mod subcategory;
 mod tag;
@@ -14,12 +20,18 @@ mod transaction_type;
 
 pub use account::*;
 pub use account_transaction::*;
+pub use account_transaction_split::*;
 pub use account_transaction_tag::*;
+pub use account_transfer::*;
 pub use account_type::*;
 pub use budget::*;
 pub use category::*;
+pub use currency::*;
+pub use exchange_rate::*;
 pub use institution::*;
+pub use money::*;
 pub use reminder::*;
+pub use report::*;
 pub use report_template::*;
 pub use subcategory::*;
 pub use tag::*;
@@ -0,0 +1,251 @@
+use crate::ExpectedCommandError as Error;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// An exact amount of money, kept as a whole number of minor units, such as
+/// cents, so that sums never drift the way floating point amounts do. All
+/// arithmetic is checked, giving nothing rather than overflowing.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Money(i64);
+
+impl Money {
+    /// The number of digits after the decimal point. This is the same for
+    /// every currency, so currencies with a different number are rejected by
+    /// [`Currency::new`].
+    ///
+    /// [`Currency::new`]: crate::Currency::new
+    pub const DECIMALS: u16 = 2;
+
+    /// The number of minor units in one major unit.
+    pub const MINOR_UNITS: i64 = 10i64.pow(Self::DECIMALS as u32);
+
+    /// No money at all.
+    pub const ZERO: Self = Self(0);
+
+    /// The smallest amount of money that can be represented.
+    pub const MIN: Self = Self(i64::MIN);
+
+    /// The largest amount of money that can be represented.
+    pub const MAX: Self = Self(i64::MAX);
+
+    /// Creates an amount from a number of minor units.
+    pub const fn from_minor_units(minor_units: i64) -> Self {
+        Self(minor_units)
+    }
+
+    /// Creates an amount from a number of major units, if it can be
+    /// represented.
+    pub fn from_major_units(major_units: i64) -> Option<Self> {
+        major_units.checked_mul(Self::MINOR_UNITS).map(Self)
+    }
+
+    /// Gets the number of minor units in the amount.
+    pub const fn minor_units(self) -> i64 {
+        self.0
+    }
+
+    /// Gets the amount as a floating point number of major units. This is
+    /// only suitable for display, such as sizing charts, as it can lose
+    /// precision.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::MINOR_UNITS as f64
+    }
+
+    /// Checks whether the amount is zero.
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Checks whether the amount is less than zero.
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    /// Adds two amounts, if the sum can be represented.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    /// Subtracts an amount from this one, if the difference can be
+    /// represented.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    /// Multiplies the amount by a whole number, if the product can be
+    /// represented.
+    pub fn checked_mul(self, factor: i64) -> Option<Self> {
+        self.0.checked_mul(factor).map(Self)
+    }
+
+    /// Negates the amount, if the negation can be represented.
+    pub fn checked_neg(self) -> Option<Self> {
+        self.0.checked_neg().map(Self)
+    }
+
+    /// Gets the absolute amount, if it can be represented.
+    pub fn checked_abs(self) -> Option<Self> {
+        self.0.checked_abs().map(Self)
+    }
+
+    /// Sums any number of amounts, if the sum can be represented.
+    pub fn checked_sum<I>(amounts: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        amounts
+            .into_iter()
+            .try_fold(Self::ZERO, |sum, amount| sum.checked_add(amount))
+    }
+}
+
+impl From<i64> for Money {
+    /// Creates an amount from a number of minor units, as amounts are
+    /// stored in the database.
+    fn from(minor_units: i64) -> Self {
+        Self(minor_units)
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_decimal(self.0, Self::DECIMALS))
+    }
+}
+
+impl FromStr for Money {
+    type Err = Error;
+
+    /// Parses a decimal number of major units, such as `-12.34`. Amounts
+    /// with more digits after the decimal point than minor units allow are
+    /// refused rather than rounded.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_decimal(s, Self::DECIMALS).map(Self)
+    }
+}
+
+/// Formats a whole number of units as a decimal number with the given
+/// number of digits after the decimal point.
+pub(crate) fn format_decimal(units: i64, decimals: u16) -> String {
+    let sign = if units < 0 { "-" } else { "" };
+    let units = units.unsigned_abs();
+    let scale = 10u64.pow(decimals as u32);
+
+    format!(
+        "{sign}{}.{:0width$}",
+        units / scale,
+        units % scale,
+        width = decimals as usize
+    )
+}
+
+/// Parses a decimal number, such as `-12.34`, into a whole number of units
+/// with the given number of digits after the decimal point. Numbers with
+/// more digits after the decimal point are refused rather than rounded.
+pub(crate) fn parse_decimal(s: &str, decimals: u16) -> Result<i64, Error> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, s),
+    };
+    let (whole_str, fraction_str) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (whole_str.is_empty() && fraction_str.is_empty())
+        || !is_digits(whole_str)
+        || !is_digits(fraction_str)
+        || fraction_str.len() > decimals as usize
+    {
+        Err(Error::InvalidAmount)?;
+    }
+
+    let whole = match whole_str {
+        "" => 0,
+        _ => whole_str
+            .parse::<i64>()
+            .map_err(|_| Error::AmountOutOfRange)?,
+    };
+    let fraction = match decimals {
+        0 => 0,
+        _ => format!("{fraction_str:0<width$}", width = decimals as usize)
+            .parse::<i64>()
+            .map_err(|_| Error::InvalidAmount)?,
+    };
+
+    let units = whole
+        .checked_mul(10i64.pow(decimals as u32))
+        .and_then(|whole| whole.checked_add(fraction))
+        .ok_or(Error::AmountOutOfRange)?;
+
+    Ok(if negative { -units } else { units })
+}
+
+/// Money tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_money() {
+        // Sums of cents do not drift
+        let ten_cents = "0.1".parse::<Money>().unwrap();
+        let twenty_cents = "0.2".parse::<Money>().unwrap();
+        let sum = ten_cents.checked_add(twenty_cents).unwrap();
+        assert_eq!(sum, Money::from_minor_units(30));
+        assert_eq!(sum, "0.30".parse().unwrap());
+
+        // Parse amounts
+        assert_eq!("12.34".parse::<Money>().unwrap().minor_units(), 1234);
+        assert_eq!("-12.3".parse::<Money>().unwrap().minor_units(), -1230);
+        assert_eq!("12".parse::<Money>().unwrap().minor_units(), 1200);
+        assert_eq!(".5".parse::<Money>().unwrap().minor_units(), 50);
+        assert_eq!("-0.05".parse::<Money>().unwrap().minor_units(), -5);
+        assert_eq!("".parse::<Money>(), Err(Error::InvalidAmount));
+        assert_eq!(".".parse::<Money>(), Err(Error::InvalidAmount));
+        assert_eq!("1.005".parse::<Money>(), Err(Error::InvalidAmount));
+        assert_eq!("1.2.3".parse::<Money>(), Err(Error::InvalidAmount));
+        assert_eq!("+1".parse::<Money>(), Err(Error::InvalidAmount));
+        assert_eq!("1e3".parse::<Money>(), Err(Error::InvalidAmount));
+        assert_eq!(
+            "99999999999999999999".parse::<Money>(),
+            Err(Error::AmountOutOfRange)
+        );
+
+        // Display amounts
+        assert_eq!(Money::from_minor_units(1234).to_string(), "12.34");
+        assert_eq!(Money::from_minor_units(-5).to_string(), "-0.05");
+        assert_eq!(Money::ZERO.to_string(), "0.00");
+        assert_eq!(Money::MIN.to_string(), "-92233720368547758.08");
+        for amount in [
+            Money::MAX,
+            Money::from_minor_units(-1),
+            Money::from_minor_units(100),
+        ] {
+            assert_eq!(amount.to_string().parse::<Money>().unwrap(), amount);
+        }
+
+        // Arithmetic is checked
+        assert_eq!(Money::MAX.checked_add(Money::from_minor_units(1)), None);
+        assert_eq!(Money::MIN.checked_sub(Money::from_minor_units(1)), None);
+        assert_eq!(Money::MIN.checked_neg(), None);
+        assert_eq!(Money::MIN.checked_abs(), None);
+        assert_eq!(Money::MAX.checked_mul(2), None);
+        assert_eq!(
+            Money::from_minor_units(-250).checked_abs(),
+            Some(Money::from_minor_units(250))
+        );
+        assert_eq!(
+            Money::from_major_units(3),
+            Some(Money::from_minor_units(300))
+        );
+        assert_eq!(Money::from_major_units(i64::MAX), None);
+        assert_eq!(
+            Money::checked_sum([ten_cents, twenty_cents, Money::from_minor_units(-30)]),
+            Some(Money::ZERO)
+        );
+        assert_eq!(Money::checked_sum([Money::MAX, Money::MAX]), None);
+    }
+}
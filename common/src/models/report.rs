@@ -0,0 +1,71 @@
+use super::{AccountTransaction, AccountTransactionSplit, Money};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// A transaction as it appears in a report. The transaction keeps its amount
+/// in its account's currency, alongside the amount converted into the
+/// report's base currency.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct ReportTransaction {
+    /// The transaction.
+    pub transaction: AccountTransaction,
+    /// The code of the currency of the transaction's account.
+    pub currency: String,
+    /// The transaction amount converted into the report's base currency, as
+    /// of the transaction's date. For split transactions, this is the total
+    /// of the converted lines.
+    pub base_amount: Money,
+    /// The lines the transaction is split into, if any.
+    pub splits: Vec<ReportSplit>,
+}
+
+/// A line of a split transaction as it appears in a report.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ReportSplit {
+    /// The split line.
+    pub split: AccountTransactionSplit,
+    /// The line amount converted into the report's base currency, as of the
+    /// transaction's date.
+    pub base_amount: Money,
+}
+
+/// The totals of a single category within a report, in the report's base
+/// currency.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ReportCategory {
+    /// The ID of the category.
+    pub category_id: String,
+    /// The total of the category's credit transactions and split lines.
+    pub income: Money,
+    /// The total of the category's debit transactions and split lines.
+    pub expenses: Money,
+}
+
+/// A report of the transactions within a range of dates, across all
+/// accounts. Every amount is converted into the report's base currency as of
+/// the date of its transaction. Split transactions count towards the
+/// categories of their lines rather than their own.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Report {
+    /// The code of the currency the report's totals are in.
+    pub base_currency: String,
+    /// The first date included in the report.
+    pub start_date: NaiveDate,
+    /// The last date included in the report.
+    pub end_date: NaiveDate,
+    /// The transactions within the report, in date order.
+    pub transactions: Vec<ReportTransaction>,
+    /// The total of all credit transactions.
+    pub income: Money,
+    /// The total of all debit transactions.
+    pub expenses: Money,
+    /// The totals of each category with transactions in the report.
+    pub categories: Vec<ReportCategory>,
+}
+
+impl Report {
+    /// Gets the income left over after expenses, if it can be represented.
+    pub fn net(&self) -> Option<Money> {
+        self.income.checked_sub(self.expenses)
+    }
+}
@@ -3,12 +3,20 @@
 #![forbid(unsafe_code)]
 #![deny(missing_docs)]
 
-use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
 use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
 use backend_common::*;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::digest::generic_array::GenericArray;
 use sha2::{Digest, Sha256};
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use std::fmt;
+use std::ops::Deref;
+use tokio::io::{
+    self, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt,
+};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 /// The number of bytes to use for an AES key.
 pub const AES_KEY_SIZE: usize = 32;
@@ -16,14 +24,156 @@ pub const AES_KEY_SIZE: usize = 32;
 /// The number of bytes to use for an AES nonce.
 pub const AES_NONCE_SIZE: usize = 12;
 
-/// Fixed bcrypt hash cost.
+/// The number of bytes in an AES authentication tag.
+pub const AES_TAG_SIZE: usize = 16;
+
+/// The number of bytes in a wrapped key, including its nonce and
+/// authentication tag.
+pub const WRAPPED_KEY_SIZE: usize = AES_NONCE_SIZE + AES_KEY_SIZE + AES_TAG_SIZE;
+
+/// The number of bytes in an HMAC-SHA256 authentication code.
+pub const HMAC_SIZE: usize = 32;
+
+/// The number of bytes to use for the random identifier at the start of each
+/// encrypted stream.
+pub const STREAM_ID_SIZE: usize = 16;
+
+/// The characters used in recovery phrases. This is Crockford's base 32
+/// alphabet, which leaves out letters that are easily confused.
+const RECOVERY_PHRASE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// The number of characters in each group of a recovery phrase.
+const RECOVERY_PHRASE_GROUP_SIZE: usize = 5;
+
+/// The number of groups in a recovery phrase.
+const RECOVERY_PHRASE_GROUPS: usize = 5;
+
+/// The number of bytes to use for a key derivation salt.
+pub const KDF_SALT_SIZE: usize = 16;
+
+/// The default Argon2id memory cost, in KiB.
+pub const DEFAULT_ARGON2_MEMORY_COST: u32 = 64 * 1024;
+
+/// The default number of Argon2id iterations.
+pub const DEFAULT_ARGON2_TIME_COST: u32 = 3;
+
+/// The default degree of Argon2id parallelism.
+pub const DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+
+/// Fixed bcrypt hash cost, used only by the legacy key derivation scheme.
 const FIXED_PASSWORD_COST: u32 = 12;
 
-/// Fixed bcrypt hash salt.
+/// Fixed bcrypt hash salt, used only by the legacy key derivation scheme.
 const FIXED_PASSWORD_SALT: [u8; 16] = [
     108, 101, 118, 101, 108, 101, 118, 101, 108, 101, 118, 101, 108, 101, 118, 101,
 ];
 
+/// A key, wiped from memory when it is dropped. It dereferences to its bytes
+/// so that it can be passed wherever a key is expected, but its bytes are
+/// never shown when it is debug formatted.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey([u8; AES_KEY_SIZE]);
+
+impl SecretKey {
+    /// Creates a key from its bytes.
+    pub fn new(key: [u8; AES_KEY_SIZE]) -> Self {
+        Self(key)
+    }
+}
+
+impl Deref for SecretKey {
+    type Target = [u8; AES_KEY_SIZE];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey([REDACTED])")
+    }
+}
+
+/// A password or recovery phrase, wiped from memory when it is dropped. Its
+/// contents are never shown when it is debug formatted.
+#[derive(Clone, Default, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Creates a secret from a string, taking ownership of it so that it is
+    /// wiped without being copied first.
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self::new(secret.to_owned())
+    }
+}
+
+impl Deref for SecretString {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+/// The algorithm and parameters used to derive an encryption key from a
+/// password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfParams {
+    /// bcrypt with a fixed cost and salt, followed by SHA-256. This exists
+    /// only so that saves created before per-save salts were introduced can
+    /// still be opened.
+    LegacyBcrypt,
+    /// Argon2id with a random per-save salt.
+    Argon2id {
+        /// The random salt.
+        salt: [u8; KDF_SALT_SIZE],
+        /// The memory cost, in KiB.
+        memory_cost: u32,
+        /// The number of iterations.
+        time_cost: u32,
+        /// The degree of parallelism.
+        parallelism: u32,
+    },
+}
+
+impl KdfParams {
+    /// Generates new Argon2id parameters with a random salt and the default
+    /// costs.
+    pub fn generate() -> Self {
+        Self::Argon2id {
+            salt: rand::random(),
+            memory_cost: DEFAULT_ARGON2_MEMORY_COST,
+            time_cost: DEFAULT_ARGON2_TIME_COST,
+            parallelism: DEFAULT_ARGON2_PARALLELISM,
+        }
+    }
+
+    /// Checks whether these parameters use the legacy key derivation scheme,
+    /// meaning anything encrypted with the derived key should be upgraded.
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, Self::LegacyBcrypt)
+    }
+}
+
 /// Encrypts data with AES.
 pub fn aes_encrypt(key: &[u8; AES_KEY_SIZE], plaintext: &[u8]) -> Result<Vec<u8>> {
     let cipher = Aes256Gcm::new_from_slice(key).unwrap();
@@ -39,6 +189,10 @@ pub fn aes_encrypt(key: &[u8; AES_KEY_SIZE], plaintext: &[u8]) -> Result<Vec<u8>
 
 /// Decrypts data with AES.
 pub fn aes_decrypt(key: &[u8; AES_KEY_SIZE], ciphertext_with_nonce: &[u8]) -> Result<Vec<u8>> {
+    if ciphertext_with_nonce.len() < AES_NONCE_SIZE {
+        Err(aes_gcm::Error)?;
+    }
+
     let cipher = Aes256Gcm::new_from_slice(key).unwrap();
     let (nonce_slice, ciphertext) = ciphertext_with_nonce.split_at(AES_NONCE_SIZE);
     let nonce_slice_sized: [u8; AES_NONCE_SIZE] =
@@ -49,29 +203,313 @@ pub fn aes_decrypt(key: &[u8; AES_KEY_SIZE], ciphertext_with_nonce: &[u8]) -> Re
     Ok(plaintext)
 }
 
-/// Encrypts a file in chunks.
-pub async fn encrypt_file(src: &mut File, dest: &mut File, key: &[u8; AES_KEY_SIZE]) -> Result<()> {
-    let mut buffer = [0u8; READER_CAPACITY];
+/// Generates a random data encryption key.
+pub fn generate_key() -> SecretKey {
+    SecretKey(rand::random())
+}
 
-    loop {
-        let n = src.read(&mut buffer).await?;
+/// Encrypts a data encryption key with a key encryption key, so that it can
+/// be stored alongside the data it protects.
+pub fn wrap_key(
+    key_encryption_key: &[u8; AES_KEY_SIZE],
+    key: &[u8; AES_KEY_SIZE],
+) -> Result<[u8; WRAPPED_KEY_SIZE]> {
+    let wrapped_key = aes_encrypt(key_encryption_key, key)?;
+
+    Ok(wrapped_key.try_into().unwrap())
+}
+
+/// Decrypts a data encryption key that was wrapped with [`wrap_key`]. This
+/// fails if the key encryption key is wrong.
+pub fn unwrap_key(
+    key_encryption_key: &[u8; AES_KEY_SIZE],
+    wrapped_key: &[u8; WRAPPED_KEY_SIZE],
+) -> Result<SecretKey> {
+    let key = Zeroizing::new(aes_decrypt(key_encryption_key, wrapped_key)?);
+
+    Ok(SecretKey(
+        key.as_slice().try_into().map_err(|_| aes_gcm::Error)?,
+    ))
+}
+
+/// Derives a subkey from a key for a specific purpose, so that the same key
+/// is never used for two different things. The context must be unique to
+/// each purpose.
+pub fn derive_subkey(key: &[u8; AES_KEY_SIZE], context: &[u8]) -> SecretKey {
+    let mut subkey = SecretKey([0u8; AES_KEY_SIZE]);
+    Hkdf::<Sha256>::new(None, key)
+        .expand(context, &mut subkey.0)
+        .unwrap();
+    subkey
+}
+
+/// Computes an HMAC-SHA256 authentication code for data.
+pub fn hmac_sha256(key: &[u8; AES_KEY_SIZE], data: &[u8]) -> [u8; HMAC_SIZE] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Checks an HMAC-SHA256 authentication code for data, in constant time.
+pub fn verify_hmac_sha256(key: &[u8; AES_KEY_SIZE], data: &[u8], tag: &[u8]) -> bool {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.verify_slice(tag).is_ok()
+}
+
+/// Generates a random recovery phrase, made up of dash-separated groups of
+/// characters. This carries 125 bits of randomness.
+pub fn generate_recovery_phrase() -> String {
+    let groups = (0..RECOVERY_PHRASE_GROUPS)
+        .map(|_| {
+            (0..RECOVERY_PHRASE_GROUP_SIZE)
+                .map(|_| {
+                    let index = rand::random::<usize>() % RECOVERY_PHRASE_ALPHABET.len();
+                    char::from(RECOVERY_PHRASE_ALPHABET[index])
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+
+    groups.join("-")
+}
+
+/// Normalizes a recovery phrase as typed by a user, ignoring case, spacing
+/// and dashes, and correcting characters commonly mistaken for others.
+/// Returns `None` if the input cannot be a recovery phrase.
+pub fn normalize_recovery_phrase(phrase: &str) -> Option<String> {
+    let chars = phrase
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            c => c,
+        })
+        .collect::<Vec<_>>();
+
+    if chars.len() != RECOVERY_PHRASE_GROUPS * RECOVERY_PHRASE_GROUP_SIZE
+        || !chars
+            .iter()
+            .all(|c| c.is_ascii() && RECOVERY_PHRASE_ALPHABET.contains(&(*c as u8)))
+    {
+        return None;
+    }
+
+    let groups = chars
+        .chunks(RECOVERY_PHRASE_GROUP_SIZE)
+        .map(|group| group.iter().collect::<String>())
+        .collect::<Vec<_>>();
+
+    Some(groups.join("-"))
+}
+
+/// Builds the associated data binding a chunk to its stream and position.
+fn stream_chunk_aad(stream_id: &[u8; STREAM_ID_SIZE], index: u64, is_final: bool) -> Vec<u8> {
+    let mut aad = stream_id.to_vec();
+    aad.extend(index.to_be_bytes());
+    aad.push(u8::from(is_final));
+    aad
+}
+
+/// Encrypts a chunk of a stream with AES. The chunk is bound to the stream,
+/// its position within it, and whether it is the final chunk.
+fn aes_encrypt_chunk(
+    key: &[u8; AES_KEY_SIZE],
+    stream_id: &[u8; STREAM_ID_SIZE],
+    index: u64,
+    is_final: bool,
+    plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(key).unwrap();
+    let nonce_slice: [u8; AES_NONCE_SIZE] = rand::random();
+    let nonce = Nonce::from(nonce_slice);
+    let aad = stream_chunk_aad(stream_id, index, is_final);
+    let ciphertext = cipher.encrypt(
+        &nonce,
+        Payload {
+            msg: plaintext,
+            aad: &aad,
+        },
+    )?;
+
+    let mut ciphertext_with_nonce = nonce_slice.to_vec();
+    ciphertext_with_nonce.extend(ciphertext);
+
+    Ok(ciphertext_with_nonce)
+}
+
+/// Decrypts a chunk of a stream with AES, failing if the chunk does not
+/// belong at the given position in the stream.
+fn aes_decrypt_chunk(
+    key: &[u8; AES_KEY_SIZE],
+    stream_id: &[u8; STREAM_ID_SIZE],
+    index: u64,
+    is_final: bool,
+    ciphertext_with_nonce: &[u8],
+) -> Result<Vec<u8>> {
+    if ciphertext_with_nonce.len() < AES_NONCE_SIZE {
+        Err(aes_gcm::Error)?;
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(key).unwrap();
+    let (nonce_slice, ciphertext) = ciphertext_with_nonce.split_at(AES_NONCE_SIZE);
+    let nonce_slice_sized: [u8; AES_NONCE_SIZE] =
+        nonce_slice.try_into().map_err(|_| aes_gcm::Error)?;
+    let nonce = Nonce::from(nonce_slice_sized);
+    let aad = stream_chunk_aad(stream_id, index, is_final);
+    let plaintext = cipher.decrypt(
+        &nonce,
+        Payload {
+            msg: ciphertext,
+            aad: &aad,
+        },
+    )?;
+
+    Ok(plaintext)
+}
+
+/// Reads as much as possible into a buffer, stopping early only at the end
+/// of the file.
+async fn read_full<R>(src: &mut R, buffer: &mut [u8]) -> Result<usize>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    let mut total = 0;
+
+    while total < buffer.len() {
+        let n = src.read(&mut buffer[total..]).await?;
 
         if n == 0 {
             break;
         }
 
-        let encrypted_data = aes_encrypt(key, &buffer[..n])?;
+        total += n;
+    }
+
+    Ok(total)
+}
+
+/// Encrypts a file as a stream of chunks. The stream starts with a random
+/// identifier, and every chunk is bound to that identifier, its index, and
+/// whether it is the last chunk, so chunks cannot be reordered, duplicated,
+/// dropped, or spliced in from another stream. At least one chunk is always
+/// written, so that an empty file can still be told apart from a truncated
+/// one.
+pub async fn encrypt_file<R, W>(src: &mut R, dest: &mut W, key: &[u8; AES_KEY_SIZE]) -> Result<()>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + AsyncSeek + Unpin + ?Sized,
+{
+    let stream_id: [u8; STREAM_ID_SIZE] = rand::random();
+    write_section(dest, &stream_id).await?;
+
+    let mut buffer = vec![0u8; READER_CAPACITY];
+    let mut next_buffer = vec![0u8; READER_CAPACITY];
+    let mut n = read_full(src, &mut buffer).await?;
+    let mut index = 0u64;
+
+    loop {
+        let next_n = if n == READER_CAPACITY {
+            read_full(src, &mut next_buffer).await?
+        } else {
+            0
+        };
+        let is_final = next_n == 0;
+
+        let encrypted_data = aes_encrypt_chunk(key, &stream_id, index, is_final, &buffer[..n])?;
         write_section(dest, &encrypted_data).await?;
+
+        if is_final {
+            break;
+        }
+
+        std::mem::swap(&mut buffer, &mut next_buffer);
+        n = next_n;
+        index += 1;
+    }
+
+    dest.rewind().await?;
+    dest.flush().await?;
+
+    Ok(())
+}
+
+/// Decrypts a file that was encrypted with [`encrypt_file`], writing the
+/// decrypted data to the destination. Fails if the stream has been tampered
+/// with in any way, including being truncated.
+async fn decrypt_stream<R, W>(src: &mut R, dest: &mut W, key: &[u8; AES_KEY_SIZE]) -> Result<()>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let stream_id: [u8; STREAM_ID_SIZE] = match read_section(src).await? {
+        Some(data) => data.try_into().map_err(|_| aes_gcm::Error)?,
+        None => Err(aes_gcm::Error)?,
+    };
+
+    let mut data = match read_section(src).await? {
+        Some(data) => data,
+        None => Err(aes_gcm::Error)?,
+    };
+    let mut index = 0u64;
+
+    loop {
+        let next_data = read_section(src).await?;
+        let is_final = next_data.is_none();
+
+        let decrypted_data = aes_decrypt_chunk(key, &stream_id, index, is_final, &data)?;
+
+        dest.write_all(&decrypted_data).await?;
+
+        match next_data {
+            Some(next_data) => data = next_data,
+            None => break,
+        }
+
+        index += 1;
     }
 
+    Ok(())
+}
+
+/// Decrypts a file that was encrypted with [`encrypt_file`].
+pub async fn decrypt_file<R, W>(src: &mut R, dest: &mut W, key: &[u8; AES_KEY_SIZE]) -> Result<()>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + AsyncSeek + Unpin + ?Sized,
+{
+    decrypt_stream(src, dest, key).await?;
+
     dest.rewind().await?;
     dest.flush().await?;
 
     Ok(())
 }
 
-/// Decrypts a file in chunks.
-pub async fn decrypt_file(src: &mut File, dest: &mut File, key: &[u8; AES_KEY_SIZE]) -> Result<()> {
+/// Attempts to decrypt a file that was encrypted with [`encrypt_file`],
+/// without writing the decrypted data anywhere. Useful for validating the
+/// crypto key.
+pub async fn try_decrypt_file<R>(src: &mut R, key: &[u8; AES_KEY_SIZE]) -> Result<()>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    decrypt_stream(src, &mut io::sink(), key).await
+}
+
+/// Decrypts a file encrypted in independent chunks, as was done before
+/// chunks were bound to their position in the stream. This offers no
+/// protection against chunks being reordered or dropped, and only exists to
+/// read old files.
+pub async fn legacy_decrypt_file<R, W>(
+    src: &mut R,
+    dest: &mut W,
+    key: &[u8; AES_KEY_SIZE],
+) -> Result<()>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + AsyncSeek + Unpin + ?Sized,
+{
     loop {
         let data = match read_section(src).await? {
             Some(data) => data,
@@ -89,8 +527,12 @@ pub async fn decrypt_file(src: &mut File, dest: &mut File, key: &[u8; AES_KEY_SI
     Ok(())
 }
 
-/// Attempts to decrypt a file in chunks, without writing the decrypted data anywhere. Useful for validating the crypto key.
-pub async fn try_decrypt_file(src: &mut File, key: &[u8; AES_KEY_SIZE]) -> Result<()> {
+/// Attempts to decrypt a file encrypted in independent chunks, without
+/// writing the decrypted data anywhere. See [`legacy_decrypt_file`].
+pub async fn legacy_try_decrypt_file<R>(src: &mut R, key: &[u8; AES_KEY_SIZE]) -> Result<()>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
     loop {
         let data = match read_section(src).await? {
             Some(data) => data,
@@ -106,47 +548,63 @@ pub async fn try_decrypt_file(src: &mut File, key: &[u8; AES_KEY_SIZE]) -> Resul
 /// This is the underlying synchronous implementation of [`password_to_key`].
 /// Never call this in an async context, as it relies very heavily on CPU
 /// operations.
-pub fn password_to_key_sync(password: &str) -> [u8; AES_KEY_SIZE] {
-    let bcrypt_hash = bcrypt::hash_with_salt(password, FIXED_PASSWORD_COST, FIXED_PASSWORD_SALT)
-        .unwrap()
-        .to_string();
-    let mut hasher = Sha256::new();
-    hasher.update(bcrypt_hash);
-    let result = hasher.finalize();
-    result.into()
+pub fn password_to_key_sync(password: &str, params: &KdfParams) -> Result<SecretKey> {
+    match params {
+        KdfParams::LegacyBcrypt => {
+            let bcrypt_hash = Zeroizing::new(
+                bcrypt::hash_with_salt(password, FIXED_PASSWORD_COST, FIXED_PASSWORD_SALT)
+                    .unwrap()
+                    .to_string(),
+            );
+            let mut hasher = Sha256::new();
+            hasher.update(bcrypt_hash.as_bytes());
+            let mut key = SecretKey([0u8; AES_KEY_SIZE]);
+            hasher.finalize_into(GenericArray::from_mut_slice(&mut key.0));
+            Ok(key)
+        }
+        KdfParams::Argon2id {
+            salt,
+            memory_cost,
+            time_cost,
+            parallelism,
+        } => {
+            let params = Params::new(*memory_cost, *time_cost, *parallelism, Some(AES_KEY_SIZE))?;
+            let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+            let mut key = SecretKey([0u8; AES_KEY_SIZE]);
+            argon2.hash_password_into(password.as_bytes(), salt, &mut key.0)?;
+            Ok(key)
+        }
+    }
 }
 
-/// Gets the AES-256 encryption/decryption key, given the password.
+/// Gets the AES-256 encryption/decryption key, given the password and the
+/// key derivation parameters stored alongside the encrypted data.
 ///
 /// ## Algorithm
 ///
-/// This function hashes the password with bcrypt, using a fixed cost and
-/// salt. This step is necessary to add a time cost. The fixed cost and salt
-/// are necessary to ensure the same hash is always generated, as a different
-/// hash means a different key used to encrypt/decrypt.
-///
-/// The bcrypt hash digest is then hashed again, this time with SHA-256. This
-/// is necessary to get a fixed-size output and additional randomness in the
-/// appearance of the encryption/decryption key.
+/// New keys are derived with Argon2id, a memory-hard function, using a salt
+/// that is randomly generated for each save. Because every save has its own
+/// salt, the same password produces a different key for every save, and an
+/// attacker cannot precompute keys for all saves at once. The memory and
+/// iteration costs are stored with the salt, so they can be raised over time
+/// without breaking existing saves.
 ///
-/// The resulting SHA-256 hash is the key that can be used for encryption and
-/// decryption.
+/// ## Legacy keys
 ///
-/// ## Backup codes
-///
-/// Because of how this algorithm works, the returned SHA-256 hash can
-/// function as a backup code. Because it will have been the hash that was
-/// originally used to encrypt the data, it can be saved as a backup and used
-/// to decrypt the data later in the event that the password is lost or
-/// forgotten.
+/// Saves created before per-save salts were introduced derive their key by
+/// hashing the password with bcrypt using a fixed cost and salt, then hashing
+/// the bcrypt digest with SHA-256 to get a fixed-size key. This scheme is
+/// still supported through [`KdfParams::LegacyBcrypt`] so those saves can be
+/// opened and upgraded, but it should never be used for new data.
 ///
 /// ## Async
 ///
 /// This algorithm relies very heavily on CPU operations. To get around this,
 /// the logic is wrapped in [`tokio::task::spawn_blocking`].
-pub async fn password_to_key(password: &str) -> [u8; AES_KEY_SIZE] {
-    let password = password.to_owned();
-    tokio::task::spawn_blocking(move || password_to_key_sync(&password))
+pub async fn password_to_key(password: &str, params: &KdfParams) -> Result<SecretKey> {
+    let password = SecretString::from(password);
+    let params = *params;
+    tokio::task::spawn_blocking(move || password_to_key_sync(&password, &params))
         .await
         .unwrap()
 }
@@ -192,12 +650,15 @@ mod tests {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(true)
             .open(path)
             .await
     }
 
     async fn encrypt_decrypt_file(data: &[u8], password: &str) -> (Vec<u8>, Vec<u8>) {
-        let key = password_to_key(password).await;
+        let key = password_to_key(password, &KdfParams::generate())
+            .await
+            .unwrap();
 
         let plaintext_path = random_save_path();
         let ciphertext_path = random_save_path();
@@ -254,12 +715,92 @@ mod tests {
     #[tokio::test]
     async fn test_aes() {
         let aes_message = "Hello, AES!";
-        let key = password_to_key("password123").await;
+        let key = password_to_key("password123", &KdfParams::generate())
+            .await
+            .unwrap();
         let aes_encrypted = aes_encrypt(&key, aes_message.as_bytes()).unwrap();
         let aes_decrypted = aes_decrypt(&key, &aes_encrypted).unwrap();
         let aes_decrypted_message = std::str::from_utf8(&aes_decrypted).unwrap();
         assert_eq!(aes_decrypted_message, aes_message);
         assert_ne!(aes_encrypted, aes_message.as_bytes());
+        aes_decrypt(&key, &[]).unwrap_err();
+        aes_decrypt(&key, &aes_encrypted[..1]).unwrap_err();
+        aes_decrypt(&key, &aes_encrypted[..AES_NONCE_SIZE]).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_key_wrapping() {
+        let key_encryption_key = password_to_key("password123", &KdfParams::generate())
+            .await
+            .unwrap();
+        let other_key_encryption_key = password_to_key("password124", &KdfParams::generate())
+            .await
+            .unwrap();
+        let key = generate_key();
+        assert_ne!(key, generate_key());
+        let wrapped_key = wrap_key(&key_encryption_key, &key).unwrap();
+        assert_ne!(&wrapped_key[AES_NONCE_SIZE..][..AES_KEY_SIZE], &key[..]);
+        assert_eq!(unwrap_key(&key_encryption_key, &wrapped_key).unwrap(), key);
+        unwrap_key(&other_key_encryption_key, &wrapped_key).unwrap_err();
+        let mut tampered_key = wrapped_key;
+        tampered_key[AES_NONCE_SIZE] ^= 1;
+        unwrap_key(&key_encryption_key, &tampered_key).unwrap_err();
+    }
+
+    #[test]
+    fn test_secrets() {
+        let mut key = SecretKey::new([7; AES_KEY_SIZE]);
+        assert_eq!(*key, [7; AES_KEY_SIZE]);
+        assert!(!format!("{key:?}").contains('7'));
+        key.zeroize();
+        assert_eq!(*key, [0; AES_KEY_SIZE]);
+
+        let mut secret = SecretString::from("password123");
+        assert_eq!(&*secret, "password123");
+        assert!(!format!("{secret:?}").contains("password123"));
+        secret.zeroize();
+        assert_eq!(&*secret, "");
+    }
+
+    #[test]
+    fn test_hmac() {
+        let key = generate_key();
+        let other_key = generate_key();
+        let data = b"Hello, HMAC!";
+        let tag = hmac_sha256(&key, data);
+        assert!(verify_hmac_sha256(&key, data, &tag));
+        assert!(!verify_hmac_sha256(&other_key, data, &tag));
+        assert!(!verify_hmac_sha256(&key, b"Hello, HMAC?", &tag));
+        assert!(!verify_hmac_sha256(&key, data, &tag[1..]));
+        let mut tampered_tag = tag;
+        tampered_tag[0] ^= 1;
+        assert!(!verify_hmac_sha256(&key, data, &tampered_tag));
+
+        let subkey = derive_subkey(&key, b"level test");
+        assert_ne!(subkey, key);
+        assert_eq!(subkey, derive_subkey(&key, b"level test"));
+        assert_ne!(subkey, derive_subkey(&key, b"level other test"));
+        assert_ne!(subkey, derive_subkey(&other_key, b"level test"));
+    }
+
+    #[test]
+    fn test_recovery_phrase() {
+        let phrase = generate_recovery_phrase();
+        assert_eq!(phrase.len(), 29);
+        assert_ne!(phrase, generate_recovery_phrase());
+        assert_eq!(normalize_recovery_phrase(&phrase), Some(phrase.clone()));
+        let sloppy_phrase = phrase.to_lowercase().replace('-', " ");
+        assert_eq!(normalize_recovery_phrase(&sloppy_phrase), Some(phrase));
+        assert_eq!(
+            normalize_recovery_phrase("oi1l0-abcde-fghjk-mnpqr-stvwx"),
+            Some("01110-ABCDE-FGHJK-MNPQR-STVWX".to_owned())
+        );
+        assert_eq!(normalize_recovery_phrase("password123"), None);
+        assert_eq!(
+            normalize_recovery_phrase("UUUUU-UUUUU-UUUUU-UUUUU-UUUUU"),
+            None
+        );
+        assert_eq!(normalize_recovery_phrase(""), None);
     }
 
     #[test]
@@ -316,16 +857,166 @@ mod tests {
         assert_ne!(plaintext, ciphertext);
     }
 
+    async fn encrypt_to_sections(data: &[u8], key: &[u8; AES_KEY_SIZE]) -> Vec<Vec<u8>> {
+        let plaintext_path = random_save_path();
+        let ciphertext_path = random_save_path();
+
+        let sections = {
+            let mut plaintext_file = create_rw_file(&plaintext_path).await.unwrap();
+            plaintext_file.write_all(data).await.unwrap();
+            plaintext_file.rewind().await.unwrap();
+
+            let mut ciphertext_file = create_rw_file(&ciphertext_path).await.unwrap();
+            encrypt_file(&mut plaintext_file, &mut ciphertext_file, key)
+                .await
+                .unwrap();
+
+            let mut sections = Vec::new();
+            while let Some(section) = read_section(&mut ciphertext_file).await.unwrap() {
+                sections.push(section);
+            }
+            sections
+        };
+
+        fs::remove_file(&plaintext_path).await.unwrap();
+        fs::remove_file(&ciphertext_path).await.unwrap();
+
+        sections
+    }
+
+    async fn try_decrypt_sections(sections: &[Vec<u8>], key: &[u8; AES_KEY_SIZE]) -> Result<()> {
+        let ciphertext_path = random_save_path();
+
+        let result = {
+            let mut ciphertext_file = create_rw_file(&ciphertext_path).await.unwrap();
+            for section in sections {
+                write_section(&mut ciphertext_file, section).await.unwrap();
+            }
+            ciphertext_file.rewind().await.unwrap();
+
+            try_decrypt_file(&mut ciphertext_file, key).await
+        };
+
+        fs::remove_file(&ciphertext_path).await.unwrap();
+
+        result
+    }
+
+    #[tokio::test]
+    async fn test_file_tampering() {
+        let key = password_to_key("password123", &KdfParams::generate())
+            .await
+            .unwrap();
+        let data = vec![7u8; READER_CAPACITY * 3 + READER_CAPACITY / 2];
+        let sections = encrypt_to_sections(&data, &key).await;
+        assert_eq!(sections.len(), 5); // stream ID and four chunks
+        try_decrypt_sections(&sections, &key).await.unwrap();
+
+        // Empty files still produce a final chunk
+        let empty_sections = encrypt_to_sections(&[], &key).await;
+        assert_eq!(empty_sections.len(), 2);
+        try_decrypt_sections(&empty_sections, &key).await.unwrap();
+        try_decrypt_sections(&empty_sections[..1], &key)
+            .await
+            .unwrap_err();
+        try_decrypt_sections(&[], &key).await.unwrap_err();
+
+        // Reordered chunks
+        let mut reordered = sections.clone();
+        reordered.swap(1, 2);
+        try_decrypt_sections(&reordered, &key).await.unwrap_err();
+
+        // Duplicated chunk
+        let mut duplicated = sections.clone();
+        duplicated.insert(2, sections[2].clone());
+        try_decrypt_sections(&duplicated, &key).await.unwrap_err();
+
+        // Dropped chunk in the middle
+        let mut dropped = sections.clone();
+        dropped.remove(2);
+        try_decrypt_sections(&dropped, &key).await.unwrap_err();
+
+        // Truncated after a full chunk
+        try_decrypt_sections(&sections[..sections.len() - 1], &key)
+            .await
+            .unwrap_err();
+
+        // Extended past the final chunk
+        let mut extended = sections.clone();
+        extended.push(sections[sections.len() - 1].clone());
+        try_decrypt_sections(&extended, &key).await.unwrap_err();
+
+        // Chunk spliced in from another stream
+        let other_sections = encrypt_to_sections(&data, &key).await;
+        let mut spliced = sections.clone();
+        spliced[2] = other_sections[2].clone();
+        try_decrypt_sections(&spliced, &key).await.unwrap_err();
+
+        // Wrong key
+        let other_key = password_to_key("password124", &KdfParams::generate())
+            .await
+            .unwrap();
+        try_decrypt_sections(&sections, &other_key)
+            .await
+            .unwrap_err();
+    }
+
     #[tokio::test]
     async fn test_password_to_key() {
-        let start = Instant::now();
-        let key1 = password_to_key("password123").await;
-        let key2 = password_to_key("password123").await;
-        let key3 = password_to_key("password124").await;
-        let elapsed = start.elapsed();
+        // Per-save salts
+        let params1 = KdfParams::generate();
+        let params2 = KdfParams::generate();
+        assert_ne!(params1, params2);
+        assert!(!params1.is_legacy());
+        let KdfParams::Argon2id {
+            memory_cost,
+            time_cost,
+            parallelism,
+            ..
+        } = params1
+        else {
+            unreachable!()
+        };
+        assert_eq!(memory_cost, DEFAULT_ARGON2_MEMORY_COST);
+        assert_eq!(time_cost, DEFAULT_ARGON2_TIME_COST);
+        assert_eq!(parallelism, DEFAULT_ARGON2_PARALLELISM);
+        let key1 = password_to_key("password123", &params1).await.unwrap();
+        let key2 = password_to_key("password123", &params1).await.unwrap();
+        let key3 = password_to_key("password124", &params1).await.unwrap();
+        let key4 = password_to_key("password123", &params2).await.unwrap();
         assert_eq!(key1, key2);
         assert_ne!(key1, key3);
+        assert_ne!(key1, key4);
+
+        // Legacy keys
+        let legacy_params = KdfParams::LegacyBcrypt;
+        assert!(legacy_params.is_legacy());
+        let start = Instant::now();
+        let legacy_key1 = password_to_key("password123", &legacy_params)
+            .await
+            .unwrap();
+        let legacy_key2 = password_to_key("password123", &legacy_params)
+            .await
+            .unwrap();
+        let legacy_key3 = password_to_key("password124", &legacy_params)
+            .await
+            .unwrap();
+        let elapsed = start.elapsed();
         assert!(elapsed >= MIN_GET_KEY_DURATION * 3);
         assert!(elapsed <= MAX_GET_KEY_DURATION * 3);
+        assert_eq!(legacy_key1, legacy_key2);
+        assert_ne!(legacy_key1, legacy_key3);
+        assert_ne!(legacy_key1, key1);
+
+        // Invalid parameters
+        let invalid_params = KdfParams::Argon2id {
+            salt: random(),
+            memory_cost: 0,
+            time_cost: 0,
+            parallelism: 0,
+        };
+        password_to_key("password123", &invalid_params)
+            .await
+            .unwrap_err();
     }
 }
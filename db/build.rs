@@ -16,6 +16,15 @@ const TABLES: &[&str] = &[
     "report_template",
 ];
 
+/// The database schema migrations, in order.
+const MIGRATIONS: &[&str] = &[
+    "0001_schema_version",
+    "0002_money_minor_units",
+    "0003_currencies",
+    "0004_transaction_splits",
+    "0005_transfers",
+];
+
 /// Initialize a database table.
 async fn init_table(conn: &mut SqliteConnection, table: &str) -> Result<()> {
     let sql_path = format!("sql/init/{table}.sql");
@@ -27,6 +36,21 @@ async fn init_table(conn: &mut SqliteConnection, table: &str) -> Result<()> {
     Ok(())
 }
 
+/// Run a database schema migration.
+async fn migrate(conn: &mut SqliteConnection, version: usize, migration: &str) -> Result<()> {
+    let sql_path = format!("sql/migrations/{migration}.sql");
+    let sql_bytes = fs::read(sql_path).await?;
+    let sql_str = String::from_utf8(sql_bytes)?;
+
+    sqlx::raw_sql(&sql_str).execute(&mut *conn).await?;
+    sqlx::query("UPDATE schema_version SET version = ?")
+        .bind(version as i64)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
 /// Initialize the test database on build.
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -58,5 +82,9 @@ async fn main() -> Result<()> {
         init_table(&mut conn, table).await?;
     }
 
+    for (index, migration) in MIGRATIONS.iter().enumerate() {
+        migrate(&mut conn, index + 1, migration).await?;
+    }
+
     Ok(())
 }
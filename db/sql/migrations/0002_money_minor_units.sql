@@ -0,0 +1,24 @@
+-- Amounts were stored as REAL major units, which cannot represent most
+-- decimal amounts exactly. They are converted to INTEGER minor units,
+-- rounding through the shortest decimal representation of each value so
+-- that amounts like 1.005, stored as 1.00499999..., round as they were
+-- entered. Columns are added and dropped rather than rebuilding the tables,
+-- which would cascade deletes through their foreign keys.
+
+ALTER TABLE account_transaction ADD COLUMN amount_minor INTEGER NOT NULL DEFAULT 0;
+
+UPDATE account_transaction
+  SET amount_minor = CAST(ROUND(CAST(printf('%.15g', amount * 100) AS REAL)) AS INTEGER);
+
+ALTER TABLE account_transaction DROP COLUMN amount;
+
+ALTER TABLE account_transaction RENAME COLUMN amount_minor TO amount;
+
+ALTER TABLE budget ADD COLUMN total_limit_minor INTEGER NOT NULL DEFAULT 0;
+
+UPDATE budget
+  SET total_limit_minor = CAST(ROUND(CAST(printf('%.15g', total_limit * 100) AS REAL)) AS INTEGER);
+
+ALTER TABLE budget DROP COLUMN total_limit;
+
+ALTER TABLE budget RENAME COLUMN total_limit_minor TO total_limit;
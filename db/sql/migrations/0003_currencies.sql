@@ -0,0 +1,14 @@
+-- Accounts hold money in a currency, given by its ISO 4217 code. Accounts
+-- from before currencies were tracked are assumed to be in US dollars.
+
+ALTER TABLE account ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD';
+
+CREATE TABLE exchange_rate (
+  from_currency TEXT     NOT NULL,
+  to_currency   TEXT     NOT NULL,
+  rate_date     DATE     NOT NULL,
+  rate          INTEGER  NOT NULL,
+  created_at    DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  PRIMARY KEY (from_currency, to_currency, rate_date)
+);
@@ -0,0 +1,39 @@
+-- Transactions can be split into lines, each with its own amount, category,
+-- subcategory, memo and tags. The lines of a split transaction add up to its
+-- amount.
+
+CREATE TABLE account_transaction_split (
+  id                     TEXT     NOT NULL,
+  account_transaction_id TEXT     NOT NULL,
+  amount                 INTEGER  NOT NULL,
+  category_id            TEXT     NOT NULL,
+  subcategory_id         TEXT,
+  memo                   TEXT,
+  created_at             DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  PRIMARY KEY (id),
+
+  FOREIGN KEY (account_transaction_id)
+    REFERENCES account_transaction (id)
+      ON DELETE CASCADE,
+
+  FOREIGN KEY (category_id)
+    REFERENCES category (id),
+
+  FOREIGN KEY (subcategory_id)
+    REFERENCES subcategory (id)
+);
+
+CREATE TABLE account_transaction_split_tag (
+  account_transaction_split_id TEXT     NOT NULL,
+  tag_id                       TEXT     NOT NULL,
+  created_at                   DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  FOREIGN KEY (account_transaction_split_id)
+    REFERENCES account_transaction_split (id)
+      ON DELETE CASCADE,
+
+  FOREIGN KEY (tag_id)
+    REFERENCES tag (id)
+      ON DELETE CASCADE
+);
@@ -0,0 +1,20 @@
+-- Transfers link the debit taken from one account with the credit made to
+-- another. Both sides are kept in sync, and neither counts as income or an
+-- expense.
+
+CREATE TABLE account_transfer (
+  id                    TEXT     NOT NULL,
+  debit_transaction_id  TEXT     NOT NULL UNIQUE,
+  credit_transaction_id TEXT     NOT NULL UNIQUE,
+  created_at            DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  PRIMARY KEY (id),
+
+  FOREIGN KEY (debit_transaction_id)
+    REFERENCES account_transaction (id)
+      ON DELETE CASCADE,
+
+  FOREIGN KEY (credit_transaction_id)
+    REFERENCES account_transaction (id)
+      ON DELETE CASCADE
+);
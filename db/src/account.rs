@@ -7,12 +7,13 @@ use common::*;
 /// The database implementation of the account model.
 #[async_trait]
 pub trait DBAccount: Sized {
-    /// Creates a new account.
+    /// Creates a new account, holding money in the given currency.
     async fn create(
         db: &mut DBImpl,
         account_type: AccountType,
         name: &str,
         description: &str,
+        currency: &Currency,
     ) -> Result<Self>;
 
     /// Gets an account from the database.
@@ -36,7 +37,9 @@ pub trait DBAccount: Sized {
     /// Sets the account description.
     async fn set_description(&mut self, db: &mut DBImpl, description: &str) -> Result<()>;
 
-    /// Deletes the account from the database.
+    /// Deletes the account from the database, along with its transactions.
+    /// Transfers to or from the account are deleted whole, including the
+    /// other account's side.
     async fn delete(self, db: &mut DBImpl) -> Result<()>;
 }
 
@@ -47,16 +50,19 @@ impl DBAccount for Account {
         account_type: AccountType,
         name: &str,
         description: &str,
+        currency: &Currency,
     ) -> Result<Self> {
         let id = new_id();
         let account_type_name = account_type.to_internal_name();
+        let currency_code = currency.code();
 
         sqlx::query!(
-            "INSERT INTO account (id, account_type, name, description) VALUES (?, ?, ?, ?);",
+            "INSERT INTO account (id, account_type, name, description, currency) VALUES (?, ?, ?, ?, ?);",
             id,
             account_type_name,
             name,
-            description
+            description,
+            currency_code
         )
         .execute(&mut *db)
         .await?;
@@ -158,6 +164,16 @@ impl DBAccount for Account {
     }
 
     async fn delete(self, db: &mut DBImpl) -> Result<()> {
+        // The other side of a transfer would otherwise be left behind, and
+        // count as income or spending in its account
+        sqlx::query!(
+            "DELETE FROM account_transaction WHERE id IN (SELECT credit_transaction_id FROM account_transfer JOIN account_transaction ON account_transaction.id = debit_transaction_id WHERE account_id = ? UNION ALL SELECT debit_transaction_id FROM account_transfer JOIN account_transaction ON account_transaction.id = credit_transaction_id WHERE account_id = ?);",
+            self.id,
+            self.id
+        )
+        .execute(&mut *db)
+        .await?;
+
         sqlx::query!("DELETE FROM account WHERE id = ?;", self.id)
             .execute(&mut *db)
             .await?;
@@ -183,6 +199,7 @@ mod tests {
             AccountType::BankAccount,
             "My Bank Account",
             "Description of bank account",
+            &Currency::default(),
         )
         .await
         .unwrap();
@@ -191,6 +208,7 @@ mod tests {
             AccountType::CreditCard,
             "My Credit Card",
             "Description of credit card",
+            &Currency::new("EUR").unwrap(),
         )
         .await
         .unwrap();
@@ -214,6 +232,10 @@ mod tests {
         assert_eq!(account1.get_account_type(), AccountType::BankAccount);
         assert_eq!(account2.get_account_type(), AccountType::CreditCard);
 
+        // Get currency
+        assert_eq!(account1.get_currency(), Currency::default());
+        assert_eq!(account2.get_currency().code(), "EUR");
+
         // Mark edited
         assert!(account1.edited_at.is_none());
         account1.mark_edited(&mut db).await.unwrap();
@@ -1,10 +1,20 @@
-use crate::{new_id, DBAccount, DBCategory, DBImpl, DBInstitution, DBSubcategory};
+use crate::account_transaction_split::create_split;
+use crate::account_transfer::linked_ids;
+use crate::{
+    new_id, DBAccount, DBAccountTransactionSplit, DBAccountTransfer, DBCategory, DBImpl,
+    DBInstitution, DBSubcategory,
+};
 use async_trait::async_trait;
 use backend_common::Result;
 use chrono::{NaiveDate, Utc};
 use common::{ExpectedCommandError as Error, *};
+use sqlx::Connection;
+use std::future::Future;
+use std::pin::Pin;
 
-/// The database implementation of the account transaction model.
+/// The database implementation of the account transaction model. Setting the
+/// name, description, amount, date, category or subcategory of either side
+/// of a transfer sets it on both.
 #[async_trait]
 pub trait DBAccountTransaction: Sized {
     /// Creates a new account transaction. This can fail if the
@@ -14,7 +24,7 @@ pub trait DBAccountTransaction: Sized {
         account: &mut Account,
         name: &str,
         description: &str,
-        amount: f64,
+        amount: Money,
         transaction_type: TransactionType,
         institution: &Institution,
         date: NaiveDate,
@@ -51,13 +61,20 @@ pub trait DBAccountTransaction: Sized {
     /// Gets the subcategory in which the transaction exists.
     async fn get_subcategory(&self, db: &mut DBImpl) -> Result<Option<Subcategory>>;
 
-    /// Marks the transaction as edited.
+    /// Gets the lines the transaction is split into, in the order they were
+    /// given. Transactions that are not split have no lines.
+    async fn get_splits(&self, db: &mut DBImpl) -> Result<Vec<AccountTransactionSplit>>;
+
+    /// Marks the transaction as edited, along with the other side of its
+    /// transfer if it is one.
     async fn mark_edited(&mut self, db: &mut DBImpl) -> Result<()>;
 
     /// Marks the transaction as reconciled.
     async fn mark_reconciled(&mut self, db: &mut DBImpl) -> Result<()>;
 
-    /// Sets the account the transaction is associated with.
+    /// Sets the account the transaction is associated with. This can fail if
+    /// the transaction is one side of a transfer, and the account is the
+    /// other side's or has a different currency.
     async fn set_account(&mut self, db: &mut DBImpl, account: &Account) -> Result<()>;
 
     /// Sets the transaction name.
@@ -66,8 +83,10 @@ pub trait DBAccountTransaction: Sized {
     /// Sets the transaction description.
     async fn set_description(&mut self, db: &mut DBImpl, description: &str) -> Result<()>;
 
-    /// Sets the transaction amount.
-    async fn set_amount(&mut self, db: &mut DBImpl, amount: f64) -> Result<()>;
+    /// Sets the transaction amount. This can fail if the transaction is split
+    /// and its lines do not add up to the new amount, in which case its lines
+    /// should be cleared or replaced first.
+    async fn set_amount(&mut self, db: &mut DBImpl, amount: Money) -> Result<()>;
 
     /// Sets the date of the transaction.
     async fn set_date(&mut self, db: &mut DBImpl, date: NaiveDate) -> Result<()>;
@@ -90,10 +109,52 @@ pub trait DBAccountTransaction: Sized {
         subcategory: Option<&Subcategory>,
     ) -> Result<()>;
 
-    /// Deletes the account transaction from the database.
+    /// Splits the transaction into the given lines, replacing any lines it
+    /// was already split into. The transaction keeps its own category and
+    /// subcategory. This can fail if the lines do not add up to the
+    /// transaction amount, or if a line's category/subcategory combination
+    /// is invalid, or if the transaction is one side of a transfer. Giving no
+    /// lines leaves the transaction unsplit.
+    async fn set_splits(
+        &mut self,
+        db: &mut DBImpl,
+        lines: &[SplitLine],
+    ) -> Result<Vec<AccountTransactionSplit>>;
+
+    /// Deletes the account transaction from the database. Deleting either
+    /// side of a transfer deletes both.
     async fn delete(self, db: &mut DBImpl) -> Result<()>;
 }
 
+/// Edits a transaction within a database transaction, so that the edit
+/// takes effect in full or not at all. The transaction callback cannot
+/// borrow, so the edit works on a copy of the transaction, which replaces
+/// the original once the edit is committed.
+async fn edit_atomically<F, R>(
+    db: &mut DBImpl,
+    account_transaction: &mut AccountTransaction,
+    edit: F,
+) -> Result<R>
+where
+    for<'c> F: FnOnce(
+            &'c mut DBImpl,
+            AccountTransaction,
+        )
+            -> Pin<Box<dyn Future<Output = Result<(AccountTransaction, R)>> + Send + 'c>>
+        + Send
+        + Sync
+        + 'static,
+    R: Send + 'static,
+{
+    let copy = account_transaction.clone();
+    let (edited, result) = db
+        .transaction(|db| Box::pin(async move { edit(db, copy).await }))
+        .await?;
+    *account_transaction = edited;
+
+    Ok(result)
+}
+
 #[async_trait]
 impl DBAccountTransaction for AccountTransaction {
     async fn create(
@@ -101,7 +162,7 @@ impl DBAccountTransaction for AccountTransaction {
         account: &mut Account,
         name: &str,
         description: &str,
-        amount: f64,
+        amount: Money,
         transaction_type: TransactionType,
         institution: &Institution,
         date: NaiveDate,
@@ -118,6 +179,7 @@ impl DBAccountTransaction for AccountTransaction {
         let transaction_type_name = transaction_type.to_internal_name();
         let transaction_date = date.and_hms_milli_opt(12, 0, 0, 0).unwrap();
         let subcategory_id = subcategory.map(|x| x.id.as_str());
+        let amount = amount.minor_units();
 
         sqlx::query!(
             "INSERT INTO account_transaction (id, account_id, name, description, amount, transaction_type, institution_id, transaction_date, category_id, subcategory_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);",
@@ -210,18 +272,25 @@ impl DBAccountTransaction for AccountTransaction {
         }
     }
 
+    async fn get_splits(&self, db: &mut DBImpl) -> Result<Vec<AccountTransactionSplit>> {
+        AccountTransactionSplit::list_by_transaction(db, self).await
+    }
+
     async fn mark_edited(&mut self, db: &mut DBImpl) -> Result<()> {
         self.edited_at = Some(Utc::now().naive_utc());
 
-        sqlx::query!(
-            "UPDATE account_transaction SET edited_at = ? WHERE id = ?;",
-            self.edited_at,
-            self.id
-        )
-        .execute(&mut *db)
-        .await?;
+        for id in linked_ids(db, self).await? {
+            sqlx::query!(
+                "UPDATE account_transaction SET edited_at = ? WHERE id = ?;",
+                self.edited_at,
+                id
+            )
+            .execute(&mut *db)
+            .await?;
 
-        self.get_account(db).await?.mark_edited(db).await?;
+            let transaction = Self::get(db, &id).await?.unwrap();
+            transaction.get_account(db).await?.mark_edited(db).await?;
+        }
 
         Ok(())
     }
@@ -241,100 +310,166 @@ impl DBAccountTransaction for AccountTransaction {
     }
 
     async fn set_account(&mut self, db: &mut DBImpl, account: &Account) -> Result<()> {
-        self.account_id = account.id.clone();
+        for id in linked_ids(db, self).await? {
+            if id != self.id {
+                let other_account = Self::get(db, &id).await?.unwrap().get_account(db).await?;
 
-        sqlx::query!(
-            "UPDATE account_transaction SET account_id = ? WHERE id = ?;",
-            self.account_id,
-            self.id
-        )
-        .execute(&mut *db)
-        .await?;
+                if other_account.id == account.id || other_account.currency != account.currency {
+                    Err(Error::InvalidTransfer)?;
+                }
+            }
+        }
 
-        self.mark_edited(db).await?;
+        let account_id = account.id.clone();
 
-        Ok(())
-    }
+        edit_atomically(db, self, |db, mut this| {
+            Box::pin(async move {
+                this.account_id = account_id;
 
-    async fn set_name(&mut self, db: &mut DBImpl, name: &str) -> Result<()> {
-        self.name = name.to_owned();
+                sqlx::query!(
+                    "UPDATE account_transaction SET account_id = ? WHERE id = ?;",
+                    this.account_id,
+                    this.id
+                )
+                .execute(&mut *db)
+                .await?;
 
-        sqlx::query!(
-            "UPDATE account_transaction SET name = ? WHERE id = ?;",
-            self.name,
-            self.id
-        )
-        .execute(&mut *db)
-        .await?;
+                this.mark_edited(db).await?;
 
-        self.mark_edited(db).await?;
+                Ok((this, ()))
+            })
+        })
+        .await
+    }
 
-        Ok(())
+    async fn set_name(&mut self, db: &mut DBImpl, name: &str) -> Result<()> {
+        let name = name.to_owned();
+
+        edit_atomically(db, self, |db, mut this| {
+            Box::pin(async move {
+                this.name = name;
+
+                for id in linked_ids(db, &this).await? {
+                    sqlx::query!(
+                        "UPDATE account_transaction SET name = ? WHERE id = ?;",
+                        this.name,
+                        id
+                    )
+                    .execute(&mut *db)
+                    .await?;
+                }
+
+                this.mark_edited(db).await?;
+
+                Ok((this, ()))
+            })
+        })
+        .await
     }
 
     async fn set_description(&mut self, db: &mut DBImpl, description: &str) -> Result<()> {
-        self.description = Some(description.to_owned());
-
-        sqlx::query!(
-            "UPDATE account_transaction SET description = ? WHERE id = ?;",
-            self.description,
-            self.id
-        )
-        .execute(&mut *db)
-        .await?;
-
-        self.mark_edited(db).await?;
-
-        Ok(())
+        let description = description.to_owned();
+
+        edit_atomically(db, self, |db, mut this| {
+            Box::pin(async move {
+                this.description = Some(description);
+
+                for id in linked_ids(db, &this).await? {
+                    sqlx::query!(
+                        "UPDATE account_transaction SET description = ? WHERE id = ?;",
+                        this.description,
+                        id
+                    )
+                    .execute(&mut *db)
+                    .await?;
+                }
+
+                this.mark_edited(db).await?;
+
+                Ok((this, ()))
+            })
+        })
+        .await
     }
 
-    async fn set_amount(&mut self, db: &mut DBImpl, amount: f64) -> Result<()> {
-        self.amount = amount;
-
-        sqlx::query!(
-            "UPDATE account_transaction SET amount = ? WHERE id = ?;",
-            self.amount,
-            self.id
-        )
-        .execute(&mut *db)
-        .await?;
+    async fn set_amount(&mut self, db: &mut DBImpl, amount: Money) -> Result<()> {
+        let splits = self.get_splits(db).await?;
 
-        self.mark_edited(db).await?;
+        if !splits.is_empty() && Money::checked_sum(splits.iter().map(|x| x.amount)) != Some(amount)
+        {
+            Err(Error::SplitLinesMismatch)?;
+        }
 
-        Ok(())
+        edit_atomically(db, self, move |db, mut this| {
+            Box::pin(async move {
+                this.amount = amount;
+                let amount = amount.minor_units();
+
+                for id in linked_ids(db, &this).await? {
+                    sqlx::query!(
+                        "UPDATE account_transaction SET amount = ? WHERE id = ?;",
+                        amount,
+                        id
+                    )
+                    .execute(&mut *db)
+                    .await?;
+                }
+
+                this.mark_edited(db).await?;
+
+                Ok((this, ()))
+            })
+        })
+        .await
     }
 
     async fn set_date(&mut self, db: &mut DBImpl, date: NaiveDate) -> Result<()> {
-        self.transaction_date = date.and_hms_milli_opt(12, 0, 0, 0).unwrap();
-
-        sqlx::query!(
-            "UPDATE account_transaction SET transaction_date = ? WHERE id = ?;",
-            self.transaction_date,
-            self.id
-        )
-        .execute(&mut *db)
-        .await?;
-
-        self.mark_edited(db).await?;
-
-        Ok(())
+        edit_atomically(db, self, move |db, mut this| {
+            Box::pin(async move {
+                this.transaction_date = date.and_hms_milli_opt(12, 0, 0, 0).unwrap();
+
+                for id in linked_ids(db, &this).await? {
+                    sqlx::query!(
+                        "UPDATE account_transaction SET transaction_date = ? WHERE id = ?;",
+                        this.transaction_date,
+                        id
+                    )
+                    .execute(&mut *db)
+                    .await?;
+                }
+
+                this.mark_edited(db).await?;
+
+                Ok((this, ()))
+            })
+        })
+        .await
     }
 
     async fn set_category(&mut self, db: &mut DBImpl, category: &Category) -> Result<()> {
-        self.subcategory_id = None;
-        self.category_id = category.id.clone();
-
-        sqlx::query!(
-            "UPDATE account_transaction SET category_id = ?, subcategory_id = NULL WHERE id = ?;",
-            self.category_id,
-            self.id
-        )
-        .execute(&mut *db)
-        .await?;
-
-        self.mark_edited(db).await?;
-
-        Ok(())
+        let category_id = category.id.clone();
+
+        edit_atomically(db, self, |db, mut this| {
+            Box::pin(async move {
+                this.subcategory_id = None;
+                this.category_id = category_id;
+
+                for id in linked_ids(db, &this).await? {
+                    sqlx::query!(
+                        "UPDATE account_transaction SET category_id = ?, subcategory_id = NULL WHERE id = ?;",
+                        this.category_id,
+                        id
+                    )
+                    .execute(&mut *db)
+                    .await?;
+                }
+
+                this.mark_edited(db).await?;
+
+                Ok((this, ()))
+            })
+        })
+        .await
     }
 
     async fn set_subcategory(
@@ -348,19 +483,28 @@ impl DBAccountTransaction for AccountTransaction {
             }
         }
 
-        self.subcategory_id = subcategory.map(|x| x.id.clone());
+        let subcategory_id = subcategory.map(|x| x.id.clone());
 
-        sqlx::query!(
-            "UPDATE account_transaction SET subcategory_id = ? WHERE id = ?;",
-            self.subcategory_id,
-            self.id
-        )
-        .execute(&mut *db)
-        .await?;
+        edit_atomically(db, self, |db, mut this| {
+            Box::pin(async move {
+                this.subcategory_id = subcategory_id;
 
-        self.mark_edited(db).await?;
+                for id in linked_ids(db, &this).await? {
+                    sqlx::query!(
+                        "UPDATE account_transaction SET subcategory_id = ? WHERE id = ?;",
+                        this.subcategory_id,
+                        id
+                    )
+                    .execute(&mut *db)
+                    .await?;
+                }
 
-        Ok(())
+                this.mark_edited(db).await?;
+
+                Ok((this, ()))
+            })
+        })
+        .await
     }
 
     async fn set_category_and_subcategory(
@@ -375,29 +519,101 @@ impl DBAccountTransaction for AccountTransaction {
             }
         }
 
-        self.category_id = category.id.clone();
-        self.subcategory_id = subcategory.map(|x| x.id.clone());
+        let category_id = category.id.clone();
+        let subcategory_id = subcategory.map(|x| x.id.clone());
+
+        edit_atomically(db, self, |db, mut this| {
+            Box::pin(async move {
+                this.category_id = category_id;
+                this.subcategory_id = subcategory_id;
+
+                for id in linked_ids(db, &this).await? {
+                    sqlx::query!(
+                        "UPDATE account_transaction SET category_id = ?, subcategory_id = ? WHERE id = ?;",
+                        this.category_id,
+                        this.subcategory_id,
+                        id
+                    )
+                    .execute(&mut *db)
+                    .await?;
+                }
+
+                this.mark_edited(db).await?;
+
+                Ok((this, ()))
+            })
+        })
+        .await
+    }
 
-        sqlx::query!(
-            "UPDATE account_transaction SET category_id = ?, subcategory_id = ? WHERE id = ?;",
-            self.category_id,
-            self.subcategory_id,
-            self.id
-        )
-        .execute(&mut *db)
-        .await?;
+    async fn set_splits(
+        &mut self,
+        db: &mut DBImpl,
+        lines: &[SplitLine],
+    ) -> Result<Vec<AccountTransactionSplit>> {
+        if !lines.is_empty()
+            && AccountTransfer::get_by_transaction(db, self)
+                .await?
+                .is_some()
+        {
+            Err(Error::SplitTransfer)?;
+        }
 
-        self.mark_edited(db).await?;
+        if !lines.is_empty() {
+            let total = Money::checked_sum(lines.iter().map(|x| x.amount))
+                .ok_or(Error::AmountOutOfRange)?;
 
-        Ok(())
+            if total != self.amount {
+                Err(Error::SplitLinesMismatch)?;
+            }
+        }
+
+        for line in lines {
+            if let Some(subcategory) = &line.subcategory {
+                if subcategory.category_id != line.category.id {
+                    Err(Error::InvalidSubcategory)?;
+                }
+            }
+        }
+
+        let lines = lines.to_vec();
+
+        edit_atomically(db, self, |db, mut this| {
+            Box::pin(async move {
+                sqlx::query!(
+                    "DELETE FROM account_transaction_split WHERE account_transaction_id = ?;",
+                    this.id
+                )
+                .execute(&mut *db)
+                .await?;
+
+                let mut splits = Vec::with_capacity(lines.len());
+
+                for line in &lines {
+                    splits.push(create_split(db, &this, line).await?);
+                }
+
+                this.mark_edited(db).await?;
+
+                Ok((this, splits))
+            })
+        })
+        .await
     }
 
     async fn delete(self, db: &mut DBImpl) -> Result<()> {
-        sqlx::query!("DELETE FROM account_transaction WHERE id = ?;", self.id)
-            .execute(&mut *db)
-            .await?;
-
-        Ok(())
+        db.transaction(|db| {
+            Box::pin(async move {
+                for id in linked_ids(db, &self).await? {
+                    sqlx::query!("DELETE FROM account_transaction WHERE id = ?;", id)
+                        .execute(&mut **db)
+                        .await?;
+                }
+
+                Ok(())
+            })
+        })
+        .await
     }
 }
 
@@ -418,12 +634,19 @@ mod tests {
             AccountType::RetirementAccount,
             "My Retirement Account",
             "",
+            &Currency::default(),
+        )
+        .await
+        .unwrap();
+        let mut account2 = Account::create(
+            &mut db,
+            AccountType::Property,
+            "My Property",
+            "",
+            &Currency::default(),
         )
         .await
         .unwrap();
-        let mut account2 = Account::create(&mut db, AccountType::Property, "My Property", "")
-            .await
-            .unwrap();
         let institution1 = Institution::create(&mut db, "IHOP", "Internation House of Pancakes")
             .await
             .unwrap();
@@ -443,7 +666,7 @@ mod tests {
             &mut account1,
             "Breakfast",
             "Breakfast at IHOP",
-            16.75,
+            Money::from_minor_units(1675),
             TransactionType::Debit,
             &institution1,
             NaiveDate::from_ymd_opt(2023, 4, 1).unwrap(),
@@ -457,7 +680,7 @@ mod tests {
             &mut account2,
             "Another transaction",
             "",
-            20.00,
+            Money::from_minor_units(2000),
             TransactionType::Credit,
             &institution2,
             NaiveDate::from_ymd_opt(2020, 3, 15).unwrap(),
@@ -471,7 +694,7 @@ mod tests {
             &mut account1,
             "",
             "",
-            0.00,
+            Money::ZERO,
             TransactionType::Credit,
             &institution1,
             NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
@@ -485,7 +708,7 @@ mod tests {
             &mut account1,
             "",
             "",
-            0.00,
+            Money::ZERO,
             TransactionType::Debit,
             &institution2,
             NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
@@ -543,7 +766,7 @@ mod tests {
             &mut account1,
             "Batch transaction #1",
             "",
-            0.0,
+            Money::ZERO,
             TransactionType::Credit,
             &institution1,
             NaiveDate::from_ymd_opt(2020, 5, 1).unwrap(),
@@ -557,7 +780,7 @@ mod tests {
             &mut account1,
             "Batch transaction #2",
             "",
-            0.0,
+            Money::ZERO,
             TransactionType::Credit,
             &institution1,
             NaiveDate::from_ymd_opt(2020, 5, 2).unwrap(),
@@ -571,7 +794,7 @@ mod tests {
             &mut account1,
             "Batch transaction #3",
             "",
-            0.0,
+            Money::ZERO,
             TransactionType::Credit,
             &institution1,
             NaiveDate::from_ymd_opt(2020, 5, 3).unwrap(),
@@ -727,8 +950,11 @@ mod tests {
         assert_eq!(transaction9, transaction1);
 
         // Set amount
-        transaction1.set_amount(&mut db, 0.01).await.unwrap();
-        assert_eq!(transaction1.amount, 0.01);
+        transaction1
+            .set_amount(&mut db, Money::from_minor_units(1))
+            .await
+            .unwrap();
+        assert_eq!(transaction1.amount, Money::from_minor_units(1));
         let transaction10 = AccountTransaction::get(&mut db, &transaction1.id)
             .await
             .unwrap()
@@ -0,0 +1,357 @@
+use crate::{new_id, DBAccountTransaction, DBCategory, DBImpl, DBSubcategory, DBTag};
+use async_trait::async_trait;
+use backend_common::Result;
+use common::*;
+
+/// Creates a line of a split transaction, along with its tags. This does not
+/// check that the transaction's lines add up to its amount, which is left to
+/// [`DBAccountTransaction::set_splits`].
+pub(crate) async fn create_split(
+    db: &mut DBImpl,
+    account_transaction: &AccountTransaction,
+    line: &SplitLine,
+) -> Result<AccountTransactionSplit> {
+    let id = new_id();
+    let amount = line.amount.minor_units();
+    let subcategory_id = line.subcategory.as_ref().map(|x| x.id.as_str());
+
+    sqlx::query!(
+        "INSERT INTO account_transaction_split (id, account_transaction_id, amount, category_id, subcategory_id, memo) VALUES (?, ?, ?, ?, ?, ?);",
+        id,
+        account_transaction.id,
+        amount,
+        line.category.id,
+        subcategory_id,
+        line.memo
+    )
+    .execute(&mut *db)
+    .await?;
+
+    for tag in &line.tags {
+        sqlx::query!(
+            "INSERT INTO account_transaction_split_tag (account_transaction_split_id, tag_id) VALUES (?, ?);",
+            id,
+            tag.id
+        )
+        .execute(&mut *db)
+        .await?;
+    }
+
+    AccountTransactionSplit::get(db, &id)
+        .await
+        .map(|x| x.unwrap())
+}
+
+/// The database implementation of the split transaction line model. Lines
+/// are created and replaced through the transaction they split, with
+/// [`DBAccountTransaction::set_splits`].
+#[async_trait]
+pub trait DBAccountTransactionSplit: Sized {
+    /// Gets a split line from the database.
+    async fn get(db: &mut DBImpl, id: &str) -> Result<Option<Self>>;
+
+    /// Lists the lines of a split transaction, in the order they were given.
+    /// Transactions that are not split have no lines.
+    async fn list_by_transaction(
+        db: &mut DBImpl,
+        account_transaction: &AccountTransaction,
+    ) -> Result<Vec<Self>>;
+
+    /// Gets the transaction the line is a part of.
+    async fn get_account_transaction(&self, db: &mut DBImpl) -> Result<AccountTransaction>;
+
+    /// Gets the category in which the line exists.
+    async fn get_category(&self, db: &mut DBImpl) -> Result<Category>;
+
+    /// Gets the subcategory in which the line exists.
+    async fn get_subcategory(&self, db: &mut DBImpl) -> Result<Option<Subcategory>>;
+
+    /// Lists the links between the line and its tags.
+    async fn list_tags(&self, db: &mut DBImpl) -> Result<Vec<AccountTransactionSplitTag>>;
+
+    /// Gets the tags of the line.
+    async fn get_tags(&self, db: &mut DBImpl) -> Result<Vec<Tag>>;
+}
+
+#[async_trait]
+impl DBAccountTransactionSplit for AccountTransactionSplit {
+    async fn get(db: &mut DBImpl, id: &str) -> Result<Option<Self>> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM account_transaction_split WHERE id = ?;",
+            id
+        )
+        .fetch_optional(&mut *db)
+        .await?)
+    }
+
+    async fn list_by_transaction(
+        db: &mut DBImpl,
+        account_transaction: &AccountTransaction,
+    ) -> Result<Vec<Self>> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM account_transaction_split WHERE account_transaction_id = ? ORDER BY rowid;",
+            account_transaction.id
+        )
+        .fetch_all(&mut *db)
+        .await?)
+    }
+
+    async fn get_account_transaction(&self, db: &mut DBImpl) -> Result<AccountTransaction> {
+        AccountTransaction::get(db, &self.account_transaction_id)
+            .await
+            .map(|x| x.unwrap())
+    }
+
+    async fn get_category(&self, db: &mut DBImpl) -> Result<Category> {
+        Category::get(db, &self.category_id)
+            .await
+            .map(|x| x.unwrap())
+    }
+
+    async fn get_subcategory(&self, db: &mut DBImpl) -> Result<Option<Subcategory>> {
+        match &self.subcategory_id {
+            Some(subcategory_id) => Subcategory::get(db, subcategory_id)
+                .await
+                .map(|x| Some(x.unwrap())),
+            None => Ok(None),
+        }
+    }
+
+    async fn list_tags(&self, db: &mut DBImpl) -> Result<Vec<AccountTransactionSplitTag>> {
+        Ok(sqlx::query_as!(
+            AccountTransactionSplitTag,
+            "SELECT * FROM account_transaction_split_tag WHERE account_transaction_split_id = ? ORDER BY rowid;",
+            self.id
+        )
+        .fetch_all(&mut *db)
+        .await?)
+    }
+
+    async fn get_tags(&self, db: &mut DBImpl) -> Result<Vec<Tag>> {
+        let mut tags = Vec::new();
+
+        for split_tag in self.list_tags(db).await? {
+            tags.push(Tag::get(db, &split_tag.tag_id).await?.unwrap());
+        }
+
+        Ok(tags)
+    }
+}
+
+/// Split transaction tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DBAccount, DBInstitution, TestDB};
+    use backend_common::Error as BackendError;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn test_account_transaction_split() {
+        // Init
+        let mut db = TestDB::new().await.unwrap();
+        let mut account = Account::create(
+            &mut db,
+            AccountType::BankAccount,
+            "My account",
+            "",
+            &Currency::default(),
+        )
+        .await
+        .unwrap();
+        let institution = Institution::create(&mut db, "Grocery store", "")
+            .await
+            .unwrap();
+        let shopping = Category::create(&mut db, "Shopping", "").await.unwrap();
+        let food = Category::create(&mut db, "Food", "").await.unwrap();
+        let household = Category::create(&mut db, "Household", "").await.unwrap();
+        let produce = Subcategory::create(&mut db, &food, "Produce", "")
+            .await
+            .unwrap();
+        let cleaning = Subcategory::create(&mut db, &household, "Cleaning", "")
+            .await
+            .unwrap();
+        let tag1 = Tag::create(&mut db, "Weekly shop", "").await.unwrap();
+        let tag2 = Tag::create(&mut db, "Organic", "").await.unwrap();
+        let mut transaction = AccountTransaction::create(
+            &mut db,
+            &mut account,
+            "Grocery receipt",
+            "",
+            Money::from_minor_units(5000),
+            TransactionType::Debit,
+            &institution,
+            NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+            &shopping,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(transaction.get_splits(&mut db).await.unwrap().is_empty());
+
+        // Split
+        let lines = vec![
+            SplitLine {
+                amount: Money::from_minor_units(3250),
+                category: food.clone(),
+                subcategory: Some(produce.clone()),
+                memo: "Fruit and vegetables".to_owned(),
+                tags: vec![tag1.clone(), tag2.clone()],
+            },
+            SplitLine {
+                amount: Money::from_minor_units(1750),
+                category: household.clone(),
+                subcategory: None,
+                memo: String::new(),
+                tags: vec![tag1.clone()],
+            },
+        ];
+        let splits = transaction.set_splits(&mut db, &lines).await.unwrap();
+        assert_eq!(splits.len(), 2);
+        assert!(transaction.edited_at.is_some());
+        assert_eq!(splits[0].account_transaction_id, transaction.id);
+        assert_eq!(splits[0].amount, Money::from_minor_units(3250));
+        assert_eq!(splits[0].memo.as_deref(), Some("Fruit and vegetables"));
+        assert_eq!(splits[0].get_category(&mut db).await.unwrap(), food);
+        assert_eq!(
+            splits[0].get_subcategory(&mut db).await.unwrap(),
+            Some(produce.clone())
+        );
+        assert_eq!(
+            splits[0].get_tags(&mut db).await.unwrap(),
+            vec![tag1.clone(), tag2.clone()]
+        );
+        assert_eq!(splits[1].get_subcategory(&mut db).await.unwrap(), None);
+        assert_eq!(
+            splits[1].get_tags(&mut db).await.unwrap(),
+            vec![tag1.clone()]
+        );
+        assert_eq!(
+            splits[1].get_account_transaction(&mut db).await.unwrap(),
+            transaction
+        );
+
+        // Get
+        let split = AccountTransactionSplit::get(&mut db, &splits[1].id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(split, splits[1]);
+        assert!(AccountTransactionSplit::get(&mut db, "")
+            .await
+            .unwrap()
+            .is_none());
+
+        // List in the order given
+        assert_eq!(transaction.get_splits(&mut db).await.unwrap(), splits);
+
+        // Lines must add up to the transaction amount
+        let mut mismatched_lines = lines.clone();
+        mismatched_lines[1].amount = Money::from_minor_units(1749);
+        let err = transaction
+            .set_splits(&mut db, &mismatched_lines)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == ExpectedCommandError::SplitLinesMismatch
+        ));
+        mismatched_lines[1].amount = Money::MAX;
+        let err = transaction
+            .set_splits(&mut db, &mismatched_lines)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == ExpectedCommandError::AmountOutOfRange
+        ));
+        let err = transaction
+            .set_amount(&mut db, Money::from_minor_units(6000))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == ExpectedCommandError::SplitLinesMismatch
+        ));
+        assert_eq!(transaction.amount, Money::from_minor_units(5000));
+
+        // Line subcategories must be within their categories
+        let mut invalid_lines = lines.clone();
+        invalid_lines[1].subcategory = Some(produce.clone());
+        let err = transaction
+            .set_splits(&mut db, &invalid_lines)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == ExpectedCommandError::InvalidSubcategory
+        ));
+        assert_eq!(transaction.get_splits(&mut db).await.unwrap(), splits);
+
+        // Replace lines
+        let mut new_lines = lines.clone();
+        new_lines[1].subcategory = Some(cleaning.clone());
+        new_lines[1].tags = Vec::new();
+        let new_splits = transaction.set_splits(&mut db, &new_lines).await.unwrap();
+        assert!(AccountTransactionSplit::get(&mut db, &splits[0].id)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            new_splits[1].get_subcategory(&mut db).await.unwrap(),
+            Some(cleaning)
+        );
+        assert!(new_splits[1].list_tags(&mut db).await.unwrap().is_empty());
+
+        // Lines are replaced in full or not at all
+        let mut failing_lines = new_lines.clone();
+        failing_lines[1].tags = vec![Tag {
+            id: "missing tag".to_owned(),
+            ..tag1.clone()
+        }];
+        let edited_at = transaction.edited_at;
+        transaction
+            .set_splits(&mut db, &failing_lines)
+            .await
+            .unwrap_err();
+        assert_eq!(transaction.edited_at, edited_at);
+        assert_eq!(transaction.get_splits(&mut db).await.unwrap(), new_splits);
+
+        // Clear lines, after which the amount can change freely
+        assert!(transaction
+            .set_splits(&mut db, &[])
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(transaction.get_splits(&mut db).await.unwrap().is_empty());
+        transaction
+            .set_amount(&mut db, Money::from_minor_units(6000))
+            .await
+            .unwrap();
+
+        // Deleting the transaction deletes its lines
+        let splits = transaction
+            .set_splits(
+                &mut db,
+                &[SplitLine {
+                    amount: Money::from_minor_units(6000),
+                    category: food,
+                    subcategory: None,
+                    memo: String::new(),
+                    tags: vec![tag2],
+                }],
+            )
+            .await
+            .unwrap();
+        transaction.delete(&mut db).await.unwrap();
+        assert!(AccountTransactionSplit::get(&mut db, &splits[0].id)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Clean up
+        db.delete().await.unwrap();
+    }
+}
@@ -186,9 +186,15 @@ mod tests {
         let mut db = TestDB::new().await.unwrap();
 
         // Create
-        let mut account = Account::create(&mut db, AccountType::BankAccount, "My account", "")
-            .await
-            .unwrap();
+        let mut account = Account::create(
+            &mut db,
+            AccountType::BankAccount,
+            "My account",
+            "",
+            &Currency::default(),
+        )
+        .await
+        .unwrap();
         let institution = Institution::create(&mut db, "My institution", "")
             .await
             .unwrap();
@@ -198,7 +204,7 @@ mod tests {
             &mut account,
             "Transaction 1",
             "",
-            0.01,
+            Money::from_minor_units(1),
             TransactionType::Credit,
             &institution,
             NaiveDate::from_ymd_opt(2023, 4, 1).unwrap(),
@@ -212,7 +218,7 @@ mod tests {
             &mut account,
             "Transaction 2",
             "",
-            0.99,
+            Money::from_minor_units(99),
             TransactionType::Debit,
             &institution,
             NaiveDate::from_ymd_opt(2023, 3, 31).unwrap(),
@@ -0,0 +1,608 @@
+use crate::{new_id, DBAccountTransaction, DBImpl};
+use async_trait::async_trait;
+use backend_common::Result;
+use chrono::NaiveDate;
+use common::{ExpectedCommandError as Error, *};
+use sqlx::Connection;
+
+/// Gets the IDs of a transaction and, if it is one side of a transfer, the
+/// other side. Edits to either side of a transfer are made to both.
+pub(crate) async fn linked_ids(
+    db: &mut DBImpl,
+    account_transaction: &AccountTransaction,
+) -> Result<Vec<String>> {
+    match AccountTransfer::get_by_transaction(db, account_transaction).await? {
+        Some(transfer) => Ok(vec![
+            transfer.debit_transaction_id,
+            transfer.credit_transaction_id,
+        ]),
+        None => Ok(vec![account_transaction.id.clone()]),
+    }
+}
+
+/// The database implementation of the account transfer model.
+#[async_trait]
+pub trait DBAccountTransfer: Sized {
+    /// Creates a new transfer, debiting the amount from one account and
+    /// crediting it to the other. Both sides are created together or not at
+    /// all. This can fail if the accounts are the same or have different
+    /// currencies, or if the category/subcategory combination is invalid.
+    async fn create(
+        db: &mut DBImpl,
+        from_account: &mut Account,
+        to_account: &mut Account,
+        name: &str,
+        description: &str,
+        amount: Money,
+        institution: &Institution,
+        date: NaiveDate,
+        category: &Category,
+        subcategory: Option<&Subcategory>,
+    ) -> Result<Self>;
+
+    /// Gets a transfer from the database.
+    async fn get(db: &mut DBImpl, id: &str) -> Result<Option<Self>>;
+
+    /// Gets the transfer a transaction is one side of, if any.
+    async fn get_by_transaction(
+        db: &mut DBImpl,
+        account_transaction: &AccountTransaction,
+    ) -> Result<Option<Self>>;
+
+    /// Lists all transfers in the database.
+    async fn list(db: &mut DBImpl) -> Result<Vec<Self>>;
+
+    /// Gets the transaction taking the money out of its account.
+    async fn get_debit_transaction(&self, db: &mut DBImpl) -> Result<AccountTransaction>;
+
+    /// Gets the transaction putting the money into its account.
+    async fn get_credit_transaction(&self, db: &mut DBImpl) -> Result<AccountTransaction>;
+
+    /// Updates the details both sides of the transfer share, all together or
+    /// not at all, returning the updated debit and credit transactions. This
+    /// can fail if the category/subcategory combination is invalid.
+    async fn update(
+        &self,
+        db: &mut DBImpl,
+        name: &str,
+        description: &str,
+        amount: Money,
+        date: NaiveDate,
+        category: &Category,
+        subcategory: Option<&Subcategory>,
+    ) -> Result<(AccountTransaction, AccountTransaction)>;
+
+    /// Deletes the transfer from the database, along with both of its
+    /// transactions.
+    async fn delete(self, db: &mut DBImpl) -> Result<()>;
+}
+
+#[async_trait]
+impl DBAccountTransfer for AccountTransfer {
+    async fn create(
+        db: &mut DBImpl,
+        from_account: &mut Account,
+        to_account: &mut Account,
+        name: &str,
+        description: &str,
+        amount: Money,
+        institution: &Institution,
+        date: NaiveDate,
+        category: &Category,
+        subcategory: Option<&Subcategory>,
+    ) -> Result<Self> {
+        if from_account.id == to_account.id || from_account.currency != to_account.currency {
+            Err(Error::InvalidTransfer)?;
+        }
+
+        // The transaction callback cannot borrow, so it works on copies
+        let mut accounts = (from_account.clone(), to_account.clone());
+        let name = name.to_owned();
+        let description = description.to_owned();
+        let institution = institution.clone();
+        let category = category.clone();
+        let subcategory = subcategory.cloned();
+
+        let (id, (new_from_account, new_to_account)) = db
+            .transaction(|db| {
+                Box::pin(async move {
+                    let debit_transaction = AccountTransaction::create(
+                        db,
+                        &mut accounts.0,
+                        &name,
+                        &description,
+                        amount,
+                        TransactionType::Debit,
+                        &institution,
+                        date,
+                        &category,
+                        subcategory.as_ref(),
+                    )
+                    .await?;
+                    let credit_transaction = AccountTransaction::create(
+                        db,
+                        &mut accounts.1,
+                        &name,
+                        &description,
+                        amount,
+                        TransactionType::Credit,
+                        &institution,
+                        date,
+                        &category,
+                        subcategory.as_ref(),
+                    )
+                    .await?;
+
+                    let id = new_id();
+
+                    sqlx::query!(
+                        "INSERT INTO account_transfer (id, debit_transaction_id, credit_transaction_id) VALUES (?, ?, ?);",
+                        id,
+                        debit_transaction.id,
+                        credit_transaction.id
+                    )
+                    .execute(&mut **db)
+                    .await?;
+
+                    Ok::<_, backend_common::Error>((id, accounts))
+                })
+            })
+            .await?;
+
+        *from_account = new_from_account;
+        *to_account = new_to_account;
+
+        Self::get(db, &id).await.map(|x| x.unwrap())
+    }
+
+    async fn get(db: &mut DBImpl, id: &str) -> Result<Option<Self>> {
+        Ok(
+            sqlx::query_as!(Self, "SELECT * FROM account_transfer WHERE id = ?;", id)
+                .fetch_optional(&mut *db)
+                .await?,
+        )
+    }
+
+    async fn get_by_transaction(
+        db: &mut DBImpl,
+        account_transaction: &AccountTransaction,
+    ) -> Result<Option<Self>> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM account_transfer WHERE debit_transaction_id = ? OR credit_transaction_id = ?;",
+            account_transaction.id,
+            account_transaction.id
+        )
+        .fetch_optional(&mut *db)
+        .await?)
+    }
+
+    async fn list(db: &mut DBImpl) -> Result<Vec<Self>> {
+        Ok(
+            sqlx::query_as!(Self, "SELECT * FROM account_transfer ORDER BY created_at;")
+                .fetch_all(&mut *db)
+                .await?,
+        )
+    }
+
+    async fn get_debit_transaction(&self, db: &mut DBImpl) -> Result<AccountTransaction> {
+        AccountTransaction::get(db, &self.debit_transaction_id)
+            .await
+            .map(|x| x.unwrap())
+    }
+
+    async fn get_credit_transaction(&self, db: &mut DBImpl) -> Result<AccountTransaction> {
+        AccountTransaction::get(db, &self.credit_transaction_id)
+            .await
+            .map(|x| x.unwrap())
+    }
+
+    async fn update(
+        &self,
+        db: &mut DBImpl,
+        name: &str,
+        description: &str,
+        amount: Money,
+        date: NaiveDate,
+        category: &Category,
+        subcategory: Option<&Subcategory>,
+    ) -> Result<(AccountTransaction, AccountTransaction)> {
+        if let Some(given_subcategory) = subcategory {
+            if given_subcategory.category_id != category.id {
+                Err(Error::InvalidSubcategory)?;
+            }
+        }
+
+        // The transaction callback cannot borrow, so it works on copies
+        let transfer = self.clone();
+        let name = name.to_owned();
+        let description = description.to_owned();
+        let category = category.clone();
+        let subcategory = subcategory.cloned();
+
+        db.transaction(|db| {
+            Box::pin(async move {
+                // Edits to either side are made to both
+                let mut debit_transaction = transfer.get_debit_transaction(db).await?;
+                debit_transaction.set_name(db, &name).await?;
+                debit_transaction.set_description(db, &description).await?;
+                debit_transaction.set_amount(db, amount).await?;
+                debit_transaction.set_date(db, date).await?;
+                debit_transaction
+                    .set_category_and_subcategory(db, &category, subcategory.as_ref())
+                    .await?;
+                let credit_transaction = transfer.get_credit_transaction(db).await?;
+
+                Ok((debit_transaction, credit_transaction))
+            })
+        })
+        .await
+    }
+
+    async fn delete(self, db: &mut DBImpl) -> Result<()> {
+        self.get_debit_transaction(db).await?.delete(db).await
+    }
+}
+
+/// Account transfer tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DBAccount, DBCategory, DBInstitution, DBReport, TestDB};
+    use backend_common::Error as BackendError;
+
+    /// Creates a date.
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_account_transfer() {
+        // Init
+        let mut db = TestDB::new().await.unwrap();
+        let usd = Currency::default();
+        let mut checking = Account::create(&mut db, AccountType::BankAccount, "Checking", "", &usd)
+            .await
+            .unwrap();
+        let mut credit_card =
+            Account::create(&mut db, AccountType::CreditCard, "Credit card", "", &usd)
+                .await
+                .unwrap();
+        let mut euro_account = Account::create(
+            &mut db,
+            AccountType::BankAccount,
+            "Euro account",
+            "",
+            &Currency::new("EUR").unwrap(),
+        )
+        .await
+        .unwrap();
+        let institution = Institution::create(&mut db, "Bank", "").await.unwrap();
+        let transfers = Category::create(&mut db, "Transfers", "").await.unwrap();
+        let payments = Category::create(&mut db, "Payments", "").await.unwrap();
+        let salary = Category::create(&mut db, "Salary", "").await.unwrap();
+
+        // Create
+        let transfer = AccountTransfer::create(
+            &mut db,
+            &mut checking,
+            &mut credit_card,
+            "Card payment",
+            "Paying off the card",
+            Money::from_minor_units(25000),
+            &institution,
+            date(2024, 3, 5),
+            &transfers,
+            None,
+        )
+        .await
+        .unwrap();
+        let debit_transaction = transfer.get_debit_transaction(&mut db).await.unwrap();
+        let credit_transaction = transfer.get_credit_transaction(&mut db).await.unwrap();
+        assert_eq!(debit_transaction.account_id, checking.id);
+        assert_eq!(
+            debit_transaction.get_transaction_type(),
+            TransactionType::Debit
+        );
+        assert_eq!(credit_transaction.account_id, credit_card.id);
+        assert_eq!(
+            credit_transaction.get_transaction_type(),
+            TransactionType::Credit
+        );
+        for transaction in [&debit_transaction, &credit_transaction] {
+            assert_eq!(transaction.name, "Card payment");
+            assert_eq!(transaction.amount, Money::from_minor_units(25000));
+            assert_eq!(transaction.get_date(), date(2024, 3, 5));
+        }
+
+        // Transfers must be between different accounts in the same currency
+        let err = AccountTransfer::create(
+            &mut db,
+            &mut checking.clone(),
+            &mut checking,
+            "Nowhere",
+            "",
+            Money::from_minor_units(100),
+            &institution,
+            date(2024, 3, 5),
+            &transfers,
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == Error::InvalidTransfer
+        ));
+        let err = AccountTransfer::create(
+            &mut db,
+            &mut checking,
+            &mut euro_account,
+            "Abroad",
+            "",
+            Money::from_minor_units(100),
+            &institution,
+            date(2024, 3, 5),
+            &transfers,
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == Error::InvalidTransfer
+        ));
+        let mut debit_transaction_moved = debit_transaction.clone();
+        let err = debit_transaction_moved
+            .set_account(&mut db, &credit_card)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == Error::InvalidTransfer
+        ));
+
+        // Get/list
+        let transfer2 = AccountTransfer::get(&mut db, &transfer.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transfer, transfer2);
+        for transaction in [&debit_transaction, &credit_transaction] {
+            let transfer3 = AccountTransfer::get_by_transaction(&mut db, transaction)
+                .await
+                .unwrap();
+            assert_eq!(transfer3, Some(transfer.clone()));
+        }
+        assert_eq!(
+            AccountTransfer::list(&mut db).await.unwrap(),
+            vec![transfer.clone()]
+        );
+
+        // Edits to either side are made to both
+        let mut debit_transaction = debit_transaction;
+        let mut credit_transaction = credit_transaction;
+        debit_transaction
+            .set_amount(&mut db, Money::from_minor_units(30000))
+            .await
+            .unwrap();
+        credit_transaction
+            .set_date(&mut db, date(2024, 3, 6))
+            .await
+            .unwrap();
+        credit_transaction
+            .set_name(&mut db, "Card repayment")
+            .await
+            .unwrap();
+        debit_transaction
+            .set_category(&mut db, &payments)
+            .await
+            .unwrap();
+        let debit_transaction = transfer.get_debit_transaction(&mut db).await.unwrap();
+        let credit_transaction = transfer.get_credit_transaction(&mut db).await.unwrap();
+        for transaction in [&debit_transaction, &credit_transaction] {
+            assert_eq!(transaction.name, "Card repayment");
+            assert_eq!(transaction.amount, Money::from_minor_units(30000));
+            assert_eq!(transaction.get_date(), date(2024, 3, 6));
+            assert_eq!(transaction.category_id, payments.id);
+            assert!(transaction.edited_at.is_some());
+        }
+
+        // Update the transfer as a whole
+        let (debit_transaction, credit_transaction) = transfer
+            .update(
+                &mut db,
+                "Card payoff",
+                "Paying off the whole card",
+                Money::from_minor_units(32000),
+                date(2024, 3, 7),
+                &transfers,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            debit_transaction,
+            transfer.get_debit_transaction(&mut db).await.unwrap()
+        );
+        for transaction in [&debit_transaction, &credit_transaction] {
+            assert_eq!(transaction.name, "Card payoff");
+            assert_eq!(
+                transaction.description.as_deref(),
+                Some("Paying off the whole card")
+            );
+            assert_eq!(transaction.amount, Money::from_minor_units(32000));
+            assert_eq!(transaction.get_date(), date(2024, 3, 7));
+            assert_eq!(transaction.category_id, transfers.id);
+        }
+
+        // Updates take effect in full or not at all
+        let missing_category = Category {
+            id: "missing category".to_owned(),
+            ..payments.clone()
+        };
+        transfer
+            .update(
+                &mut db,
+                "Half an update",
+                "",
+                Money::from_minor_units(1),
+                date(2024, 3, 8),
+                &missing_category,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            transfer.get_debit_transaction(&mut db).await.unwrap(),
+            debit_transaction
+        );
+        assert_eq!(
+            transfer.get_credit_transaction(&mut db).await.unwrap(),
+            credit_transaction
+        );
+        let mut debit_transaction = debit_transaction;
+        debit_transaction
+            .set_category(&mut db, &missing_category)
+            .await
+            .unwrap_err();
+        assert_eq!(debit_transaction.category_id, transfers.id);
+        assert_eq!(
+            transfer.get_credit_transaction(&mut db).await.unwrap(),
+            credit_transaction
+        );
+
+        // Transfers cannot be split
+        let mut split_transaction = credit_transaction.clone();
+        let err = split_transaction
+            .set_splits(
+                &mut db,
+                &[SplitLine {
+                    amount: Money::from_minor_units(30000),
+                    category: payments.clone(),
+                    subcategory: None,
+                    memo: String::new(),
+                    tags: Vec::new(),
+                }],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == Error::SplitTransfer
+        ));
+
+        // Transfers are neither income nor expenses
+        AccountTransaction::create(
+            &mut db,
+            &mut checking,
+            "Pay",
+            "",
+            Money::from_minor_units(100000),
+            TransactionType::Credit,
+            &institution,
+            date(2024, 3, 1),
+            &salary,
+            None,
+        )
+        .await
+        .unwrap();
+        let report = Report::generate(&mut db, &usd, date(2024, 3, 1), date(2024, 3, 31))
+            .await
+            .unwrap();
+        assert_eq!(report.transactions.len(), 1);
+        assert_eq!(report.income, Money::from_minor_units(100000));
+        assert_eq!(report.expenses, Money::ZERO);
+        assert_eq!(report.categories.len(), 1);
+
+        // Deleting either side deletes both
+        credit_transaction.delete(&mut db).await.unwrap();
+        assert!(AccountTransaction::get(&mut db, &debit_transaction.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(AccountTransfer::get(&mut db, &transfer.id)
+            .await
+            .unwrap()
+            .is_none());
+        let transfer = AccountTransfer::create(
+            &mut db,
+            &mut credit_card,
+            &mut checking,
+            "Refund",
+            "",
+            Money::from_minor_units(500),
+            &institution,
+            date(2024, 3, 10),
+            &transfers,
+            None,
+        )
+        .await
+        .unwrap();
+        let debit_transaction_id = transfer.debit_transaction_id.clone();
+        let credit_transaction_id = transfer.credit_transaction_id.clone();
+        transfer.delete(&mut db).await.unwrap();
+        for id in [debit_transaction_id, credit_transaction_id] {
+            assert!(AccountTransaction::get(&mut db, &id)
+                .await
+                .unwrap()
+                .is_none());
+        }
+        assert!(AccountTransfer::list(&mut db).await.unwrap().is_empty());
+
+        // Deleting an account deletes its transfers whole
+        let mut savings = Account::create(&mut db, AccountType::BankAccount, "Savings", "", &usd)
+            .await
+            .unwrap();
+        let transfer = AccountTransfer::create(
+            &mut db,
+            &mut savings,
+            &mut checking,
+            "Closing savings",
+            "",
+            Money::from_minor_units(2000),
+            &institution,
+            date(2024, 3, 20),
+            &transfers,
+            None,
+        )
+        .await
+        .unwrap();
+        let other_transfer = AccountTransfer::create(
+            &mut db,
+            &mut checking,
+            &mut credit_card,
+            "Card payment",
+            "",
+            Money::from_minor_units(700),
+            &institution,
+            date(2024, 3, 21),
+            &transfers,
+            None,
+        )
+        .await
+        .unwrap();
+        savings.delete(&mut db).await.unwrap();
+        for id in [
+            &transfer.debit_transaction_id,
+            &transfer.credit_transaction_id,
+        ] {
+            assert!(AccountTransaction::get(&mut db, id)
+                .await
+                .unwrap()
+                .is_none());
+        }
+        assert_eq!(
+            AccountTransfer::list(&mut db).await.unwrap(),
+            vec![other_transfer]
+        );
+        let report = Report::generate(&mut db, &usd, date(2024, 3, 1), date(2024, 3, 31))
+            .await
+            .unwrap();
+        assert_eq!(report.income, Money::from_minor_units(100000));
+        assert_eq!(report.expenses, Money::ZERO);
+
+        // Clean up
+        db.delete().await.unwrap();
+    }
+}
@@ -12,7 +12,7 @@ pub trait DBBudget: Sized {
         db: &mut DBImpl,
         account: &Account,
         note: &str,
-        limit: f64,
+        limit: Money,
         timeframe: Timeframe,
         timeframe_offset: NaiveDateTime,
     ) -> Result<Self>;
@@ -33,7 +33,7 @@ pub trait DBBudget: Sized {
     async fn set_note(&mut self, db: &mut DBImpl, note: &str) -> Result<()>;
 
     /// Sets the budget limit.
-    async fn set_limit(&mut self, db: &mut DBImpl, limit: f64) -> Result<()>;
+    async fn set_limit(&mut self, db: &mut DBImpl, limit: Money) -> Result<()>;
 
     /// Sets the timeframe.
     async fn set_timeframe(&mut self, db: &mut DBImpl, timeframe: Timeframe) -> Result<()>;
@@ -55,7 +55,7 @@ impl DBBudget for Budget {
         db: &mut DBImpl,
         account: &Account,
         note: &str,
-        limit: f64,
+        limit: Money,
         timeframe: Timeframe,
         timeframe_offset: NaiveDateTime,
     ) -> Result<Self> {
@@ -63,6 +63,7 @@ impl DBBudget for Budget {
             Some(_budget) => Err(Error::BudgetAlreadyExists)?,
             None => {
                 let timeframe_name = timeframe.to_internal_name();
+                let limit = limit.minor_units();
 
                 sqlx::query!("INSERT INTO budget (account_id, note, total_limit, timeframe, timeframe_offset) VALUES (?, ?, ?, ?, ?);", account.id, note, limit, timeframe_name, timeframe_offset).execute(&mut *db).await?;
 
@@ -122,12 +123,13 @@ impl DBBudget for Budget {
         Ok(())
     }
 
-    async fn set_limit(&mut self, db: &mut DBImpl, limit: f64) -> Result<()> {
+    async fn set_limit(&mut self, db: &mut DBImpl, limit: Money) -> Result<()> {
         self.total_limit = limit;
+        let limit = limit.minor_units();
 
         sqlx::query!(
             "UPDATE budget SET total_limit = ? WHERE account_id = ?;",
-            self.total_limit,
+            limit,
             self.account_id
         )
         .execute(&mut *db)
@@ -179,6 +181,7 @@ impl DBBudget for Budget {
 
 /// Budget tests.
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::*;
     use crate::TestDB;
@@ -189,17 +192,29 @@ mod tests {
         let mut db = TestDB::new().await.unwrap();
 
         // Create
-        let account1 = Account::create(&mut db, AccountType::Investment, "My investments", "")
-            .await
-            .unwrap();
-        let account2 = Account::create(&mut db, AccountType::Property, "My property", "")
-            .await
-            .unwrap();
+        let account1 = Account::create(
+            &mut db,
+            AccountType::Investment,
+            "My investments",
+            "",
+            &Currency::default(),
+        )
+        .await
+        .unwrap();
+        let account2 = Account::create(
+            &mut db,
+            AccountType::Property,
+            "My property",
+            "",
+            &Currency::default(),
+        )
+        .await
+        .unwrap();
         let mut budget1 = Budget::create(
             &mut db,
             &account1,
             "My budget",
-            123.45,
+            Money::from_minor_units(12345),
             Timeframe::Semiannually,
             NaiveDateTime::from_timestamp_millis(0).unwrap(),
         )
@@ -209,7 +224,7 @@ mod tests {
             &mut db,
             &account1,
             "My invalid budget",
-            999.99,
+            Money::from_minor_units(99999),
             Timeframe::Biweekly,
             NaiveDateTime::from_timestamp_millis(1).unwrap(),
         )
@@ -252,9 +267,12 @@ mod tests {
         assert_eq!(budget3, budget1);
 
         // Set limit
-        budget1.set_limit(&mut db, 234.56).await.unwrap();
+        budget1
+            .set_limit(&mut db, Money::from_minor_units(23456))
+            .await
+            .unwrap();
         let budget4 = Budget::get(&mut db, &account2).await.unwrap().unwrap();
-        assert_eq!(budget4.total_limit, 234.56);
+        assert_eq!(budget4.total_limit, Money::from_minor_units(23456));
         assert_eq!(budget4, budget1);
 
         // Set timeframe
@@ -1,56 +1,12 @@
-use crate::{convert_file_name, TABLES};
+use crate::{MIGRATIONS, SCHEMA_VERSION, TABLES};
 use backend_common::*;
-use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqliteLockingMode};
+use common::ExpectedCommandError;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqliteOwnedBuf};
 use sqlx::{ConnectOptions, Connection};
-use std::error::Error as StdError;
 use std::future::Future;
-use std::io;
-use std::mem;
 use std::ops::{Deref, DerefMut};
-use std::path::Path;
 use std::pin::Pin;
-use std::result::Result as StdResult;
 use std::str::FromStr;
-use tokio::fs::{self, File, OpenOptions};
-
-/// The directory in which temporary database files are stored.
-pub(crate) const TEMP_DB_DIR: &str = "temp";
-
-/// The file extension used to identify database files.
-pub(crate) const DB_EXT: &str = "db";
-
-/// Creates the temporary database directory if it does not already exist.
-pub(crate) async fn init_temp_db_dir() -> io::Result<()> {
-    let root_path = project_root::get_project_root().unwrap();
-    let temp_db_dir = root_path.join(TEMP_DB_DIR);
-
-    if !temp_db_dir.exists() {
-        fs::create_dir(&temp_db_dir).await?;
-    }
-
-    Ok(())
-}
-
-/// Gets the path to a database file.
-pub(crate) fn get_db_path(name: &str) -> String {
-    let file_name = convert_file_name(name);
-    let root_path = project_root::get_project_root().unwrap();
-    let db_path = format!(
-        "{}/{}/{}.{}",
-        root_path.display(),
-        TEMP_DB_DIR,
-        file_name,
-        DB_EXT
-    );
-    db_path
-}
-
-/// Gets the path to a sql initialization file.
-pub(crate) fn get_sql_init_path(table: &str) -> String {
-    let root_path = project_root::get_project_root().unwrap();
-    let sql_init_path = format!("{}/db/sql/init/{}.sql", root_path.display(), table);
-    sql_init_path
-}
 
 /// The underlying database connection implementation.
 pub type DBImpl = SqliteConnection;
@@ -65,20 +21,10 @@ pub struct DB {
 }
 
 impl DB {
-    /// Checks if a database file exists.
-    pub fn exists(name: &str) -> bool {
-        let db_path = get_db_path(name);
-        Path::new(&db_path).exists()
-    }
-
-    /// Opens a database file and starts a connection.
-    pub async fn open(name: &str) -> Result<Self> {
-        init_temp_db_dir().await?;
-
-        let db_path = get_db_path(name);
-        let conn = SqliteConnectOptions::new()
-            .filename(&db_path)
-            .locking_mode(SqliteLockingMode::Exclusive)
+    /// Starts a connection to a new, empty in-memory database. The database
+    /// never touches the filesystem.
+    pub(crate) async fn connect(name: &str) -> Result<Self> {
+        let conn = SqliteConnectOptions::from_str("sqlite::memory:")?
             .connect()
             .await?;
 
@@ -88,78 +34,138 @@ impl DB {
         })
     }
 
-    /// Creates a new database file and starts a connection.
+    /// Creates a new in-memory database, initializes its tables and brings
+    /// its schema up to date.
     pub async fn create(name: &str) -> Result<Self> {
-        init_temp_db_dir().await?;
+        let mut this = Self::connect(name).await?;
+        this.init_tables().await?;
+        this.migrate().await?;
 
-        {
-            let db_path = get_db_path(name);
-            File::create(&db_path).await?;
-        }
+        Ok(this)
+    }
 
-        let mut this = Self::open(name).await?;
-        this.init_tables().await?;
+    /// Creates an in-memory database from the raw bytes of a serialized
+    /// database, as produced by [`DB::serialize`].
+    pub async fn deserialize(name: &str, data: &[u8]) -> Result<Self> {
+        let mut this = Self::connect(name).await?;
+        let data = SqliteOwnedBuf::try_from(data)?;
+        this.conn.deserialize(None, data, false).await?;
 
         Ok(this)
     }
 
-    /// Creates a new database file, giving access to the raw file via a closure for reading and writing before starting the connection.
-    pub async fn create_with<F, T, E>(name: &str, f: F) -> Result<Self, E>
-    where
-        F: FnOnce(File) -> T,
-        T: Future<Output = StdResult<(), E>>,
-        E: StdError + Send + Sync + 'static,
-    {
-        init_temp_db_dir().await?;
-
-        {
-            let db_path = get_db_path(name);
-            let file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .open(db_path)
-                .await?;
+    /// Attaches a read-only copy of another serialized database to the
+    /// connection under the given schema name, so that both can be queried
+    /// together. The schema name must be a valid identifier.
+    pub async fn attach(&mut self, schema: &str, data: &[u8]) -> Result<()> {
+        sqlx::query(&format!("ATTACH DATABASE ':memory:' AS {schema}"))
+            .execute(&mut self.conn)
+            .await?;
 
-            if let Err(err) = f(file).await {
-                return Err(Error::Other(err));
-            }
-        }
+        let data = SqliteOwnedBuf::try_from(data)?;
+        self.conn.deserialize(Some(schema), data, true).await?;
 
-        match Self::open(name).await {
-            Ok(value) => Ok(value),
-            Err(err) => Err(match err {
-                Error::Expected(err) => Error::Expected(err),
-                Error::Unexpected(err) => Error::Unexpected(err),
-                Error::Other(_) => unreachable!("`Other` variant inner error is `Infallible`"),
-            }),
-        }
+        Ok(())
     }
 
-    /// Opens a database file, creating it if it doesn't exist.
-    pub async fn open_or_create(name: &str) -> Result<Self> {
-        if Self::exists(name) {
-            Self::open(name).await
-        } else {
-            Self::create(name).await
-        }
+    /// Detaches a database attached with [`DB::attach`].
+    pub async fn detach(&mut self, schema: &str) -> Result<()> {
+        sqlx::query(&format!("DETACH DATABASE {schema}"))
+            .execute(&mut self.conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Checks that the database is a well-formed SQLite database.
+    pub async fn is_intact(&mut self) -> bool {
+        let check = sqlx::query_scalar::<_, String>("PRAGMA quick_check")
+            .fetch_one(&mut self.conn)
+            .await;
+
+        matches!(check.as_deref(), Ok("ok"))
     }
 
-    /// Initialize a database table.
-    async fn init_table(&mut self, table: &str) -> Result<()> {
-        let sql_path = get_sql_init_path(table);
-        let sql_bytes = fs::read(sql_path).await?;
-        let sql_str = String::from_utf8(sql_bytes)?;
+    /// Gets the total number of rows inserted, updated or deleted since the
+    /// connection was started.
+    pub async fn total_changes(&mut self) -> Result<u64> {
+        let changes = sqlx::query_scalar::<_, i64>("SELECT total_changes()")
+            .fetch_one(&mut self.conn)
+            .await?;
 
-        sqlx::query(&sql_str).execute(&mut **self).await?;
+        Ok(changes as u64)
+    }
+
+    /// Rebuilds the database, reclaiming the space left behind by deleted
+    /// rows. This does not count towards [`DB::total_changes`].
+    pub async fn vacuum(&mut self) -> Result<()> {
+        sqlx::query("VACUUM").execute(&mut self.conn).await?;
 
         Ok(())
     }
 
     /// Initializes all database tables.
-    async fn init_tables(&mut self) -> Result<()> {
-        for table in TABLES {
-            self.init_table(table).await?;
+    pub(crate) async fn init_tables(&mut self) -> Result<()> {
+        for (_, sql) in TABLES {
+            sqlx::query(sql).execute(&mut **self).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Gets the version of the database's schema. Databases from before
+    /// schema versions were tracked are at version 0.
+    pub async fn schema_version(&mut self) -> Result<u32> {
+        let tracked = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+        )
+        .fetch_one(&mut self.conn)
+        .await?
+            > 0;
+
+        if !tracked {
+            return Ok(0);
+        }
+
+        let version = sqlx::query_scalar::<_, i64>("SELECT version FROM schema_version")
+            .fetch_one(&mut self.conn)
+            .await?;
+
+        Ok(version as u32)
+    }
+
+    /// Checks whether the database's schema is older than the latest
+    /// version. Fails if it is newer than this version of the application
+    /// understands.
+    pub async fn needs_migration(&mut self) -> Result<bool> {
+        let version = self.schema_version().await?;
+
+        if version > SCHEMA_VERSION {
+            Err(ExpectedCommandError::UnsupportedSaveVersion)?;
+        }
+
+        Ok(version < SCHEMA_VERSION)
+    }
+
+    /// Brings the database's schema up to the latest version, running each
+    /// migration it is missing in order. Every migration runs in its own
+    /// transaction along with the version update, so a failed migration
+    /// leaves the database at the last version it reached.
+    pub async fn migrate(&mut self) -> Result<()> {
+        if !self.needs_migration().await? {
+            return Ok(());
+        }
+
+        let version = self.schema_version().await?;
+
+        for (index, (_, sql)) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let mut transaction = self.conn.begin().await?;
+            sqlx::raw_sql(sql).execute(&mut *transaction).await?;
+            sqlx::query("UPDATE schema_version SET version = ?")
+                .bind(index as i64 + 1)
+                .execute(&mut *transaction)
+                .await?;
+            transaction.commit().await?;
         }
 
         Ok(())
@@ -181,83 +187,23 @@ impl DB {
         self.conn.transaction(|conn| f(conn))
     }
 
-    /// Pauses the connection temporarily, giving access to the raw file via a
-    /// closure for reading and writing before continuing.
-    pub async fn pause_with<F, T, E>(&mut self, f: F) -> Result<(), E>
-    where
-        F: FnOnce(File) -> T,
-        T: Future<Output = StdResult<(), E>>,
-        E: StdError + Send + Sync + 'static,
-    {
-        let temp_conn = SqliteConnectOptions::from_str("sqlite::memory:")?
-            .read_only(true)
-            .connect()
-            .await?;
-
-        let conn = mem::replace(&mut self.conn, temp_conn);
-        conn.close().await?;
-
-        let db_path = get_db_path(&self.name);
-
-        {
-            let file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .open(&db_path)
-                .await?;
-
-            if let Err(err) = f(file).await {
-                return Err(Error::Other(err));
-            }
-        }
-
-        let conn = SqliteConnectOptions::new()
-            .filename(&db_path)
-            .locking_mode(SqliteLockingMode::Exclusive)
-            .connect()
-            .await?;
-
-        let temp_conn = mem::replace(&mut self.conn, conn);
-        temp_conn.close().await?;
-
-        Ok(())
+    /// Gets the name of the database.
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
-    /// Deletes the existing database file.
-    pub async fn delete(self) -> Result<()> {
-        self.conn.close().await?;
-
-        let db_path = get_db_path(&self.name);
-        fs::remove_file(&db_path).await?;
+    /// Serializes the database into its raw bytes, without interrupting the
+    /// connection.
+    pub async fn serialize(&mut self) -> Result<Vec<u8>> {
+        let data = self.conn.serialize(None).await?;
 
-        Ok(())
+        Ok(data.to_vec())
     }
 
-    /// Deletes the existing database file, giving access to the raw file via a closure for reading and writing before deleting the file.
-    pub async fn delete_with<F, T, E>(self, f: F) -> Result<(), E>
-    where
-        F: FnOnce(File) -> T,
-        T: Future<Output = StdResult<(), E>>,
-        E: StdError + Send + Sync + 'static,
-    {
+    /// Closes the connection, discarding the database.
+    pub async fn delete(self) -> Result<()> {
         self.conn.close().await?;
 
-        let db_path = get_db_path(&self.name);
-
-        {
-            let file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .open(&db_path)
-                .await?;
-
-            if let Err(err) = f(file).await {
-                return Err(Error::Other(err));
-            }
-        }
-
-        fs::remove_file(&db_path).await?;
-
         Ok(())
     }
 }
@@ -275,3 +221,89 @@ impl DerefMut for DB {
         &mut self.conn
     }
 }
+
+/// Database tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_schema_migrations() {
+        // New databases start at the latest version
+        let mut db = DB::create("test_schema_new").await.unwrap();
+        assert_eq!(db.schema_version().await.unwrap(), SCHEMA_VERSION);
+        assert!(!db.needs_migration().await.unwrap());
+        db.delete().await.unwrap();
+
+        // Databases from before schema versions were tracked are migrated
+        let mut db = DB::connect("test_schema_untracked").await.unwrap();
+        db.init_tables().await.unwrap();
+        assert_eq!(db.schema_version().await.unwrap(), 0);
+        assert!(db.needs_migration().await.unwrap());
+        db.migrate().await.unwrap();
+        assert_eq!(db.schema_version().await.unwrap(), SCHEMA_VERSION);
+        db.migrate().await.unwrap();
+        assert_eq!(db.schema_version().await.unwrap(), SCHEMA_VERSION);
+
+        // Databases from newer versions of the application are refused
+        sqlx::query("UPDATE schema_version SET version = ?")
+            .bind(SCHEMA_VERSION as i64 + 1)
+            .execute(&mut *db)
+            .await
+            .unwrap();
+        let err = db.migrate().await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Expected(ref e) if **e == ExpectedCommandError::UnsupportedSaveVersion
+        ));
+        db.delete().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_money_migration() {
+        let mut db = DB::connect("test_money_migration").await.unwrap();
+        db.init_tables().await.unwrap();
+
+        sqlx::query("INSERT INTO account (id, account_type, name) VALUES ('account', 'checking', 'Account')")
+            .execute(&mut *db)
+            .await
+            .unwrap();
+
+        // Amounts as they were stored in REAL columns, and the minor units
+        // they should become
+        let amounts = [
+            (0.1 + 0.2, 30),
+            (1.005, 101),
+            (2.675, 268),
+            (-12.34, -1234),
+            (-0.005, -1),
+            (20.0, 2000),
+            (1e12 + 0.07, 100_000_000_000_007),
+        ];
+
+        for (index, (amount, _)) in amounts.iter().enumerate() {
+            sqlx::query("INSERT INTO budget (account_id, note, total_limit, timeframe, timeframe_offset) VALUES ('account', ?, ?, 'monthly', CURRENT_TIMESTAMP)")
+                .bind(index.to_string())
+                .bind(amount)
+                .execute(&mut *db)
+                .await
+                .unwrap();
+        }
+
+        db.migrate().await.unwrap();
+
+        let limits = sqlx::query_scalar::<_, i64>(
+            "SELECT total_limit FROM budget ORDER BY CAST(note AS INTEGER)",
+        )
+        .fetch_all(&mut *db)
+        .await
+        .unwrap();
+        let expected_limits = amounts
+            .iter()
+            .map(|(_, minor_units)| *minor_units)
+            .collect::<Vec<_>>();
+        assert_eq!(limits, expected_limits);
+
+        db.delete().await.unwrap();
+    }
+}
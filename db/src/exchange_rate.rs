@@ -0,0 +1,496 @@
+use crate::DBImpl;
+use async_trait::async_trait;
+use backend_common::Result;
+use chrono::NaiveDate;
+use common::{ExpectedCommandError as Error, *};
+
+/// The format of dates in exchange rate CSV data.
+const CSV_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The column names expected in the header line of exchange rate CSV data.
+const CSV_HEADER: &[&str] = &["date", "from", "to", "rate"];
+
+/// An exchange rate parsed from a line of CSV data.
+type CsvRate = (NaiveDate, Currency, Currency, Rate);
+
+/// Parses exchange rates from CSV data, with one rate per line in the form
+/// `date,from,to,rate`, such as `2024-01-15,EUR,USD,1.0825`. The first line
+/// may be a header naming these columns. Fails on the first invalid line,
+/// reporting its line number.
+fn parse_csv(csv: &str) -> Result<Vec<CsvRate>> {
+    let csv = csv.strip_prefix('\u{feff}').unwrap_or(csv);
+    let mut rates = Vec::new();
+
+    for (index, line) in csv.lines().enumerate() {
+        let fields = line
+            .split(',')
+            .map(|field| field.trim().trim_matches('"').trim())
+            .collect::<Vec<_>>();
+
+        if fields.iter().all(|field| field.is_empty()) {
+            continue;
+        }
+
+        let is_header = fields.len() == CSV_HEADER.len()
+            && fields
+                .iter()
+                .zip(CSV_HEADER)
+                .all(|(field, column)| field.eq_ignore_ascii_case(column));
+
+        if index == 0 && is_header {
+            continue;
+        }
+
+        let rate = match fields.as_slice() {
+            [date, from, to, rate] => (|| {
+                let date = NaiveDate::parse_from_str(date, CSV_DATE_FORMAT).ok()?;
+                let from = Currency::new(from).ok()?;
+                let to = Currency::new(to).ok()?;
+                let rate = rate.parse::<Rate>().ok()?;
+                (from != to).then_some((date, from, to, rate))
+            })(),
+            _ => None,
+        };
+
+        match rate {
+            Some(rate) => rates.push(rate),
+            None => Err(Error::InvalidExchangeRateCsv { line: index + 1 })?,
+        }
+    }
+
+    Ok(rates)
+}
+
+/// The database implementation of the exchange rate model.
+#[async_trait]
+pub trait DBExchangeRate: Sized {
+    /// Sets the exchange rate between two currencies from the given date,
+    /// replacing any rate already set between them on that date. This can
+    /// fail if the currencies are the same.
+    async fn set(
+        db: &mut DBImpl,
+        from: &Currency,
+        to: &Currency,
+        rate_date: NaiveDate,
+        rate: Rate,
+    ) -> Result<Self>;
+
+    /// Gets the exchange rate between two currencies set on the given date.
+    async fn get(
+        db: &mut DBImpl,
+        from: &Currency,
+        to: &Currency,
+        rate_date: NaiveDate,
+    ) -> Result<Option<Self>>;
+
+    /// Gets the exchange rate in effect between two currencies as of the
+    /// given date, which is the latest rate set on or before it. A rate set
+    /// in the opposite direction can also be in effect.
+    async fn as_of(
+        db: &mut DBImpl,
+        from: &Currency,
+        to: &Currency,
+        date: NaiveDate,
+    ) -> Result<Option<Self>>;
+
+    /// Lists all exchange rates in the database.
+    async fn list(db: &mut DBImpl) -> Result<Vec<Self>>;
+
+    /// Imports exchange rates from CSV data, with one rate per line in the
+    /// form `date,from,to,rate`, such as `2024-01-15,EUR,USD,1.0825`. The
+    /// first line may be a header naming these columns. Nothing is imported
+    /// if any line is invalid.
+    async fn import_csv(db: &mut DBImpl, csv: &str) -> Result<Vec<Self>>;
+
+    /// Converts an amount between currencies at the exchange rate in effect
+    /// as of the given date, rounding to the nearest minor unit. This can
+    /// fail if there is no such rate.
+    async fn convert(
+        db: &mut DBImpl,
+        amount: Money,
+        from: &Currency,
+        to: &Currency,
+        date: NaiveDate,
+    ) -> Result<Money>;
+
+    /// Deletes the exchange rate from the database.
+    async fn delete(self, db: &mut DBImpl) -> Result<()>;
+}
+
+#[async_trait]
+impl DBExchangeRate for ExchangeRate {
+    async fn set(
+        db: &mut DBImpl,
+        from: &Currency,
+        to: &Currency,
+        rate_date: NaiveDate,
+        rate: Rate,
+    ) -> Result<Self> {
+        if from == to {
+            Err(Error::InvalidExchangeRate)?;
+        }
+
+        let from_code = from.code();
+        let to_code = to.code();
+        let rate_units = rate.units();
+
+        sqlx::query!(
+            "INSERT INTO exchange_rate (from_currency, to_currency, rate_date, rate) VALUES (?, ?, ?, ?) ON CONFLICT (from_currency, to_currency, rate_date) DO UPDATE SET rate = excluded.rate, created_at = CURRENT_TIMESTAMP;",
+            from_code,
+            to_code,
+            rate_date,
+            rate_units
+        )
+        .execute(&mut *db)
+        .await?;
+
+        Self::get(db, from, to, rate_date).await.map(|x| x.unwrap())
+    }
+
+    async fn get(
+        db: &mut DBImpl,
+        from: &Currency,
+        to: &Currency,
+        rate_date: NaiveDate,
+    ) -> Result<Option<Self>> {
+        let from_code = from.code();
+        let to_code = to.code();
+
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM exchange_rate WHERE from_currency = ? AND to_currency = ? AND rate_date = ?;",
+            from_code,
+            to_code,
+            rate_date
+        )
+        .fetch_optional(&mut *db)
+        .await?)
+    }
+
+    async fn as_of(
+        db: &mut DBImpl,
+        from: &Currency,
+        to: &Currency,
+        date: NaiveDate,
+    ) -> Result<Option<Self>> {
+        let from_code = from.code();
+        let to_code = to.code();
+
+        // Prefer the latest rate, and a rate in the requested direction
+        // over one in the opposite direction set on the same date
+        Ok(sqlx::query_as!(
+            Self,
+            r#"
+            SELECT * FROM exchange_rate
+            WHERE ((from_currency = ? AND to_currency = ?) OR (from_currency = ? AND to_currency = ?)) AND rate_date <= ?
+            ORDER BY rate_date DESC, from_currency = ? DESC
+            LIMIT 1;
+            "#,
+            from_code,
+            to_code,
+            to_code,
+            from_code,
+            date,
+            from_code
+        )
+        .fetch_optional(&mut *db)
+        .await?)
+    }
+
+    async fn list(db: &mut DBImpl) -> Result<Vec<Self>> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM exchange_rate ORDER BY from_currency, to_currency, rate_date DESC;"
+        )
+        .fetch_all(&mut *db)
+        .await?)
+    }
+
+    async fn import_csv(db: &mut DBImpl, csv: &str) -> Result<Vec<Self>> {
+        let csv_rates = parse_csv(csv)?;
+        let mut rates = Vec::with_capacity(csv_rates.len());
+
+        for (rate_date, from, to, rate) in csv_rates {
+            rates.push(Self::set(db, &from, &to, rate_date, rate).await?);
+        }
+
+        Ok(rates)
+    }
+
+    async fn convert(
+        db: &mut DBImpl,
+        amount: Money,
+        from: &Currency,
+        to: &Currency,
+        date: NaiveDate,
+    ) -> Result<Money> {
+        if from == to {
+            return Ok(amount);
+        }
+
+        let Some(exchange_rate) = Self::as_of(db, from, to, date).await? else {
+            Err(Error::ExchangeRateNotFound)?
+        };
+
+        let converted = if exchange_rate.from_currency == from.code() {
+            exchange_rate.rate.convert(amount)
+        } else {
+            exchange_rate.rate.convert_inverse(amount)
+        };
+
+        Ok(converted.ok_or(Error::AmountOutOfRange)?)
+    }
+
+    async fn delete(self, db: &mut DBImpl) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM exchange_rate WHERE from_currency = ? AND to_currency = ? AND rate_date = ?;",
+            self.from_currency,
+            self.to_currency,
+            self.rate_date
+        )
+        .execute(&mut *db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Exchange rate tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestDB;
+    use backend_common::Error as BackendError;
+
+    /// The amount converted in the tests.
+    const AMOUNT: Money = Money::from_minor_units(10000);
+
+    /// Creates a date.
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// Converts the test amount between currencies as of the given date.
+    async fn convert(
+        db: &mut DBImpl,
+        from: &Currency,
+        to: &Currency,
+        date: NaiveDate,
+    ) -> Result<Money> {
+        ExchangeRate::convert(db, AMOUNT, from, to, date).await
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let eur = Currency::new("EUR").unwrap();
+        let usd = Currency::new("USD").unwrap();
+
+        // Parse rates, with or without a header
+        let rates = parse_csv("\u{feff}Date,From,To,Rate\r\n2024-01-15,eur,USD,1.0825\r\n\r\n\"2024-02-01\", \"USD\", \"EUR\", 0.92\n").unwrap();
+        assert_eq!(
+            rates,
+            vec![
+                (
+                    date(2024, 1, 15),
+                    eur.clone(),
+                    usd.clone(),
+                    Rate::from(108_250_000)
+                ),
+                (
+                    date(2024, 2, 1),
+                    usd.clone(),
+                    eur.clone(),
+                    Rate::from(92_000_000)
+                ),
+            ]
+        );
+        assert_eq!(parse_csv("2024-01-15,EUR,USD,1").unwrap().len(), 1);
+        assert!(parse_csv("").unwrap().is_empty());
+
+        // Invalid lines are reported by line number
+        for (csv, line) in [
+            ("date,from,to,rate\n2024-13-01,EUR,USD,1", 2),
+            ("2024-01-15,EUR,USD,1\n2024-01-15,EURO,USD,1", 2),
+            ("2024-01-15,EUR,USD,0", 1),
+            ("2024-01-15,EUR,EUR,1", 1),
+            ("2024-01-15,EUR,USD", 1),
+            ("2024-01-15,EUR,USD,1,extra", 1),
+            ("2024-01-15,EUR,USD,1\ndate,from,to,rate", 2),
+        ] {
+            let err = parse_csv(csv).unwrap_err();
+            assert!(matches!(
+                err,
+                BackendError::Expected(ref e) if **e == Error::InvalidExchangeRateCsv { line }
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exchange_rate() {
+        // Init
+        let mut db = TestDB::new().await.unwrap();
+        let eur = Currency::new("EUR").unwrap();
+        let gbp = Currency::new("GBP").unwrap();
+        let usd = Currency::new("USD").unwrap();
+
+        // Set
+        let rate1 = ExchangeRate::set(
+            &mut db,
+            &eur,
+            &usd,
+            date(2024, 1, 1),
+            "1.10".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(rate1.get_from_currency(), eur);
+        assert_eq!(rate1.get_to_currency(), usd);
+        assert_eq!(rate1.rate.to_string(), "1.1");
+        let rate2 = ExchangeRate::set(
+            &mut db,
+            &eur,
+            &usd,
+            date(2024, 2, 1),
+            "1.20".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+        let err = ExchangeRate::set(&mut db, &eur, &eur, date(2024, 1, 1), Rate::from(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == Error::InvalidExchangeRate
+        ));
+
+        // Setting a rate again on the same date replaces it
+        let rate3 = ExchangeRate::set(
+            &mut db,
+            &eur,
+            &usd,
+            date(2024, 1, 1),
+            "1.08".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(rate3.rate.to_string(), "1.08");
+
+        // Get
+        let rate4 = ExchangeRate::get(&mut db, &eur, &usd, date(2024, 1, 1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rate4, rate3);
+        assert!(ExchangeRate::get(&mut db, &usd, &eur, date(2024, 1, 1))
+            .await
+            .unwrap()
+            .is_none());
+
+        // List
+        let rates = ExchangeRate::list(&mut db).await.unwrap();
+        assert_eq!(rates, vec![rate2.clone(), rate3.clone()]);
+
+        // Convert as of each date
+        assert_eq!(
+            convert(&mut db, &eur, &usd, date(2024, 1, 15))
+                .await
+                .unwrap(),
+            Money::from_minor_units(10800)
+        );
+        assert_eq!(
+            convert(&mut db, &eur, &usd, date(2024, 2, 1))
+                .await
+                .unwrap(),
+            Money::from_minor_units(12000)
+        );
+        assert_eq!(
+            convert(&mut db, &usd, &eur, date(2024, 3, 1))
+                .await
+                .unwrap(),
+            Money::from_minor_units(8333)
+        );
+        assert_eq!(
+            convert(&mut db, &usd, &usd, date(2020, 1, 1))
+                .await
+                .unwrap(),
+            AMOUNT
+        );
+        for (from, to, date) in [
+            (&eur, &usd, date(2023, 12, 31)),
+            (&eur, &gbp, date(2024, 1, 15)),
+        ] {
+            let err = convert(&mut db, from, to, date).await.unwrap_err();
+            assert!(matches!(
+                err,
+                BackendError::Expected(ref e) if **e == Error::ExchangeRateNotFound
+            ));
+        }
+
+        // A rate in the requested direction is preferred on the same date
+        ExchangeRate::set(
+            &mut db,
+            &usd,
+            &eur,
+            date(2024, 2, 1),
+            "0.80".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            convert(&mut db, &usd, &eur, date(2024, 3, 1))
+                .await
+                .unwrap(),
+            Money::from_minor_units(8000)
+        );
+        assert_eq!(
+            convert(&mut db, &eur, &usd, date(2024, 3, 1))
+                .await
+                .unwrap(),
+            Money::from_minor_units(12000)
+        );
+
+        // Import
+        let imported = ExchangeRate::import_csv(
+            &mut db,
+            "date,from,to,rate\n2024-01-01,GBP,USD,1.27\n2024-01-01,EUR,USD,1.09\n",
+        )
+        .await
+        .unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(
+            convert(&mut db, &gbp, &usd, date(2024, 1, 15))
+                .await
+                .unwrap(),
+            Money::from_minor_units(12700)
+        );
+        assert_eq!(
+            convert(&mut db, &eur, &usd, date(2024, 1, 15))
+                .await
+                .unwrap(),
+            Money::from_minor_units(10900)
+        );
+        let err = ExchangeRate::import_csv(&mut db, "2024-01-01,GBP,EUR,1.16\nnot a rate")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == Error::InvalidExchangeRateCsv { line: 2 }
+        ));
+        assert!(ExchangeRate::get(&mut db, &gbp, &eur, date(2024, 1, 1))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(ExchangeRate::list(&mut db).await.unwrap().len(), 4);
+
+        // Delete
+        rate2.delete(&mut db).await.unwrap();
+        assert!(ExchangeRate::get(&mut db, &eur, &usd, date(2024, 2, 1))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(ExchangeRate::list(&mut db).await.unwrap().len(), 3);
+
+        // Clean up
+        db.delete().await.unwrap();
+    }
+}
@@ -0,0 +1,1633 @@
+use backend_common::*;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use common::*;
+use crypto::*;
+use flate2::read::{ZlibDecoder, ZlibEncoder};
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Cursor, Read};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+/// The magic bytes at the start of every save file.
+pub(crate) const SAVE_MAGIC: &[u8; 8] = b"LEVELSAV";
+
+/// The current save file format version. This must be incremented whenever
+/// the layout of the header changes, and an upgrade from the previous
+/// version must be added to [`UPGRADES`].
+pub(crate) const FORMAT_VERSION: u16 = 7;
+
+/// The format version given to save files written before the header had
+/// magic bytes or a version number.
+const LEGACY_FORMAT_VERSION: u16 = 1;
+
+/// The first format version whose headers carry an authentication code.
+const AUTHENTICATED_FORMAT_VERSION: u16 = 7;
+
+/// The context used to derive the key that authenticates headers from the
+/// data encryption key.
+const HEADER_MAC_CONTEXT: &[u8] = b"level save header authentication";
+
+/// The first version of the metadata encoding within the header. Metadata
+/// that is not encrypted is still written in this version, so that versions
+/// of the application from before metadata could be encrypted can read it.
+const PLAIN_METADATA_VERSION: u32 = 1;
+
+/// The current version of the metadata encoding within the header. Metadata
+/// with encrypted fields is written in this version, so that versions of the
+/// application that cannot decrypt it refuse it rather than dropping the
+/// encrypted fields when rewriting the header.
+const METADATA_VERSION: u32 = 2;
+
+/// The largest header section that will be read. Anything larger means the
+/// file is corrupted or is not a save file.
+const MAX_HEADER_SECTION_SIZE: usize = 1 << 20; // 1 MiB
+
+/// The largest save data that will be decompressed. Anything larger means
+/// the file is corrupted or was crafted to exhaust the machine opening it.
+const MAX_DECOMPRESSED_SIZE: u64 = 1 << 30; // 1 GiB
+
+/// The identifier of the AES-256-GCM cipher over independently sealed
+/// chunks.
+const CIPHER_AES_256_GCM_CHUNKED: u8 = 1;
+
+/// The identifier of the AES-256-GCM cipher over chunks bound to their
+/// position in the stream.
+const CIPHER_AES_256_GCM_STREAM: u8 = 2;
+
+/// The identifier of uncompressed save data.
+const COMPRESSION_NONE: u8 = 0;
+
+/// The identifier of save data compressed with zlib.
+const COMPRESSION_ZLIB: u8 = 1;
+
+/// The identifier of the legacy bcrypt key derivation scheme.
+const KDF_LEGACY_BCRYPT: u8 = 0;
+
+/// The identifier of the Argon2id key derivation scheme.
+const KDF_ARGON2ID: u8 = 1;
+
+/// The most memory, in KiB, that Argon2id key derivation may be asked to use.
+/// Anything more means the header is corrupted or was crafted to exhaust the
+/// machine opening it.
+const MAX_ARGON2_MEMORY_COST: u32 = 1 << 20; // 1 GiB
+
+/// The most Argon2id iterations key derivation may be asked to run.
+const MAX_ARGON2_TIME_COST: u32 = 64;
+
+/// The highest degree of Argon2id parallelism key derivation may be asked
+/// to use.
+const MAX_ARGON2_PARALLELISM: u32 = 64;
+
+/// The identifier of a key slot unlocked with a password.
+const KEY_SLOT_PASSWORD: u8 = 0;
+
+/// The identifier of a key slot unlocked with a recovery phrase.
+const KEY_SLOT_RECOVERY_PHRASE: u8 = 1;
+
+/// The most key slots a save can have.
+pub(crate) const MAX_KEY_SLOTS: usize = 8;
+
+/// The metadata key under which legacy saves stored the key derivation
+/// algorithm.
+const LEGACY_KDF_ALGORITHM_KEY: &str = "kdf";
+
+/// The metadata key under which legacy saves stored the key derivation salt.
+const LEGACY_KDF_SALT_KEY: &str = "kdf_salt";
+
+/// The metadata key under which legacy saves stored the key derivation
+/// memory cost.
+const LEGACY_KDF_MEMORY_COST_KEY: &str = "kdf_memory_cost";
+
+/// The metadata key under which legacy saves stored the key derivation time
+/// cost.
+const LEGACY_KDF_TIME_COST_KEY: &str = "kdf_time_cost";
+
+/// The metadata key under which legacy saves stored the key derivation
+/// parallelism.
+const LEGACY_KDF_PARALLELISM_KEY: &str = "kdf_parallelism";
+
+/// The name legacy saves used to identify the Argon2id algorithm.
+const LEGACY_KDF_ARGON2ID: &str = "argon2id";
+
+/// The raw sections making up a header, before they are decoded.
+type HeaderSections = Vec<Vec<u8>>;
+
+/// An upgrade of the raw header sections from one format version to the
+/// next.
+struct Upgrade {
+    /// The version being upgraded from.
+    from: u16,
+    /// Converts the header sections of the old version into those of the
+    /// next version.
+    apply: fn(HeaderSections) -> Result<HeaderSections>,
+}
+
+/// All format upgrades, in order. Upgrades only ever rewrite the header. The
+/// encrypted data that follows it is left untouched, with the header
+/// recording how that data was encrypted.
+const UPGRADES: &[Upgrade] = &[
+    Upgrade {
+        from: 1,
+        apply: upgrade_v1_to_v2,
+    },
+    Upgrade {
+        from: 2,
+        apply: upgrade_v2_to_v3,
+    },
+    Upgrade {
+        from: 3,
+        apply: upgrade_v3_to_v4,
+    },
+    Upgrade {
+        from: 4,
+        apply: upgrade_v4_to_v5,
+    },
+    Upgrade {
+        from: 5,
+        apply: upgrade_v5_to_v6,
+    },
+    Upgrade {
+        from: 6,
+        apply: upgrade_v6_to_v7,
+    },
+];
+
+/// Gets the number of sections making up the header of a given format
+/// version.
+fn header_section_count(version: u16) -> usize {
+    match version {
+        LEGACY_FORMAT_VERSION => 1,
+        version if version < AUTHENTICATED_FORMAT_VERSION => 2,
+        _ => 3,
+    }
+}
+
+/// The cipher used to encrypt the data in a save file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SaveCipher {
+    /// AES-256-GCM, with each chunk sealed independently.
+    Aes256GcmChunked,
+    /// AES-256-GCM, with each chunk bound to its position in the stream.
+    Aes256GcmStream,
+}
+
+impl SaveCipher {
+    /// The cipher used when writing new data.
+    pub const CURRENT: Self = Self::Aes256GcmStream;
+
+    /// Gets the cipher from its identifier in the header.
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            CIPHER_AES_256_GCM_CHUNKED => Ok(Self::Aes256GcmChunked),
+            CIPHER_AES_256_GCM_STREAM => Ok(Self::Aes256GcmStream),
+            _ => Err(ExpectedCommandError::UnsupportedSaveVersion)?,
+        }
+    }
+
+    /// Gets the identifier of the cipher in the header.
+    fn to_id(self) -> u8 {
+        match self {
+            Self::Aes256GcmChunked => CIPHER_AES_256_GCM_CHUNKED,
+            Self::Aes256GcmStream => CIPHER_AES_256_GCM_STREAM,
+        }
+    }
+
+    /// Decrypts save data that was encrypted with this cipher.
+    pub async fn decrypt_file<W>(
+        self,
+        src: &mut File,
+        dest: &mut W,
+        key: &[u8; AES_KEY_SIZE],
+    ) -> Result<()>
+    where
+        W: AsyncWrite + AsyncSeek + Unpin,
+    {
+        match self {
+            Self::Aes256GcmChunked => legacy_decrypt_file(src, dest, key).await,
+            Self::Aes256GcmStream => decrypt_file(src, dest, key).await,
+        }
+    }
+
+    /// Attempts to decrypt save data that was encrypted with this cipher,
+    /// without writing the decrypted data anywhere.
+    pub async fn try_decrypt_file(self, src: &mut File, key: &[u8; AES_KEY_SIZE]) -> Result<()> {
+        match self {
+            Self::Aes256GcmChunked => legacy_try_decrypt_file(src, key).await,
+            Self::Aes256GcmStream => try_decrypt_file(src, key).await,
+        }
+    }
+}
+
+/// The compression applied to the data in a save file before it is
+/// encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SaveCompression {
+    /// The data is not compressed.
+    None,
+    /// The data is compressed with zlib.
+    Zlib,
+}
+
+impl SaveCompression {
+    /// The compression used when writing new data.
+    pub const CURRENT: Self = Self::Zlib;
+
+    /// Gets the compression from its identifier in the header.
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            COMPRESSION_NONE => Ok(Self::None),
+            COMPRESSION_ZLIB => Ok(Self::Zlib),
+            _ => Err(ExpectedCommandError::UnsupportedSaveVersion)?,
+        }
+    }
+
+    /// Gets the identifier of the compression in the header.
+    fn to_id(self) -> u8 {
+        match self {
+            Self::None => COMPRESSION_NONE,
+            Self::Zlib => COMPRESSION_ZLIB,
+        }
+    }
+
+    /// Compresses save data. Compression is CPU bound, so it is run in
+    /// [`tokio::task::spawn_blocking`].
+    pub async fn compress(self, data: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data),
+            Self::Zlib => tokio::task::spawn_blocking(move || {
+                let mut compressed = Vec::new();
+                ZlibEncoder::new(data.as_slice(), Compression::default())
+                    .read_to_end(&mut compressed)?;
+                Ok(compressed)
+            })
+            .await
+            .unwrap(),
+        }
+    }
+
+    /// Decompresses save data that was compressed with this compression.
+    /// Data that fails to decompress, or decompresses to more than
+    /// [`MAX_DECOMPRESSED_SIZE`], is treated as a corrupted save file.
+    pub async fn decompress(self, data: Vec<u8>) -> Result<Vec<u8>> {
+        self.decompress_up_to(data, MAX_DECOMPRESSED_SIZE).await
+    }
+
+    /// Decompresses save data, failing if it decompresses to more than the
+    /// given size.
+    async fn decompress_up_to(self, data: Vec<u8>, max_size: u64) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data),
+            Self::Zlib => tokio::task::spawn_blocking(move || {
+                let mut decompressed = Vec::new();
+                ZlibDecoder::new(data.as_slice())
+                    .take(max_size + 1)
+                    .read_to_end(&mut decompressed)
+                    .map_err(|_| ExpectedCommandError::InvalidSaveFile)?;
+
+                if decompressed.len() as u64 > max_size {
+                    Err(ExpectedCommandError::InvalidSaveFile)?;
+                }
+
+                Ok(decompressed)
+            })
+            .await
+            .unwrap(),
+        }
+    }
+}
+
+/// A key slot, holding a copy of the data encryption key that can be
+/// unlocked with a particular secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SaveKeySlot {
+    /// The key slot's identifier.
+    pub id: u32,
+    /// The kind of secret that unlocks the key slot.
+    pub kind: KeySlotKind,
+    /// The parameters used to derive the key encryption key from the secret.
+    pub kdf: KdfParams,
+    /// The data encryption key, wrapped with the key derived from the
+    /// secret. Slots without one belong to saves whose data is encrypted
+    /// directly with the key derived from the secret, and are always the
+    /// only slot in the save.
+    pub wrapped_key: Option<[u8; WRAPPED_KEY_SIZE]>,
+}
+
+impl SaveKeySlot {
+    /// Gets the public information on the key slot.
+    pub fn info(&self) -> KeySlot {
+        KeySlot {
+            id: self.id,
+            kind: self.kind,
+        }
+    }
+}
+
+/// A header's authentication code as it was read, along with the bytes of
+/// the header it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HeaderMac {
+    /// The bytes of the header covered by the authentication code.
+    authenticated: Vec<u8>,
+    /// The authentication code.
+    tag: Vec<u8>,
+}
+
+/// The plaintext header at the start of a save file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SaveHeader {
+    /// The cipher used to encrypt the save data.
+    pub cipher: SaveCipher,
+    /// The compression applied to the save data before it was encrypted.
+    pub compression: SaveCompression,
+    /// The key slots, at least one of which must be unlocked to decrypt the
+    /// save data.
+    pub key_slots: Vec<SaveKeySlot>,
+    /// The save's metadata. If its private fields are encrypted, they are
+    /// left empty until [`SaveHeader::unseal_metadata`] decrypts them.
+    pub metadata: SaveMetadata,
+    /// The private fields of the metadata, encrypted with the data
+    /// encryption key, if the save keeps them encrypted.
+    pub sealed_metadata: Option<Vec<u8>>,
+    /// The authentication code the header was read with, if it has one.
+    /// Headers in a format version from before they were authenticated have
+    /// none, and are authenticated the next time they are written with the
+    /// data encryption key.
+    pub mac: Option<HeaderMac>,
+}
+
+impl SaveHeader {
+    /// Reads the header from the start of a save file, upgrading it from an
+    /// older format version if necessary. On success, the file is positioned
+    /// at the start of the encrypted data.
+    pub async fn read(save_file: &mut File, save_name: &str) -> Result<Self> {
+        let (version, sections) = read_header_sections(save_file).await?;
+
+        // The authentication code covers the header as it was written, so
+        // it is taken before any upgrades. Headers in an authenticated
+        // format version always have one, even if it was left empty.
+        let mac = (version >= AUTHENTICATED_FORMAT_VERSION).then(|| HeaderMac {
+            authenticated: header_authenticated_bytes(version, &sections[0], &sections[1]),
+            tag: sections[2].clone(),
+        });
+        let sections = upgrade_header_sections(version, sections)?;
+
+        let (metadata, sealed_metadata) = decode_metadata(save_name, &sections[1])?;
+
+        let mut header = decode_header_fields(&sections[0], metadata)?;
+        header.sealed_metadata = sealed_metadata;
+        header.mac = mac;
+
+        Ok(header)
+    }
+
+    /// Sets the save's metadata. If the save keeps its metadata encrypted,
+    /// the private fields are encrypted with the data encryption key.
+    pub fn set_metadata(&mut self, metadata: SaveMetadata, key: &[u8; AES_KEY_SIZE]) -> Result<()> {
+        self.sealed_metadata = if metadata.encrypted {
+            let private_metadata = PrivateMetadata {
+                name: metadata.name.clone(),
+                description: metadata.description.clone(),
+            };
+            let private_metadata_bytes = serde_json::to_vec(&private_metadata)?;
+
+            Some(aes_encrypt(key, &private_metadata_bytes)?)
+        } else {
+            None
+        };
+        self.metadata = metadata;
+
+        Ok(())
+    }
+
+    /// Decrypts the private fields of the metadata with the data encryption
+    /// key, if they are encrypted.
+    pub fn unseal_metadata(&mut self, key: &[u8; AES_KEY_SIZE]) -> Result<()> {
+        if let Some(sealed_metadata) = &self.sealed_metadata {
+            let private_metadata = aes_decrypt(key, sealed_metadata)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<PrivateMetadata>(&bytes).ok())
+                .ok_or(ExpectedCommandError::InvalidSaveFile)?;
+
+            self.metadata.name = private_metadata.name;
+            self.metadata.description = private_metadata.description;
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the header is in a format version from before headers
+    /// were authenticated.
+    pub fn is_legacy(&self) -> bool {
+        self.mac.is_none()
+    }
+
+    /// Checks the header against its authentication code with the data
+    /// encryption key, failing if the header has been tampered with or is
+    /// corrupted, including if its authentication code is missing. Legacy
+    /// headers have nothing to check here, so they must be checked against
+    /// the data they precede instead.
+    pub fn verify_mac(&self, key: &[u8; AES_KEY_SIZE]) -> Result<()> {
+        if let Some(mac) = &self.mac {
+            let mac_key = derive_subkey(key, HEADER_MAC_CONTEXT);
+
+            if !verify_hmac_sha256(&mac_key, &mac.authenticated, &mac.tag) {
+                Err(ExpectedCommandError::SaveHeaderTampered)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decrypts the save data following the header, then decompresses it.
+    /// The file must be positioned at the start of the encrypted data.
+    pub async fn decrypt_data(&self, src: &mut File, key: &[u8; AES_KEY_SIZE]) -> Result<Vec<u8>> {
+        let mut data = Cursor::new(Vec::new());
+        self.cipher.decrypt_file(src, &mut data, key).await?;
+
+        self.compression.decompress(data.into_inner()).await
+    }
+
+    /// Writes the header to a save file in the current format version,
+    /// authenticated with the data encryption key. Without the key, which
+    /// should only happen for saves that have not been opened since before
+    /// headers were authenticated, the header is written in the last format
+    /// version without authentication instead, so it is still accepted.
+    pub async fn write(
+        &self,
+        save_file: &mut File,
+        key: Option<&[u8; AES_KEY_SIZE]>,
+    ) -> io::Result<()> {
+        let fields = encode_header_fields(self);
+        let metadata = encode_metadata(&self.metadata, self.sealed_metadata.as_deref());
+        let version = match key {
+            Some(_) => FORMAT_VERSION,
+            None => AUTHENTICATED_FORMAT_VERSION - 1,
+        };
+
+        save_file.write_all(SAVE_MAGIC).await?;
+        save_file.write_all(&version.to_be_bytes()).await?;
+        write_section(save_file, &fields).await?;
+        write_section(save_file, &metadata).await?;
+
+        if let Some(key) = key {
+            let mac_key = derive_subkey(key, HEADER_MAC_CONTEXT);
+            let authenticated = header_authenticated_bytes(version, &fields, &metadata);
+            write_section(save_file, &hmac_sha256(&mac_key, &authenticated)).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Gets the bytes of a header covered by its authentication code: the magic
+/// bytes, the format version, and the length-prefixed header fields and
+/// metadata sections.
+fn header_authenticated_bytes(version: u16, fields: &[u8], metadata: &[u8]) -> Vec<u8> {
+    let mut authenticated = SAVE_MAGIC.to_vec();
+    authenticated.extend(version.to_be_bytes());
+
+    for section in [fields, metadata] {
+        authenticated.extend((section.len() as u64).to_be_bytes());
+        authenticated.extend(section);
+    }
+
+    authenticated
+}
+
+/// Reads the raw header sections from a save file, along with the format
+/// version they were written in.
+async fn read_header_sections(save_file: &mut File) -> Result<(u16, HeaderSections)> {
+    let mut magic = [0u8; SAVE_MAGIC.len()];
+
+    let version = match save_file.read_exact(&mut magic).await {
+        Ok(_) if &magic == SAVE_MAGIC => {
+            let mut version_bytes = [0u8; 2];
+            read_header_bytes(save_file, &mut version_bytes).await?;
+            u16::from_be_bytes(version_bytes)
+        }
+        Ok(_) => {
+            save_file.rewind().await?;
+            LEGACY_FORMAT_VERSION
+        }
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            save_file.rewind().await?;
+            LEGACY_FORMAT_VERSION
+        }
+        Err(err) => Err(err)?,
+    };
+
+    if version > FORMAT_VERSION {
+        Err(ExpectedCommandError::UnsupportedSaveVersion)?;
+    }
+
+    if version < LEGACY_FORMAT_VERSION {
+        Err(ExpectedCommandError::InvalidSaveFile)?;
+    }
+
+    let mut sections = Vec::new();
+
+    for _ in 0..header_section_count(version) {
+        sections.push(read_header_section(save_file).await?);
+    }
+
+    Ok((version, sections))
+}
+
+/// Reads a single header section. Unlike [`read_section`], this refuses
+/// sections that are missing or implausibly large, since the file may not be
+/// a save file at all.
+async fn read_header_section(save_file: &mut File) -> Result<Vec<u8>> {
+    let mut size_buffer = [0u8; LEN_SIZE];
+    read_header_bytes(save_file, &mut size_buffer).await?;
+
+    let size = decode_section_size(&size_buffer);
+
+    if size > MAX_HEADER_SECTION_SIZE {
+        Err(ExpectedCommandError::InvalidSaveFile)?;
+    }
+
+    let mut section = vec![0u8; size];
+    read_header_bytes(save_file, &mut section).await?;
+
+    Ok(section)
+}
+
+/// Fills a buffer from the header, treating a premature end of file as an
+/// invalid save file.
+async fn read_header_bytes(save_file: &mut File, buffer: &mut [u8]) -> Result<()> {
+    match save_file.read_exact(buffer).await {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            Err(ExpectedCommandError::InvalidSaveFile)?
+        }
+        Err(err) => Err(err)?,
+    }
+}
+
+/// Runs all upgrades needed to bring header sections from the given format
+/// version up to the current one.
+fn upgrade_header_sections(version: u16, mut sections: HeaderSections) -> Result<HeaderSections> {
+    for upgrade in UPGRADES.iter().filter(|upgrade| upgrade.from >= version) {
+        sections = (upgrade.apply)(sections)?;
+    }
+
+    Ok(sections)
+}
+
+/// Upgrades a legacy header, which was just the metadata section, possibly
+/// with the key derivation parameters mixed in. The cipher was always
+/// AES-256-GCM over independent chunks, and a missing key derivation
+/// algorithm means the key was derived with the legacy bcrypt scheme.
+fn upgrade_v1_to_v2(sections: HeaderSections) -> Result<HeaderSections> {
+    let metadata_str = String::from_utf8(sections[0].clone())
+        .map_err(|_| ExpectedCommandError::InvalidSaveFile)?;
+    let kdf = read_legacy_kdf_params(&metadata_str)?;
+    let metadata = metadata_str
+        .split('\n')
+        .filter(|line| {
+            !line
+                .split_once('=')
+                .is_some_and(|(key, _)| key.starts_with(LEGACY_KDF_ALGORITHM_KEY))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut fields = vec![CIPHER_AES_256_GCM_CHUNKED];
+    fields.extend(encode_kdf_params(&kdf));
+
+    Ok(vec![fields, metadata.into_bytes()])
+}
+
+/// Upgrades a header from before the data was encrypted with its own random
+/// key. Such data is encrypted directly with the key derived from the
+/// password, which is recorded by the absence of a wrapped key.
+fn upgrade_v2_to_v3(mut sections: HeaderSections) -> Result<HeaderSections> {
+    sections[0].push(0);
+
+    Ok(sections)
+}
+
+/// Upgrades a header from before saves could have more than one key slot.
+/// The key derivation parameters and wrapped key become the one password
+/// slot.
+fn upgrade_v3_to_v4(mut sections: HeaderSections) -> Result<HeaderSections> {
+    if sections[0].is_empty() {
+        Err(ExpectedCommandError::InvalidSaveFile)?;
+    }
+
+    let mut slot_fields = vec![1];
+    slot_fields.extend(1u32.to_be_bytes());
+    slot_fields.push(KEY_SLOT_PASSWORD);
+    sections[0].splice(1..1, slot_fields);
+
+    Ok(sections)
+}
+
+/// Upgrades a header from before save data could be compressed. Such data
+/// was always stored uncompressed.
+fn upgrade_v4_to_v5(mut sections: HeaderSections) -> Result<HeaderSections> {
+    if sections[0].is_empty() {
+        Err(ExpectedCommandError::InvalidSaveFile)?;
+    }
+
+    sections[0].insert(1, COMPRESSION_NONE);
+
+    Ok(sections)
+}
+
+/// Upgrades a header from before the metadata was encoded as JSON, when it
+/// was made of `key=value` lines.
+fn upgrade_v5_to_v6(mut sections: HeaderSections) -> Result<HeaderSections> {
+    let metadata_str = String::from_utf8(sections[1].clone())
+        .map_err(|_| ExpectedCommandError::InvalidSaveFile)?;
+    let record = MetadataRecord::read_legacy(&metadata_str);
+    sections[1] = serde_json::to_vec(&record)?;
+
+    Ok(sections)
+}
+
+/// Upgrades a header from before it was authenticated. It is given an empty
+/// authentication code section, but is still read as a legacy header, since
+/// its authentication code is taken before upgrading. It is not
+/// authenticated until it is next written with the data encryption key.
+fn upgrade_v6_to_v7(mut sections: HeaderSections) -> Result<HeaderSections> {
+    sections.push(Vec::new());
+
+    Ok(sections)
+}
+
+/// Parses the key derivation parameters out of a legacy metadata section.
+fn read_legacy_kdf_params(metadata: &str) -> Result<KdfParams> {
+    let metadata_pairs = legacy_metadata_pairs(metadata);
+
+    match metadata_pairs
+        .get(LEGACY_KDF_ALGORITHM_KEY)
+        .map(String::as_str)
+    {
+        None => Ok(KdfParams::LegacyBcrypt),
+        Some(LEGACY_KDF_ARGON2ID) => {
+            let get_cost = |key: &str| {
+                metadata_pairs
+                    .get(key)
+                    .and_then(|value| value.parse::<u32>().ok())
+                    .ok_or(ExpectedCommandError::InvalidKeyDerivation)
+            };
+
+            let salt = metadata_pairs
+                .get(LEGACY_KDF_SALT_KEY)
+                .and_then(|value| decode_hex(value))
+                .and_then(|value| value.try_into().ok())
+                .ok_or(ExpectedCommandError::InvalidKeyDerivation)?;
+
+            let kdf = KdfParams::Argon2id {
+                salt,
+                memory_cost: get_cost(LEGACY_KDF_MEMORY_COST_KEY)?,
+                time_cost: get_cost(LEGACY_KDF_TIME_COST_KEY)?,
+                parallelism: get_cost(LEGACY_KDF_PARALLELISM_KEY)?,
+            };
+
+            if !kdf_params_in_bounds(&kdf) {
+                Err(ExpectedCommandError::InvalidKeyDerivation)?;
+            }
+
+            Ok(kdf)
+        }
+        Some(_) => Err(ExpectedCommandError::InvalidKeyDerivation)?,
+    }
+}
+
+/// Encodes bytes as a hexadecimal string.
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Decodes a hexadecimal string into bytes.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Encodes the key derivation parameters as binary header fields.
+fn encode_kdf_params(kdf: &KdfParams) -> Vec<u8> {
+    match kdf {
+        KdfParams::LegacyBcrypt => vec![KDF_LEGACY_BCRYPT],
+        KdfParams::Argon2id {
+            salt,
+            memory_cost,
+            time_cost,
+            parallelism,
+        } => {
+            let mut fields = vec![KDF_ARGON2ID];
+            fields.extend(salt);
+            fields.extend(memory_cost.to_be_bytes());
+            fields.extend(time_cost.to_be_bytes());
+            fields.extend(parallelism.to_be_bytes());
+            fields
+        }
+    }
+}
+
+/// Decodes key derivation parameters from binary header fields.
+fn decode_kdf_params(reader: &mut FieldReader) -> Result<KdfParams> {
+    let kdf = match reader.read_u8()? {
+        KDF_LEGACY_BCRYPT => KdfParams::LegacyBcrypt,
+        KDF_ARGON2ID => KdfParams::Argon2id {
+            salt: reader.read_array()?,
+            memory_cost: reader.read_u32()?,
+            time_cost: reader.read_u32()?,
+            parallelism: reader.read_u32()?,
+        },
+        _ => Err(ExpectedCommandError::UnsupportedSaveVersion)?,
+    };
+
+    if !kdf_params_in_bounds(&kdf) {
+        Err(ExpectedCommandError::InvalidSaveFile)?;
+    }
+
+    Ok(kdf)
+}
+
+/// Checks that key derivation parameters read from a header are within
+/// bounds that can be derived with in reasonable time and memory.
+fn kdf_params_in_bounds(kdf: &KdfParams) -> bool {
+    match *kdf {
+        KdfParams::LegacyBcrypt => true,
+        KdfParams::Argon2id {
+            memory_cost,
+            time_cost,
+            parallelism,
+            ..
+        } => {
+            (1..=MAX_ARGON2_MEMORY_COST).contains(&memory_cost)
+                && (1..=MAX_ARGON2_TIME_COST).contains(&time_cost)
+                && (1..=MAX_ARGON2_PARALLELISM).contains(&parallelism)
+        }
+    }
+}
+
+/// Encodes a key slot as binary header fields.
+fn encode_key_slot(key_slot: &SaveKeySlot) -> Vec<u8> {
+    let mut fields = key_slot.id.to_be_bytes().to_vec();
+
+    fields.push(match key_slot.kind {
+        KeySlotKind::Password => KEY_SLOT_PASSWORD,
+        KeySlotKind::RecoveryPhrase => KEY_SLOT_RECOVERY_PHRASE,
+    });
+    fields.extend(encode_kdf_params(&key_slot.kdf));
+
+    match &key_slot.wrapped_key {
+        Some(wrapped_key) => {
+            fields.push(1);
+            fields.extend(wrapped_key);
+        }
+        None => fields.push(0),
+    }
+
+    fields
+}
+
+/// Decodes a key slot from binary header fields.
+fn decode_key_slot(reader: &mut FieldReader) -> Result<SaveKeySlot> {
+    let id = reader.read_u32()?;
+    let kind = match reader.read_u8()? {
+        KEY_SLOT_PASSWORD => KeySlotKind::Password,
+        KEY_SLOT_RECOVERY_PHRASE => KeySlotKind::RecoveryPhrase,
+        _ => Err(ExpectedCommandError::UnsupportedSaveVersion)?,
+    };
+    let kdf = decode_kdf_params(reader)?;
+    let wrapped_key = match reader.read_u8()? {
+        0 => None,
+        1 => Some(reader.read_array()?),
+        _ => Err(ExpectedCommandError::InvalidSaveFile)?,
+    };
+
+    Ok(SaveKeySlot {
+        id,
+        kind,
+        kdf,
+        wrapped_key,
+    })
+}
+
+/// Encodes the binary header fields.
+fn encode_header_fields(header: &SaveHeader) -> Vec<u8> {
+    let mut fields = vec![
+        header.cipher.to_id(),
+        header.compression.to_id(),
+        header.key_slots.len() as u8,
+    ];
+
+    for key_slot in &header.key_slots {
+        fields.extend(encode_key_slot(key_slot));
+    }
+
+    fields
+}
+
+/// Decodes the binary header fields, combining them with the save's
+/// metadata. Any encrypted metadata is left for the caller to add.
+fn decode_header_fields(fields: &[u8], metadata: SaveMetadata) -> Result<SaveHeader> {
+    let mut reader = FieldReader::new(fields);
+
+    let cipher = SaveCipher::from_id(reader.read_u8()?)?;
+    let compression = SaveCompression::from_id(reader.read_u8()?)?;
+    let num_key_slots = usize::from(reader.read_u8()?);
+
+    if num_key_slots == 0 || num_key_slots > MAX_KEY_SLOTS {
+        Err(ExpectedCommandError::InvalidSaveFile)?;
+    }
+
+    let key_slots = (0..num_key_slots)
+        .map(|_| decode_key_slot(&mut reader))
+        .collect::<Result<Vec<_>>>()?;
+
+    reader.finish()?;
+
+    Ok(SaveHeader {
+        cipher,
+        compression,
+        key_slots,
+        metadata,
+        sealed_metadata: None,
+        mac: None,
+    })
+}
+
+/// A cursor over binary header fields, failing on malformed input.
+struct FieldReader<'a> {
+    /// The remaining unread bytes.
+    remaining: &'a [u8],
+}
+
+impl<'a> FieldReader<'a> {
+    /// Creates a new field reader.
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { remaining: bytes }
+    }
+
+    /// Reads a fixed-size array of bytes.
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.remaining.len() < N {
+            Err(ExpectedCommandError::InvalidSaveFile)?;
+        }
+
+        let (value, remaining) = self.remaining.split_at(N);
+        self.remaining = remaining;
+
+        Ok(value.try_into().unwrap())
+    }
+
+    /// Reads a single byte.
+    fn read_u8(&mut self) -> Result<u8> {
+        self.read_array::<1>().map(|value| value[0])
+    }
+
+    /// Reads a big-endian 32-bit integer.
+    fn read_u32(&mut self) -> Result<u32> {
+        self.read_array().map(u32::from_be_bytes)
+    }
+
+    /// Checks that all fields have been read.
+    fn finish(self) -> Result<()> {
+        if self.remaining.is_empty() {
+            Ok(())
+        } else {
+            Err(ExpectedCommandError::InvalidSaveFile)?
+        }
+    }
+}
+
+/// Splits a legacy metadata section into its key/value pairs. Lines without
+/// a key belong to the value before them, as legacy saves wrote values
+/// containing line breaks as they were.
+fn legacy_metadata_pairs(metadata: &str) -> HashMap<String, String> {
+    let mut pairs = HashMap::<String, String>::new();
+    let mut last_key = None;
+
+    for line in metadata.split('\n') {
+        match line.split_once('=') {
+            Some((key, value)) => {
+                pairs.insert(key.to_owned(), value.to_owned());
+                last_key = Some(key.to_owned());
+            }
+            None => {
+                if let Some(value) = last_key.as_ref().and_then(|key| pairs.get_mut(key)) {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Parses a legacy timestamp, falling back to the current time as legacy
+/// saves did when it is missing or cannot be parsed.
+fn read_legacy_timestamp(metadata_pairs: &HashMap<String, String>, key: &str) -> i64 {
+    metadata_pairs
+        .get(key)
+        .and_then(|timestamp| timestamp.parse().ok())
+        .filter(|timestamp| DateTime::from_timestamp(*timestamp, 0).is_some())
+        .unwrap_or_else(|| Utc::now().timestamp())
+}
+
+/// A save's metadata as it is encoded in the header. Fields added later must
+/// have defaults, so that metadata written before they existed can still be
+/// decoded, and fields this version does not know about are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct MetadataRecord {
+    /// The version of the metadata encoding. This only changes when the
+    /// existing fields change in a way older versions cannot read.
+    version: u32,
+    /// The save's unique identifier.
+    #[serde(default)]
+    id: String,
+    /// The name of the save, if it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// A description of the save.
+    #[serde(default)]
+    description: String,
+    /// When the save was created, in seconds since the Unix epoch.
+    created_at: i64,
+    /// When the save was last opened, in seconds since the Unix epoch.
+    last_opened_at: i64,
+    /// The hex encoded private fields, encrypted with the data encryption
+    /// key, in place of the name and description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed: Option<String>,
+}
+
+/// The private fields of a save's metadata, which are encrypted when the
+/// save keeps its metadata encrypted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct PrivateMetadata {
+    /// The name of the save.
+    name: String,
+    /// A description of the save.
+    description: String,
+}
+
+impl MetadataRecord {
+    /// Parses a legacy metadata section made of `key=value` lines. Legacy
+    /// saves were read leniently, so missing or malformed values fall back
+    /// to defaults rather than failing.
+    fn read_legacy(metadata: &str) -> Self {
+        let metadata_pairs = legacy_metadata_pairs(metadata);
+
+        Self {
+            version: PLAIN_METADATA_VERSION,
+            id: metadata_pairs.get("id").cloned().unwrap_or_default(),
+            name: metadata_pairs.get("name").cloned(),
+            description: metadata_pairs
+                .get("description")
+                .cloned()
+                .unwrap_or_default(),
+            created_at: read_legacy_timestamp(&metadata_pairs, "created_at"),
+            last_opened_at: read_legacy_timestamp(&metadata_pairs, "last_opened_at"),
+            sealed: None,
+        }
+    }
+}
+
+/// Decodes a timestamp in seconds since the Unix epoch.
+fn decode_timestamp(timestamp: i64) -> Result<NaiveDateTime> {
+    let timestamp =
+        DateTime::from_timestamp(timestamp, 0).ok_or(ExpectedCommandError::InvalidSaveFile)?;
+
+    Ok(timestamp.naive_utc())
+}
+
+/// Decodes the metadata section, using the given save name if the metadata
+/// does not record one. If the private fields are encrypted, they are left
+/// empty and returned still encrypted alongside the metadata. Fails if the
+/// section is corrupted, or was written in a newer metadata encoding version.
+fn decode_metadata(save_name: &str, metadata: &[u8]) -> Result<(SaveMetadata, Option<Vec<u8>>)> {
+    let record = serde_json::from_slice::<MetadataRecord>(metadata)
+        .map_err(|_| ExpectedCommandError::InvalidSaveFile)?;
+
+    if record.version > METADATA_VERSION {
+        Err(ExpectedCommandError::UnsupportedSaveVersion)?;
+    }
+
+    let sealed_metadata = record
+        .sealed
+        .map(|sealed| decode_hex(&sealed).ok_or(ExpectedCommandError::InvalidSaveFile))
+        .transpose()?;
+    let (name, description) = match sealed_metadata {
+        Some(_) => (String::new(), String::new()),
+        None => (
+            record.name.unwrap_or_else(|| save_name.to_owned()),
+            record.description,
+        ),
+    };
+
+    let metadata = SaveMetadata {
+        id: record.id,
+        name,
+        description,
+        created_at: decode_timestamp(record.created_at)?,
+        last_opened_at: decode_timestamp(record.last_opened_at)?,
+        encrypted: sealed_metadata.is_some(),
+    };
+
+    Ok((metadata, sealed_metadata))
+}
+
+/// Encodes the metadata section. If the private fields are encrypted, only
+/// their encrypted form is written.
+fn encode_metadata(metadata: &SaveMetadata, sealed_metadata: Option<&[u8]>) -> Vec<u8> {
+    let record = match sealed_metadata {
+        Some(sealed_metadata) => MetadataRecord {
+            version: METADATA_VERSION,
+            id: metadata.id.clone(),
+            name: None,
+            description: String::new(),
+            created_at: metadata.created_at.and_utc().timestamp(),
+            last_opened_at: metadata.last_opened_at.and_utc().timestamp(),
+            sealed: Some(encode_hex(sealed_metadata)),
+        },
+        None => MetadataRecord {
+            version: PLAIN_METADATA_VERSION,
+            id: metadata.id.clone(),
+            name: Some(metadata.name.clone()),
+            description: metadata.description.clone(),
+            created_at: metadata.created_at.and_utc().timestamp(),
+            last_opened_at: metadata.last_opened_at.and_utc().timestamp(),
+            sealed: None,
+        },
+    };
+
+    serde_json::to_vec(&record).expect("metadata should always serialize")
+}
+
+/// Writes metadata as a legacy metadata section, as saves were before the
+/// metadata was encoded as JSON.
+#[cfg(test)]
+pub(crate) fn write_legacy_metadata(metadata: &SaveMetadata) -> String {
+    [
+        format!("id={}", metadata.id),
+        format!("name={}", metadata.name),
+        format!("description={}", metadata.description),
+        format!("created_at={}", metadata.created_at.and_utc().timestamp()),
+        format!(
+            "last_opened_at={}",
+            metadata.last_opened_at.and_utc().timestamp()
+        ),
+    ]
+    .join("\n")
+}
+
+/// Save file header tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_id;
+    use std::path::PathBuf;
+
+    /// Creates a scratch file with the given contents, returning its path.
+    async fn scratch_file(contents: &[u8]) -> (PathBuf, File) {
+        let path = std::env::temp_dir().join(format!("level_header_{}", new_id()));
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .await
+            .unwrap();
+        file.write_all(contents).await.unwrap();
+        file.rewind().await.unwrap();
+        (path, file)
+    }
+
+    /// Reads a header from the given file contents.
+    async fn read_header(contents: &[u8]) -> Result<SaveHeader> {
+        let (path, mut file) = scratch_file(contents).await;
+        let header = SaveHeader::read(&mut file, "fallback name").await;
+        remove_scratch_file(path).await;
+        header
+    }
+
+    /// Removes a scratch file.
+    async fn remove_scratch_file(path: PathBuf) {
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+
+    /// Gets the expected error out of a failed read.
+    fn expected_error<T: std::fmt::Debug>(result: Result<T>) -> ExpectedCommandError {
+        match result {
+            Err(Error::Expected(err)) => *err,
+            other => panic!("expected an expected error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_header() {
+        let now = DateTime::from_timestamp(Utc::now().timestamp(), 0)
+            .unwrap()
+            .naive_utc();
+        let metadata = SaveMetadata {
+            id: new_id(),
+            name: "Header test".to_owned(),
+            description: "Header test description".to_owned(),
+            created_at: now,
+            last_opened_at: now,
+            encrypted: false,
+        };
+        let header = SaveHeader {
+            cipher: SaveCipher::CURRENT,
+            compression: SaveCompression::CURRENT,
+            key_slots: vec![
+                SaveKeySlot {
+                    id: 1,
+                    kind: KeySlotKind::Password,
+                    kdf: KdfParams::generate(),
+                    wrapped_key: Some(wrap_key(&generate_key(), &generate_key()).unwrap()),
+                },
+                SaveKeySlot {
+                    id: 3,
+                    kind: KeySlotKind::RecoveryPhrase,
+                    kdf: KdfParams::generate(),
+                    wrapped_key: Some(wrap_key(&generate_key(), &generate_key()).unwrap()),
+                },
+            ],
+            metadata: metadata.clone(),
+            sealed_metadata: None,
+            mac: None,
+        };
+
+        // Round trip, leaving the file positioned at the data
+        let (path, mut file) = scratch_file(&[]).await;
+        header.write(&mut file, None).await.unwrap();
+        file.write_all(b"data").await.unwrap();
+        file.rewind().await.unwrap();
+        let parsed = SaveHeader::read(&mut file, "fallback name").await.unwrap();
+        assert_eq!(parsed, header);
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).await.unwrap();
+        assert_eq!(data, b"data");
+        remove_scratch_file(path).await;
+
+        // Legacy format with bcrypt key derivation
+        let mut legacy = Vec::new();
+        let legacy_metadata = write_legacy_metadata(&metadata);
+        legacy.extend((legacy_metadata.len() as u64).to_be_bytes()[3..].iter());
+        legacy.extend(legacy_metadata.as_bytes());
+        let parsed = read_header(&legacy).await.unwrap();
+        assert_eq!(parsed.cipher, SaveCipher::Aes256GcmChunked);
+        assert_eq!(parsed.compression, SaveCompression::None);
+        assert_eq!(parsed.key_slots.len(), 1);
+        assert_eq!(parsed.key_slots[0].kind, KeySlotKind::Password);
+        assert_eq!(parsed.key_slots[0].kdf, KdfParams::LegacyBcrypt);
+        assert_eq!(parsed.key_slots[0].wrapped_key, None);
+        assert_eq!(parsed.metadata, metadata);
+
+        // Legacy format with Argon2id key derivation
+        let kdf = KdfParams::generate();
+        let KdfParams::Argon2id {
+            salt,
+            memory_cost,
+            time_cost,
+            parallelism,
+        } = kdf
+        else {
+            unreachable!()
+        };
+        let salt_hex = salt.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        let legacy_metadata = format!(
+            "{}\nkdf=argon2id\nkdf_salt={salt_hex}\nkdf_memory_cost={memory_cost}\nkdf_time_cost={time_cost}\nkdf_parallelism={parallelism}",
+            write_legacy_metadata(&metadata)
+        );
+        let mut legacy = Vec::new();
+        legacy.extend((legacy_metadata.len() as u64).to_be_bytes()[3..].iter());
+        legacy.extend(legacy_metadata.as_bytes());
+        let parsed = read_header(&legacy).await.unwrap();
+        assert_eq!(parsed.key_slots[0].kdf, kdf);
+        assert_eq!(parsed.metadata, metadata);
+
+        // Version 2, without a wrapped data key
+        let (path, mut file) = scratch_file(&[]).await;
+        file.write_all(SAVE_MAGIC).await.unwrap();
+        file.write_all(&2u16.to_be_bytes()).await.unwrap();
+        let mut fields = vec![CIPHER_AES_256_GCM_STREAM];
+        fields.extend(encode_kdf_params(&kdf));
+        write_section(&mut file, &fields).await.unwrap();
+        write_section(&mut file, write_legacy_metadata(&metadata).as_bytes())
+            .await
+            .unwrap();
+        file.rewind().await.unwrap();
+        let parsed = SaveHeader::read(&mut file, "fallback name").await.unwrap();
+        assert_eq!(parsed.cipher, SaveCipher::Aes256GcmStream);
+        assert_eq!(parsed.compression, SaveCompression::None);
+        assert_eq!(parsed.key_slots.len(), 1);
+        assert_eq!(parsed.key_slots[0].kdf, kdf);
+        assert_eq!(parsed.key_slots[0].wrapped_key, None);
+        assert_eq!(parsed.metadata, metadata);
+        remove_scratch_file(path).await;
+
+        // Newer format version
+        let mut newer = SAVE_MAGIC.to_vec();
+        newer.extend((FORMAT_VERSION + 1).to_be_bytes());
+        let err = expected_error(read_header(&newer).await);
+        assert_eq!(err, ExpectedCommandError::UnsupportedSaveVersion);
+
+        // Truncated header
+        let mut truncated = SAVE_MAGIC.to_vec();
+        truncated.extend(FORMAT_VERSION.to_be_bytes());
+        let err = expected_error(read_header(&truncated).await);
+        assert_eq!(err, ExpectedCommandError::InvalidSaveFile);
+
+        // Malformed header fields
+        let malformed_fields: &[&[u8]] = &[
+            &[CIPHER_AES_256_GCM_STREAM, COMPRESSION_ZLIB, 1, 0],
+            &[CIPHER_AES_256_GCM_STREAM, COMPRESSION_ZLIB, 0],
+            &[
+                CIPHER_AES_256_GCM_STREAM,
+                COMPRESSION_ZLIB,
+                MAX_KEY_SLOTS as u8 + 1,
+            ],
+        ];
+        for fields in malformed_fields {
+            let (path, mut file) = scratch_file(&[]).await;
+            file.write_all(SAVE_MAGIC).await.unwrap();
+            file.write_all(&FORMAT_VERSION.to_be_bytes()).await.unwrap();
+            write_section(&mut file, fields).await.unwrap();
+            write_section(&mut file, &encode_metadata(&metadata, None))
+                .await
+                .unwrap();
+            write_section(&mut file, &[]).await.unwrap();
+            file.rewind().await.unwrap();
+            let err = expected_error(SaveHeader::read(&mut file, "fallback name").await);
+            assert_eq!(err, ExpectedCommandError::InvalidSaveFile);
+            remove_scratch_file(path).await;
+        }
+
+        // Key derivation costs out of bounds
+        let out_of_bounds_kdfs = [
+            (
+                u32::MAX,
+                DEFAULT_ARGON2_TIME_COST,
+                DEFAULT_ARGON2_PARALLELISM,
+            ),
+            (
+                DEFAULT_ARGON2_MEMORY_COST,
+                u32::MAX,
+                DEFAULT_ARGON2_PARALLELISM,
+            ),
+            (
+                DEFAULT_ARGON2_MEMORY_COST,
+                DEFAULT_ARGON2_TIME_COST,
+                u32::MAX,
+            ),
+            (DEFAULT_ARGON2_MEMORY_COST, 0, DEFAULT_ARGON2_PARALLELISM),
+        ];
+        for (memory_cost, time_cost, parallelism) in out_of_bounds_kdfs {
+            let mut out_of_bounds_header = header.clone();
+            out_of_bounds_header.key_slots[1].kdf = KdfParams::Argon2id {
+                salt,
+                memory_cost,
+                time_cost,
+                parallelism,
+            };
+            let (path, mut file) = scratch_file(&[]).await;
+            out_of_bounds_header.write(&mut file, None).await.unwrap();
+            file.rewind().await.unwrap();
+            let err = expected_error(SaveHeader::read(&mut file, "fallback name").await);
+            assert_eq!(err, ExpectedCommandError::InvalidSaveFile);
+            remove_scratch_file(path).await;
+        }
+        let legacy_metadata = format!(
+            "{}\nkdf=argon2id\nkdf_salt={salt_hex}\nkdf_memory_cost={}\nkdf_time_cost={time_cost}\nkdf_parallelism={parallelism}",
+            write_legacy_metadata(&metadata),
+            u32::MAX
+        );
+        let mut legacy = Vec::new();
+        legacy.extend((legacy_metadata.len() as u64).to_be_bytes()[3..].iter());
+        legacy.extend(legacy_metadata.as_bytes());
+        let err = expected_error(read_header(&legacy).await);
+        assert_eq!(err, ExpectedCommandError::InvalidKeyDerivation);
+
+        // Unknown compression
+        let mut fields = encode_header_fields(&header);
+        fields[1] = u8::MAX;
+        let (path, mut file) = scratch_file(&[]).await;
+        file.write_all(SAVE_MAGIC).await.unwrap();
+        file.write_all(&FORMAT_VERSION.to_be_bytes()).await.unwrap();
+        write_section(&mut file, &fields).await.unwrap();
+        write_section(&mut file, &encode_metadata(&metadata, None))
+            .await
+            .unwrap();
+        write_section(&mut file, &[]).await.unwrap();
+        file.rewind().await.unwrap();
+        let err = expected_error(SaveHeader::read(&mut file, "fallback name").await);
+        assert_eq!(err, ExpectedCommandError::UnsupportedSaveVersion);
+        remove_scratch_file(path).await;
+
+        // Not a save file
+        let err = expected_error(read_header(b"garbage").await);
+        assert_eq!(err, ExpectedCommandError::InvalidSaveFile);
+    }
+
+    #[tokio::test]
+    async fn test_save_metadata() {
+        let now = DateTime::from_timestamp(Utc::now().timestamp(), 0)
+            .unwrap()
+            .naive_utc();
+        let metadata = SaveMetadata {
+            id: new_id(),
+            name: "Metadata = test".to_owned(),
+            description: "First line\nname=Not the name\nlast_opened_at=0".to_owned(),
+            created_at: now,
+            last_opened_at: now,
+            encrypted: false,
+        };
+
+        // Round trip, whatever the values contain
+        let (decoded, sealed_metadata) =
+            decode_metadata("fallback name", &encode_metadata(&metadata, None)).unwrap();
+        assert_eq!(decoded, metadata);
+        assert_eq!(sealed_metadata, None);
+
+        // Legacy values spanning several lines
+        let legacy_metadata = SaveMetadata {
+            description: "First line\nSecond line".to_owned(),
+            ..metadata.clone()
+        };
+        let header = SaveHeader {
+            cipher: SaveCipher::CURRENT,
+            compression: SaveCompression::CURRENT,
+            key_slots: vec![SaveKeySlot {
+                id: 1,
+                kind: KeySlotKind::Password,
+                kdf: KdfParams::generate(),
+                wrapped_key: Some(wrap_key(&generate_key(), &generate_key()).unwrap()),
+            }],
+            metadata: legacy_metadata.clone(),
+            sealed_metadata: None,
+            mac: None,
+        };
+        let (path, mut file) = scratch_file(&[]).await;
+        file.write_all(SAVE_MAGIC).await.unwrap();
+        file.write_all(&5u16.to_be_bytes()).await.unwrap();
+        write_section(&mut file, &encode_header_fields(&header))
+            .await
+            .unwrap();
+        write_section(
+            &mut file,
+            write_legacy_metadata(&legacy_metadata).as_bytes(),
+        )
+        .await
+        .unwrap();
+        file.rewind().await.unwrap();
+        let parsed = SaveHeader::read(&mut file, "fallback name").await.unwrap();
+        assert_eq!(parsed, header);
+        remove_scratch_file(path).await;
+
+        // Encrypted private fields
+        let key = generate_key();
+        let encrypted_metadata = SaveMetadata {
+            encrypted: true,
+            ..metadata.clone()
+        };
+        let mut encrypted_header = header.clone();
+        encrypted_header
+            .set_metadata(encrypted_metadata.clone(), &key)
+            .unwrap();
+        let (path, mut file) = scratch_file(&[]).await;
+        encrypted_header.write(&mut file, Some(&key)).await.unwrap();
+        file.rewind().await.unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).await.unwrap();
+        assert!(!contents
+            .windows(metadata.name.len())
+            .any(|window| window == metadata.name.as_bytes()));
+        file.rewind().await.unwrap();
+        let mut parsed = SaveHeader::read(&mut file, "fallback name").await.unwrap();
+        remove_scratch_file(path).await;
+        assert_eq!(
+            parsed.metadata,
+            SaveMetadata {
+                name: String::new(),
+                description: String::new(),
+                ..encrypted_metadata.clone()
+            }
+        );
+        assert_eq!(parsed.sealed_metadata, encrypted_header.sealed_metadata);
+        let mut wrong_key_header = parsed.clone();
+        let err = expected_error(wrong_key_header.unseal_metadata(&generate_key()));
+        assert_eq!(err, ExpectedCommandError::InvalidSaveFile);
+        let mut truncated_header = parsed.clone();
+        truncated_header.sealed_metadata = Some(vec![0]);
+        let err = expected_error(truncated_header.unseal_metadata(&key));
+        assert_eq!(err, ExpectedCommandError::InvalidSaveFile);
+        parsed.unseal_metadata(&key).unwrap();
+        assert_eq!(parsed.metadata, encrypted_metadata);
+        parsed.set_metadata(metadata.clone(), &key).unwrap();
+        assert_eq!(parsed.sealed_metadata, None);
+
+        // Missing optional fields and unknown fields
+        let (decoded, _) = decode_metadata(
+            "fallback name",
+            br#"{"version":1,"created_at":0,"last_opened_at":0,"added_later":true}"#,
+        )
+        .unwrap();
+        assert_eq!(decoded.id, "");
+        assert_eq!(decoded.name, "fallback name");
+        assert_eq!(decoded.description, "");
+        assert_eq!(decoded.created_at.and_utc().timestamp(), 0);
+
+        // Corrupted metadata
+        let corrupted: &[&[u8]] = &[
+            b"",
+            b"name=Not JSON",
+            br#"{"version":1,"created_at":0"#,
+            br#"{"version":1,"last_opened_at":0}"#,
+            br#"{"version":1,"created_at":"yesterday","last_opened_at":0}"#,
+            br#"{"version":1,"created_at":9223372036854775807,"last_opened_at":0}"#,
+        ];
+        for metadata in corrupted {
+            let err = expected_error(decode_metadata("fallback name", metadata));
+            assert_eq!(err, ExpectedCommandError::InvalidSaveFile);
+        }
+
+        // Newer metadata version
+        let newer = format!(
+            r#"{{"version":{},"created_at":0,"last_opened_at":0}}"#,
+            METADATA_VERSION + 1
+        );
+        let err = expected_error(decode_metadata("fallback name", newer.as_bytes()));
+        assert_eq!(err, ExpectedCommandError::UnsupportedSaveVersion);
+    }
+
+    #[tokio::test]
+    async fn test_save_header_authentication() {
+        let now = DateTime::from_timestamp(Utc::now().timestamp(), 0)
+            .unwrap()
+            .naive_utc();
+        let metadata = SaveMetadata {
+            id: new_id(),
+            name: "Authentication test".to_owned(),
+            description: String::new(),
+            created_at: now,
+            last_opened_at: now,
+            encrypted: false,
+        };
+        let header = SaveHeader {
+            cipher: SaveCipher::CURRENT,
+            compression: SaveCompression::CURRENT,
+            key_slots: vec![SaveKeySlot {
+                id: 1,
+                kind: KeySlotKind::Password,
+                kdf: KdfParams::generate(),
+                wrapped_key: Some(wrap_key(&generate_key(), &generate_key()).unwrap()),
+            }],
+            metadata: metadata.clone(),
+            sealed_metadata: None,
+            mac: None,
+        };
+        let key = generate_key();
+
+        // Authenticated round trip
+        let (path, mut file) = scratch_file(&[]).await;
+        header.write(&mut file, Some(&key)).await.unwrap();
+        file.rewind().await.unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).await.unwrap();
+        file.rewind().await.unwrap();
+        let parsed = SaveHeader::read(&mut file, "fallback name").await.unwrap();
+        remove_scratch_file(path).await;
+        assert!(parsed.mac.is_some());
+        assert_eq!(
+            SaveHeader {
+                mac: None,
+                ..parsed.clone()
+            },
+            header
+        );
+        parsed.verify_mac(&key).unwrap();
+        let err = expected_error(parsed.verify_mac(&generate_key()));
+        assert_eq!(err, ExpectedCommandError::SaveHeaderTampered);
+
+        // Tampered metadata
+        let created_at = now.and_utc().timestamp().to_string();
+        let offset = contents
+            .windows(created_at.len())
+            .position(|window| window == created_at.as_bytes())
+            .unwrap();
+        let mut tampered = contents.clone();
+        tampered[offset] = if tampered[offset] == b'1' { b'2' } else { b'1' };
+        let parsed = read_header(&tampered).await.unwrap();
+        let err = expected_error(parsed.verify_mac(&key));
+        assert_eq!(err, ExpectedCommandError::SaveHeaderTampered);
+
+        // Tampered header fields
+        let mut tampered = contents.clone();
+        tampered[SAVE_MAGIC.len() + 2 + LEN_SIZE + 1] = COMPRESSION_NONE;
+        let parsed = read_header(&tampered).await.unwrap();
+        let err = expected_error(parsed.verify_mac(&key));
+        assert_eq!(err, ExpectedCommandError::SaveHeaderTampered);
+
+        // Tampered authentication code
+        let mut tampered = contents;
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        let parsed = read_header(&tampered).await.unwrap();
+        let err = expected_error(parsed.verify_mac(&key));
+        assert_eq!(err, ExpectedCommandError::SaveHeaderTampered);
+
+        // Stripped authentication code
+        let (path, mut file) = scratch_file(&[]).await;
+        file.write_all(SAVE_MAGIC).await.unwrap();
+        file.write_all(&FORMAT_VERSION.to_be_bytes()).await.unwrap();
+        write_section(&mut file, &encode_header_fields(&header))
+            .await
+            .unwrap();
+        write_section(&mut file, &encode_metadata(&metadata, None))
+            .await
+            .unwrap();
+        write_section(&mut file, &[]).await.unwrap();
+        file.rewind().await.unwrap();
+        let parsed = SaveHeader::read(&mut file, "fallback name").await.unwrap();
+        remove_scratch_file(path).await;
+        assert!(!parsed.is_legacy());
+        let err = expected_error(parsed.verify_mac(&key));
+        assert_eq!(err, ExpectedCommandError::SaveHeaderTampered);
+
+        // Headers written without a key are legacy headers
+        let (path, mut file) = scratch_file(&[]).await;
+        header.write(&mut file, None).await.unwrap();
+        file.rewind().await.unwrap();
+        let mut version_bytes = [0u8; 2];
+        file.seek(io::SeekFrom::Start(SAVE_MAGIC.len() as u64))
+            .await
+            .unwrap();
+        file.read_exact(&mut version_bytes).await.unwrap();
+        assert_eq!(
+            u16::from_be_bytes(version_bytes),
+            AUTHENTICATED_FORMAT_VERSION - 1
+        );
+        file.rewind().await.unwrap();
+        let parsed = SaveHeader::read(&mut file, "fallback name").await.unwrap();
+        remove_scratch_file(path).await;
+        assert!(parsed.is_legacy());
+        assert_eq!(parsed, header);
+    }
+
+    #[tokio::test]
+    async fn test_save_compression() {
+        let data = b"level save data ".repeat(1024);
+
+        // Uncompressed data is left as is
+        let uncompressed = SaveCompression::None.compress(data.clone()).await.unwrap();
+        assert_eq!(uncompressed, data);
+        let decompressed = SaveCompression::None
+            .decompress(uncompressed)
+            .await
+            .unwrap();
+        assert_eq!(decompressed, data);
+
+        // Compressed data round trips and takes up less space
+        let compressed = SaveCompression::Zlib.compress(data.clone()).await.unwrap();
+        assert!(compressed.len() < data.len());
+        let decompressed = SaveCompression::Zlib
+            .decompress(compressed.clone())
+            .await
+            .unwrap();
+        assert_eq!(decompressed, data);
+
+        // Compressed data that decompresses to more than the limit
+        let decompressed = SaveCompression::Zlib
+            .decompress_up_to(compressed.clone(), data.len() as u64)
+            .await
+            .unwrap();
+        assert_eq!(decompressed, data);
+        let err = match SaveCompression::Zlib
+            .decompress_up_to(compressed.clone(), data.len() as u64 - 1)
+            .await
+        {
+            Err(Error::Expected(err)) => *err,
+            other => panic!("expected an expected error, got {other:?}"),
+        };
+        assert_eq!(err, ExpectedCommandError::InvalidSaveFile);
+
+        // Corrupted compressed data
+        let mut corrupted = compressed;
+        corrupted.truncate(corrupted.len() / 2);
+        let err = match SaveCompression::Zlib.decompress(corrupted).await {
+            Err(Error::Expected(err)) => *err,
+            other => panic!("expected an expected error, got {other:?}"),
+        };
+        assert_eq!(err, ExpectedCommandError::InvalidSaveFile);
+    }
+}
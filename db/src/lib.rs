@@ -5,61 +5,103 @@
 
 mod account;
 mod account_transaction;
+mod account_transaction_split;
 mod account_transaction_tag;
+mod account_transfer;
 mod budget;
 mod category;
 mod db;
+mod exchange_rate;
+mod header;
 mod id;
 mod institution;
+mod lock;
+mod recent;
 mod reminder;
+mod report;
 mod report_template;
 mod save;
 mod subcategory;
 mod tag;
+mod throttle;
 
 pub use crate::account::*;
 pub use crate::account_transaction::*;
+pub use crate::account_transaction_split::*;
 pub use crate::account_transaction_tag::*;
+pub use crate::account_transfer::*;
 pub use crate::budget::*;
 pub use crate::category::*;
 pub use crate::db::{DBImpl, DB};
+pub use crate::exchange_rate::*;
 pub use crate::institution::*;
 pub use crate::reminder::*;
+pub use crate::report::*;
 pub use crate::report_template::*;
 pub use crate::save::Save;
 pub use crate::subcategory::*;
 pub use crate::tag::*;
+pub use crate::throttle::PasswordThrottle;
 
 use crate::id::*;
 
-/// The database tables, in order.
-pub(crate) const TABLES: &[&str] = &[
-    "account",
-    "reminder",
-    "budget",
-    "category",
-    "subcategory",
-    "institution",
-    "account_transaction",
-    "tag",
-    "account_transaction_tag",
-    "report_template",
+/// The database tables, in order, each paired with its initialization script
+/// from `db/sql/init`. The scripts are embedded at compile time, so creating
+/// a database does not depend on where the application runs from.
+pub(crate) const TABLES: &[(&str, &str)] = &[
+    ("account", include_str!("../sql/init/account.sql")),
+    ("reminder", include_str!("../sql/init/reminder.sql")),
+    ("budget", include_str!("../sql/init/budget.sql")),
+    ("category", include_str!("../sql/init/category.sql")),
+    ("subcategory", include_str!("../sql/init/subcategory.sql")),
+    ("institution", include_str!("../sql/init/institution.sql")),
+    (
+        "account_transaction",
+        include_str!("../sql/init/account_transaction.sql"),
+    ),
+    ("tag", include_str!("../sql/init/tag.sql")),
+    (
+        "account_transaction_tag",
+        include_str!("../sql/init/account_transaction_tag.sql"),
+    ),
+    (
+        "report_template",
+        include_str!("../sql/init/report_template.sql"),
+    ),
 ];
 
-/// Converts a name into an acceptable file name.
-pub(crate) fn convert_file_name(name: &str) -> String {
-    name.chars()
-        .map(|c| {
-            if c.is_ascii_alphanumeric() {
-                c
-            } else if c == ' ' || c == '_' {
-                '_'
-            } else {
-                '-'
-            }
-        })
-        .collect()
-}
+/// The database schema migrations, in order, each paired with its script
+/// from `db/sql/migrations`. The scripts are embedded at compile time, so
+/// migrating does not depend on where the application runs from. Each
+/// migration brings the schema up to the version matching its position in
+/// the list, counting from 1. Databases from before schema versions were
+/// tracked are at version 0, the schema the table initialization scripts
+/// create.
+pub(crate) const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "0001_schema_version",
+        include_str!("../sql/migrations/0001_schema_version.sql"),
+    ),
+    (
+        "0002_money_minor_units",
+        include_str!("../sql/migrations/0002_money_minor_units.sql"),
+    ),
+    (
+        "0003_currencies",
+        include_str!("../sql/migrations/0003_currencies.sql"),
+    ),
+    (
+        "0004_transaction_splits",
+        include_str!("../sql/migrations/0004_transaction_splits.sql"),
+    ),
+    (
+        "0005_transfers",
+        include_str!("../sql/migrations/0005_transfers.sql"),
+    ),
+];
+
+/// The schema version of databases with every migration applied.
+pub(crate) const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
 
 #[cfg(test)]
 pub(crate) use tests::*;
@@ -70,6 +112,21 @@ mod tests {
     use backend_common::*;
     use std::ops::{Deref, DerefMut};
 
+    /// Converts a name into the file name legacy saves were given.
+    pub fn convert_file_name(name: &str) -> String {
+        name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c
+                } else if c == ' ' || c == '_' {
+                    '_'
+                } else {
+                    '-'
+                }
+            })
+            .collect()
+    }
+
     /// A test database.
     pub struct TestDB {
         /// The inner database.
@@ -0,0 +1,218 @@
+use backend_common::*;
+use chrono::{NaiveDateTime, Utc};
+use common::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// The file extension appended to a save file's path to name its lock file.
+pub(crate) const LOCK_EXT: &str = "lock";
+
+/// Gets the path to the lock file of the save file at the given path.
+pub(crate) fn get_lock_path(save_path: &str) -> String {
+    format!("{save_path}.{LOCK_EXT}")
+}
+
+/// The application instance holding a save file open, as it is recorded in
+/// the save's lock file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct LockOwner {
+    /// The identifier of the process holding the save open.
+    pid: u32,
+    /// The name of the machine the process is running on.
+    hostname: String,
+    /// When the save was locked.
+    locked_at: NaiveDateTime,
+}
+
+impl LockOwner {
+    /// Describes this process as the owner of a lock.
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            hostname: whoami::fallible::hostname().unwrap_or_default(),
+            locked_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+/// An exclusive lock on a save file, held for as long as the save is open so
+/// that two instances of the application never write over each other's
+/// changes. The lock is an OS-level advisory lock on a lock file beside the
+/// save file, which records the process holding it. The OS releases the
+/// lock if the process stops, so a lock file left behind by a crash is
+/// stale and is simply taken over.
+#[derive(Debug)]
+pub(crate) struct SaveLock {
+    /// The locked lock file.
+    file: File,
+    /// The path to the lock file.
+    path: String,
+}
+
+impl SaveLock {
+    /// Locks the save file at the given path, failing if it is already open
+    /// elsewhere.
+    pub(crate) async fn acquire(save_path: &str) -> Result<Self> {
+        let lock_path = get_lock_path(save_path);
+
+        tokio::task::spawn_blocking(move || Self::acquire_blocking(lock_path))
+            .await
+            .unwrap()
+    }
+
+    /// Locks the lock file at the given path, blocking the thread while the
+    /// file is opened and written.
+    fn acquire_blocking(lock_path: String) -> Result<Self> {
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&lock_path)?;
+
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => Err(ExpectedCommandError::SaveOpenElsewhere)?,
+                // Without advisory locks, the lock file still records who
+                // has the save open
+                Err(TryLockError::Error(err)) if err.kind() == io::ErrorKind::Unsupported => {}
+                Err(TryLockError::Error(err)) => Err(err)?,
+            }
+
+            // The previous owner may have removed the lock file between it
+            // being opened and locked here, in which case the lock is on a
+            // file no one else will look at
+            if !Self::is_locked_at(&lock_path)? {
+                continue;
+            }
+
+            let owner = LockOwner::current();
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&serde_json::to_vec(&owner)?)?;
+            file.sync_all()?;
+
+            return Ok(Self {
+                file,
+                path: lock_path,
+            });
+        }
+    }
+
+    /// Checks whether the file at the given path is the one just locked, by
+    /// trying to lock it again through a new handle.
+    fn is_locked_at(lock_path: &str) -> Result<bool> {
+        let file = match File::open(lock_path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => Err(err)?,
+        };
+
+        match file.try_lock_shared() {
+            Ok(()) => Ok(false),
+            Err(TryLockError::WouldBlock) => Ok(true),
+            Err(TryLockError::Error(err)) if err.kind() == io::ErrorKind::Unsupported => Ok(true),
+            Err(TryLockError::Error(err)) => Err(err)?,
+        }
+    }
+
+    /// Releases the lock, removing the lock file.
+    pub(crate) async fn release(self) -> Result<()> {
+        tokio::task::spawn_blocking(move || {
+            fs::remove_file(&self.path)?;
+            self.file.unlock()?;
+            Ok(())
+        })
+        .await
+        .unwrap()
+    }
+}
+
+/// Save lock tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_id, Save};
+    use std::path::Path;
+
+    /// Reads the owner recorded in the lock file at the given path.
+    fn read_owner(lock_path: &str) -> LockOwner {
+        serde_json::from_slice(&fs::read(lock_path).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_save_lock() {
+        set_data_dir(project_root::get_project_root().unwrap());
+
+        let dir = std::env::temp_dir().join(format!("level_lock_{}", new_id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let save_path = dir.join("save.level").display().to_string();
+        let lock_path = get_lock_path(&save_path);
+
+        // Only one lock can be held at a time
+        let lock = SaveLock::acquire(&save_path).await.unwrap();
+        assert_eq!(read_owner(&lock_path).pid, std::process::id());
+        let err = SaveLock::acquire(&save_path).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveOpenElsewhere)
+        );
+
+        // Releasing the lock removes the lock file
+        lock.release().await.unwrap();
+        assert!(!Path::new(&lock_path).exists());
+        let lock = SaveLock::acquire(&save_path).await.unwrap();
+        lock.release().await.unwrap();
+
+        // Lock files no longer held by anyone are stale
+        let stale_owner = LockOwner {
+            pid: u32::MAX,
+            hostname: "elsewhere".to_owned(),
+            locked_at: Utc::now().naive_utc(),
+        };
+        fs::write(&lock_path, serde_json::to_vec(&stale_owner).unwrap()).unwrap();
+        let lock = SaveLock::acquire(&save_path).await.unwrap();
+        assert_eq!(read_owner(&lock_path).pid, std::process::id());
+        lock.release().await.unwrap();
+
+        // Saves are locked while they are open
+        let password = "password123";
+        let (save, _) = Save::create_at(
+            &dir.join("locked").display().to_string(),
+            "Locked save",
+            "",
+            password,
+            false,
+        )
+        .await
+        .unwrap();
+        let save_path = save.path().to_owned();
+        let save_id = save.this_metadata().id;
+        assert!(Path::new(&get_lock_path(&save_path)).exists());
+        let err = Save::open_at(&save_path, password).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveOpenElsewhere)
+        );
+        save.close().await.unwrap();
+        assert!(!Path::new(&get_lock_path(&save_path)).exists());
+
+        // A wrong password does not leave the save locked
+        let err = Save::open_at(&save_path, "wrong password")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Expected(ref e) if **e == ExpectedCommandError::InvalidSavePassword
+        ));
+        assert!(!Path::new(&get_lock_path(&save_path)).exists());
+        let save = Save::open_at(&save_path, password).await.unwrap();
+        save.close().await.unwrap();
+
+        // Clean up
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+        tokio::fs::remove_dir_all(crate::save::get_backups_path(&save_id))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+use crate::save::*;
+use backend_common::*;
+use chrono::{NaiveDateTime, Utc};
+use common::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::io;
+use std::path::Path;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+
+/// The file in the data directory listing the recently opened saves.
+const RECENT_SAVES_FILE: &str = "recent_saves.json";
+
+/// The most saves kept in the recently opened list.
+const MAX_RECENT_SAVES: usize = 20;
+
+/// Gets the path to the file listing the recently opened saves.
+fn get_recent_saves_path() -> String {
+    format!("{}/{}", data_dir().display(), RECENT_SAVES_FILE)
+}
+
+/// An entry in the list of recently opened saves, as it is persisted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RecentSaveEntry {
+    /// The path to the save file.
+    path: String,
+    /// When the save file was last opened or created.
+    opened_at: NaiveDateTime,
+}
+
+impl Save {
+    /// Reads the list of recently opened saves, most recent first. When
+    /// there is no list yet, or it cannot be read, it is started from the
+    /// saves in the saves directory.
+    async fn read_recent_entries() -> Result<Vec<RecentSaveEntry>> {
+        let recent_saves_data = match fs::read(get_recent_saves_path()).await {
+            Ok(data) => Some(data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => Err(err)?,
+        };
+
+        if let Some(entries) = recent_saves_data
+            .and_then(|data| serde_json::from_slice::<Vec<RecentSaveEntry>>(&data).ok())
+        {
+            return Ok(entries);
+        }
+
+        let mut entries = Self::list_files()
+            .await?
+            .into_iter()
+            .map(|(path, metadata)| RecentSaveEntry {
+                path,
+                opened_at: metadata.last_opened_at,
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| Reverse(entry.opened_at));
+        entries.truncate(MAX_RECENT_SAVES);
+
+        Ok(entries)
+    }
+
+    /// Replaces the persisted list of recently opened saves.
+    async fn write_recent_entries(entries: &[RecentSaveEntry]) -> Result<()> {
+        fs::create_dir_all(data_dir()).await?;
+
+        let recent_saves_path = get_recent_saves_path();
+        let tmp_recent_saves_path = get_tmp_path(&recent_saves_path);
+        let data = serde_json::to_vec_pretty(entries)?;
+
+        let mut tmp_file = File::create(&tmp_recent_saves_path).await?;
+        tmp_file.write_all(&data).await?;
+        replace_with_tmp_file(tmp_file, &tmp_recent_saves_path, &recent_saves_path).await?;
+
+        Ok(())
+    }
+
+    /// Lists the recently opened saves, most recent first. Entries whose
+    /// save file has been moved, deleted or is no longer a valid save are
+    /// pruned from the list.
+    pub async fn recent() -> Result<Vec<RecentSave>> {
+        let entries = Self::read_recent_entries().await?;
+        let mut kept_entries = Vec::with_capacity(entries.len());
+        let mut recent_saves = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            match Self::metadata_at(&entry.path).await {
+                Ok(metadata) => {
+                    recent_saves.push(RecentSave {
+                        path: entry.path.clone(),
+                        opened_at: entry.opened_at,
+                        metadata,
+                    });
+                    kept_entries.push(entry);
+                }
+                Err(Error::Expected(_)) => {}
+                Err(err) => Err(err)?,
+            }
+        }
+
+        Self::write_recent_entries(&kept_entries).await?;
+
+        Ok(recent_saves)
+    }
+
+    /// Lists the paths in the list of recently opened saves, most recent
+    /// first, without checking that they are still saves.
+    pub(crate) async fn recent_paths() -> Result<Vec<String>> {
+        let entries = Self::read_recent_entries().await?;
+
+        Ok(entries.into_iter().map(|entry| entry.path).collect())
+    }
+
+    /// Moves the save file at the given path to the top of the list of
+    /// recently opened saves, adding it if it is not already there.
+    pub async fn add_recent(path: &str) -> Result<()> {
+        let mut entries = Self::read_recent_entries().await?;
+        entries.retain(|entry| entry.path != path);
+        entries.insert(
+            0,
+            RecentSaveEntry {
+                path: path.to_owned(),
+                opened_at: Utc::now().naive_utc(),
+            },
+        );
+        entries.truncate(MAX_RECENT_SAVES);
+
+        Self::write_recent_entries(&entries).await
+    }
+
+    /// Points entries in the list of recently opened saves at a save file's
+    /// new path after it has been moved. Nothing is done if there is no list
+    /// yet, as it will be started from the saves where they are now.
+    pub(crate) async fn move_recent(old_path: &str, new_path: &str) -> Result<()> {
+        if !Path::new(&get_recent_saves_path()).exists() {
+            return Ok(());
+        }
+
+        let mut entries = Self::read_recent_entries().await?;
+
+        for entry in &mut entries {
+            if entry.path == old_path {
+                entry.path = new_path.to_owned();
+            }
+        }
+
+        Self::write_recent_entries(&entries).await
+    }
+}
+
+/// Recent saves tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_id, DBTag};
+
+    #[tokio::test]
+    async fn test_recent_saves() {
+        set_data_dir(project_root::get_project_root().unwrap());
+
+        let password = "password123";
+        let dir = std::env::temp_dir().join(format!("level_recent_{}", new_id()));
+        fs::create_dir_all(&dir).await.unwrap();
+        let dir = dir.display().to_string();
+
+        // Create saves outside the saves directory
+        let (save1, _) = Save::create_at(
+            &format!("{dir}/recent1.{SAVE_EXT}"),
+            "Recent save 1",
+            "",
+            password,
+            false,
+        )
+        .await
+        .unwrap();
+        let path1 = save1.path().to_owned();
+        let id1 = save1.this_metadata().id;
+        save1.close().await.unwrap();
+        Save::add_recent(&path1).await.unwrap();
+        let (save2, _) = Save::create_at(
+            &format!("{dir}/recent2"),
+            "Recent save 2",
+            "",
+            password,
+            false,
+        )
+        .await
+        .unwrap();
+        let path2 = save2.path().to_owned();
+        assert_eq!(path2, format!("{dir}/recent2.{SAVE_EXT}"));
+        let id2 = save2.this_metadata().id;
+        save2.close().await.unwrap();
+        Save::add_recent(&path2).await.unwrap();
+
+        let err = Save::create_at(&path2, "Recent save 2", "", password, false)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveAlreadyExists)
+        );
+
+        // Leftovers beside recently opened saves are found too
+        let old_save_data = fs::read(&path2).await.unwrap();
+        let mut save2 = Save::open_at(&path2, password).await.unwrap();
+        let tag = Tag::create(&mut save2, "Unsaved tag", "").await.unwrap();
+        save2.close().await.unwrap();
+        fs::rename(&path2, get_tmp_path(&path2)).await.unwrap();
+        fs::write(&path2, &old_save_data).await.unwrap();
+        let leftover = Save::leftovers()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|leftover| leftover.path == path2)
+            .unwrap();
+        assert_eq!(leftover.id, id2);
+        assert_eq!(leftover.name, "Recent save 2");
+        assert_eq!(leftover.kind, LeftoverKind::UnfinishedSave);
+        assert_eq!(
+            Save::password_throttle_key_at(&leftover.path)
+                .await
+                .unwrap(),
+            std::fs::canonicalize(&path2).unwrap().display().to_string()
+        );
+        Save::recover_leftover(&leftover, password).await.unwrap();
+        assert!(!Path::new(&get_tmp_path(&path2)).exists());
+        let mut save2 = Save::open_at(&path2, password).await.unwrap();
+        assert!(Tag::get(&mut save2, &tag.id).await.unwrap().is_some());
+        save2.close().await.unwrap();
+
+        // Most recent first
+        let recent = Save::recent().await.unwrap();
+        let recent_paths = recent
+            .iter()
+            .map(|recent_save| recent_save.path.as_str())
+            .collect::<Vec<_>>();
+        let index1 = recent_paths.iter().position(|path| *path == path1).unwrap();
+        let index2 = recent_paths.iter().position(|path| *path == path2).unwrap();
+        assert!(index2 < index1);
+        assert_eq!(recent[index1].metadata.name, "Recent save 1");
+
+        // Reopening moves a save back to the top
+        let save1 = Save::open_at(&path1, password).await.unwrap();
+        save1.close().await.unwrap();
+        Save::add_recent(&path1).await.unwrap();
+        let recent = Save::recent().await.unwrap();
+        assert_eq!(recent[0].path, path1);
+
+        // Dead entries are pruned
+        fs::remove_file(&path1).await.unwrap();
+        let recent = Save::recent().await.unwrap();
+        assert!(recent.iter().all(|recent_save| recent_save.path != path1));
+        assert!(recent.iter().any(|recent_save| recent_save.path == path2));
+        let err = Save::open_at(&path1, password).await.unwrap_err();
+        assert!(matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveNotFound));
+
+        // Clean up
+        fs::remove_dir_all(&dir).await.unwrap();
+        fs::remove_dir_all(get_backups_path(&id1)).await.unwrap();
+        fs::remove_dir_all(get_backups_path(&id2)).await.unwrap();
+        let recent = Save::recent().await.unwrap();
+        assert!(recent.iter().all(|recent_save| recent_save.path != path2));
+    }
+}
@@ -163,6 +163,7 @@ impl DBReminder for Reminder {
 
 /// Reminder tests.
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::*;
     use crate::TestDB;
@@ -173,9 +174,15 @@ mod tests {
         let mut db = TestDB::new().await.unwrap();
 
         // Create
-        let account1 = Account::create(&mut db, AccountType::BankAccount, "My bank account", "")
-            .await
-            .unwrap();
+        let account1 = Account::create(
+            &mut db,
+            AccountType::BankAccount,
+            "My bank account",
+            "",
+            &Currency::default(),
+        )
+        .await
+        .unwrap();
         let mut reminder1 = Reminder::create(
             &mut db,
             &account1,
@@ -227,9 +234,15 @@ mod tests {
         assert_eq!(reminder2.get_timeframe(), Timeframe::Weekly);
 
         // Set account
-        let account4 = Account::create(&mut db, AccountType::CreditCard, "My other account", "")
-            .await
-            .unwrap();
+        let account4 = Account::create(
+            &mut db,
+            AccountType::CreditCard,
+            "My other account",
+            "",
+            &Currency::default(),
+        )
+        .await
+        .unwrap();
         reminder1.set_account(&mut db, &account4).await.unwrap();
         let account5 = reminder1.get_account(&mut db).await.unwrap();
         assert_eq!(account5, account4);
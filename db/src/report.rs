@@ -0,0 +1,394 @@
+use crate::{DBAccount, DBExchangeRate, DBImpl};
+use async_trait::async_trait;
+use backend_common::Result;
+use chrono::{Days, NaiveDate};
+use common::{ExpectedCommandError as Error, *};
+use std::collections::{BTreeMap, HashMap};
+
+/// Adds an amount to a running total, failing if the total overflows.
+fn add_to(total: &mut Money, amount: Money) -> Result<()> {
+    *total = total.checked_add(amount).ok_or(Error::AmountOutOfRange)?;
+
+    Ok(())
+}
+
+/// The database implementation of the report model.
+#[async_trait]
+pub trait DBReport: Sized {
+    /// Generates a report of the transactions dated from the start date to
+    /// the end date, inclusive, across all accounts, leaving out transfers
+    /// between accounts. Each transaction is converted into the base
+    /// currency at the exchange rate in effect as of its date, with split
+    /// transactions converted and totalled by line.
+    /// This can fail if a rate is missing.
+    async fn generate(
+        db: &mut DBImpl,
+        base_currency: &Currency,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Self>;
+}
+
+#[async_trait]
+impl DBReport for Report {
+    async fn generate(
+        db: &mut DBImpl,
+        base_currency: &Currency,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Self> {
+        let account_currencies = Account::list(db)
+            .await?
+            .into_iter()
+            .map(|account| (account.id.clone(), account.get_currency()))
+            .collect::<HashMap<_, _>>();
+
+        let start = start_date.and_hms_opt(0, 0, 0).unwrap();
+        let end = end_date
+            .checked_add_days(Days::new(1))
+            .unwrap_or(end_date)
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let transactions = sqlx::query_as!(
+            AccountTransaction,
+            "SELECT * FROM account_transaction WHERE transaction_date >= ? AND transaction_date < ? AND id NOT IN (SELECT debit_transaction_id FROM account_transfer UNION ALL SELECT credit_transaction_id FROM account_transfer) ORDER BY transaction_date, created_at;",
+            start,
+            end
+        )
+        .fetch_all(&mut *db)
+        .await?;
+
+        let mut transaction_splits = HashMap::<String, Vec<AccountTransactionSplit>>::new();
+
+        for split in sqlx::query_as!(
+            AccountTransactionSplit,
+            "SELECT * FROM account_transaction_split WHERE account_transaction_id IN (SELECT id FROM account_transaction WHERE transaction_date >= ? AND transaction_date < ?) ORDER BY rowid;",
+            start,
+            end
+        )
+        .fetch_all(&mut *db)
+        .await?
+        {
+            transaction_splits
+                .entry(split.account_transaction_id.clone())
+                .or_default()
+                .push(split);
+        }
+
+        let mut report_transactions = Vec::with_capacity(transactions.len());
+        let mut income = Money::ZERO;
+        let mut expenses = Money::ZERO;
+        let mut category_totals = BTreeMap::<String, (Money, Money)>::new();
+
+        for transaction in transactions {
+            let currency = account_currencies[&transaction.account_id].clone();
+            let date = transaction.get_date();
+            let splits = transaction_splits
+                .remove(&transaction.id)
+                .unwrap_or_default();
+
+            // Unsplit transactions are treated as a single line in their own
+            // category
+            let lines = if splits.is_empty() {
+                vec![(transaction.category_id.clone(), transaction.amount)]
+            } else {
+                splits
+                    .iter()
+                    .map(|split| (split.category_id.clone(), split.amount))
+                    .collect()
+            };
+
+            let mut base_amount = Money::ZERO;
+            let mut base_amounts = Vec::with_capacity(lines.len());
+
+            for (category_id, amount) in lines {
+                let line_base_amount =
+                    ExchangeRate::convert(db, amount, &currency, base_currency, date).await?;
+                add_to(&mut base_amount, line_base_amount)?;
+                base_amounts.push(line_base_amount);
+
+                let category_total = category_totals
+                    .entry(category_id)
+                    .or_insert((Money::ZERO, Money::ZERO));
+
+                match transaction.get_transaction_type() {
+                    TransactionType::Credit => {
+                        add_to(&mut income, line_base_amount)?;
+                        add_to(&mut category_total.0, line_base_amount)?;
+                    }
+                    TransactionType::Debit => {
+                        add_to(&mut expenses, line_base_amount)?;
+                        add_to(&mut category_total.1, line_base_amount)?;
+                    }
+                }
+            }
+
+            let splits = splits
+                .into_iter()
+                .zip(base_amounts)
+                .map(|(split, base_amount)| ReportSplit { split, base_amount })
+                .collect();
+
+            report_transactions.push(ReportTransaction {
+                transaction,
+                currency: currency.code().to_owned(),
+                base_amount,
+                splits,
+            });
+        }
+
+        let categories = category_totals
+            .into_iter()
+            .map(|(category_id, (income, expenses))| ReportCategory {
+                category_id,
+                income,
+                expenses,
+            })
+            .collect();
+
+        Ok(Self {
+            base_currency: base_currency.code().to_owned(),
+            start_date,
+            end_date,
+            transactions: report_transactions,
+            income,
+            expenses,
+            categories,
+        })
+    }
+}
+
+/// Report tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DBAccountTransaction, DBCategory, DBInstitution, TestDB};
+    use backend_common::Error as BackendError;
+
+    /// Creates a date.
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_report() {
+        // Init
+        let mut db = TestDB::new().await.unwrap();
+        let eur = Currency::new("EUR").unwrap();
+        let usd = Currency::new("USD").unwrap();
+        let usd_account = Account::create(
+            &mut db,
+            AccountType::BankAccount,
+            "Dollar account",
+            "",
+            &usd,
+        )
+        .await
+        .unwrap();
+        let eur_account =
+            Account::create(&mut db, AccountType::BankAccount, "Euro account", "", &eur)
+                .await
+                .unwrap();
+        let mut accounts = [usd_account, eur_account];
+        let institution = Institution::create(&mut db, "Bank", "").await.unwrap();
+        let salary = Category::create(&mut db, "Salary", "").await.unwrap();
+        let groceries = Category::create(&mut db, "Groceries", "").await.unwrap();
+        let household = Category::create(&mut db, "Household", "").await.unwrap();
+        ExchangeRate::set(
+            &mut db,
+            &eur,
+            &usd,
+            date(2024, 1, 1),
+            "1.10".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+        ExchangeRate::set(
+            &mut db,
+            &eur,
+            &usd,
+            date(2024, 2, 1),
+            "1.20".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+
+        // Transactions stay in their account's currency
+        let transactions = [
+            (
+                0,
+                100000,
+                TransactionType::Credit,
+                date(2024, 1, 5),
+                &salary,
+            ),
+            (
+                1,
+                5000,
+                TransactionType::Debit,
+                date(2024, 1, 20),
+                &groceries,
+            ),
+            (
+                1,
+                5000,
+                TransactionType::Debit,
+                date(2024, 2, 10),
+                &groceries,
+            ),
+            (
+                0,
+                2500,
+                TransactionType::Debit,
+                date(2024, 2, 29),
+                &groceries,
+            ),
+            (
+                0,
+                9999,
+                TransactionType::Debit,
+                date(2024, 3, 1),
+                &groceries,
+            ),
+        ];
+        let mut created_transactions = Vec::new();
+        for (account_index, amount, transaction_type, date, category) in transactions {
+            let transaction = AccountTransaction::create(
+                &mut db,
+                &mut accounts[account_index],
+                "Transaction",
+                "",
+                Money::from_minor_units(amount),
+                transaction_type,
+                &institution,
+                date,
+                category,
+                None,
+            )
+            .await
+            .unwrap();
+            assert_eq!(transaction.amount, Money::from_minor_units(amount));
+            created_transactions.push(transaction);
+        }
+
+        // Split transactions count towards the categories of their lines
+        let splits = created_transactions[1]
+            .set_splits(
+                &mut db,
+                &[
+                    SplitLine {
+                        amount: Money::from_minor_units(3001),
+                        category: groceries.clone(),
+                        subcategory: None,
+                        memo: String::new(),
+                        tags: Vec::new(),
+                    },
+                    SplitLine {
+                        amount: Money::from_minor_units(1999),
+                        category: household.clone(),
+                        subcategory: None,
+                        memo: String::new(),
+                        tags: Vec::new(),
+                    },
+                ],
+            )
+            .await
+            .unwrap();
+
+        // Amounts are converted as of each transaction's date
+        let report = Report::generate(&mut db, &usd, date(2024, 1, 1), date(2024, 2, 29))
+            .await
+            .unwrap();
+        assert_eq!(report.base_currency, "USD");
+        let base_amounts = report
+            .transactions
+            .iter()
+            .map(|x| (x.currency.as_str(), x.transaction.amount, x.base_amount))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            base_amounts,
+            vec![
+                (
+                    "USD",
+                    Money::from_minor_units(100000),
+                    Money::from_minor_units(100000)
+                ),
+                (
+                    "EUR",
+                    Money::from_minor_units(5000),
+                    Money::from_minor_units(5500)
+                ),
+                (
+                    "EUR",
+                    Money::from_minor_units(5000),
+                    Money::from_minor_units(6000)
+                ),
+                (
+                    "USD",
+                    Money::from_minor_units(2500),
+                    Money::from_minor_units(2500)
+                ),
+            ]
+        );
+        assert_eq!(
+            report.transactions[1].splits,
+            vec![
+                ReportSplit {
+                    split: splits[0].clone(),
+                    base_amount: Money::from_minor_units(3301),
+                },
+                ReportSplit {
+                    split: splits[1].clone(),
+                    base_amount: Money::from_minor_units(2199),
+                },
+            ]
+        );
+        assert!(report.transactions[0].splits.is_empty());
+        assert_eq!(report.income, Money::from_minor_units(100000));
+        assert_eq!(report.expenses, Money::from_minor_units(14000));
+        assert_eq!(report.net(), Some(Money::from_minor_units(86000)));
+        let mut expected_categories = vec![
+            ReportCategory {
+                category_id: salary.id.clone(),
+                income: Money::from_minor_units(100000),
+                expenses: Money::ZERO,
+            },
+            ReportCategory {
+                category_id: groceries.id.clone(),
+                income: Money::ZERO,
+                expenses: Money::from_minor_units(11801),
+            },
+            ReportCategory {
+                category_id: household.id.clone(),
+                income: Money::ZERO,
+                expenses: Money::from_minor_units(2199),
+            },
+        ];
+        expected_categories.sort();
+        assert_eq!(report.categories, expected_categories);
+
+        // Reports can be in any currency with rates
+        let report = Report::generate(&mut db, &eur, date(2024, 2, 1), date(2024, 2, 29))
+            .await
+            .unwrap();
+        assert_eq!(report.expenses, Money::from_minor_units(7083));
+
+        // Missing rates are reported
+        let gbp = Currency::new("GBP").unwrap();
+        let err = Report::generate(&mut db, &gbp, date(2024, 1, 1), date(2024, 1, 31))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BackendError::Expected(ref e) if **e == Error::ExchangeRateNotFound
+        ));
+        let report = Report::generate(&mut db, &gbp, date(2023, 1, 1), date(2023, 12, 31))
+            .await
+            .unwrap();
+        assert!(report.transactions.is_empty());
+        assert!(report.categories.is_empty());
+
+        // Clean up
+        db.delete().await.unwrap();
+    }
+}
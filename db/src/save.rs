@@ -1,13 +1,16 @@
-use crate::convert_file_name;
 use crate::db::*;
+use crate::header::*;
+use crate::lock::*;
+use crate::{new_id, TABLES};
 use backend_common::*;
-use chrono::{NaiveDateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use common::*;
 use crypto::*;
-use std::collections::HashMap;
+use std::cmp::Reverse;
 use std::io;
+use std::mem;
 use std::ops::{Deref, DerefMut};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::fs::{self, File};
 
 /// The directory in which database save files are stored.
@@ -19,13 +22,29 @@ pub(crate) const SAVE_EXT: &str = "level";
 /// The file extension used to identify temporary save files.
 pub(crate) const TMP_SAVE_EXT: &str = "tmp";
 
+/// The directory within the saves directory in which backups are stored.
+pub(crate) const BACKUPS_DIR: &str = "backups";
+
+/// The format of backup identifiers, which are the time the backup was taken.
+const BACKUP_ID_FORMAT: &str = "%Y%m%d%H%M%S%6f";
+
+/// The schema name a backup is attached under when comparing it with a save.
+const BACKUP_SCHEMA: &str = "backup";
+
+/// The directory in which older versions of the application kept decrypted
+/// databases while their saves were open.
+pub(crate) const LEGACY_DB_DIR: &str = "temp";
+
+/// The file extension used to identify decrypted databases left behind by
+/// older versions of the application.
+pub(crate) const LEGACY_DB_EXT: &str = "db";
+
 /// Creates the saves directory if it does not already exist.
 pub(crate) async fn init_saves_dir() -> io::Result<()> {
-    let root_path = project_root::get_project_root()?;
-    let saves_dir = root_path.join(SAVES_DIR);
+    let saves_dir = data_dir().join(SAVES_DIR);
 
     if !saves_dir.exists() {
-        fs::create_dir(&saves_dir).await?;
+        fs::create_dir_all(&saves_dir).await?;
     }
 
     Ok(())
@@ -33,109 +52,132 @@ pub(crate) async fn init_saves_dir() -> io::Result<()> {
 
 /// Gets the path to the saves directory.
 pub(crate) fn get_saves_path() -> String {
-    let root_path = project_root::get_project_root().unwrap();
-    let saves_path = format!("{}/{}", root_path.display(), SAVES_DIR);
+    let saves_path = format!("{}/{}", data_dir().display(), SAVES_DIR);
     saves_path
 }
 
-/// Gets the path to a database save file.
-pub(crate) fn get_save_path(name: &str) -> String {
-    let file_name = convert_file_name(name);
-    let root_path = project_root::get_project_root().unwrap();
-    let save_path = format!(
-        "{}/{}/{}.{}",
-        root_path.display(),
-        SAVES_DIR,
-        file_name,
-        SAVE_EXT
-    );
-    save_path
+/// Gets the path to a database save file in the saves directory.
+pub(crate) fn get_save_path(id: &str) -> String {
+    format!("{}/{}.{}", get_saves_path(), id, SAVE_EXT)
 }
 
-/// Gets the path to a temporary database save file.
-pub(crate) fn get_tmp_save_path(name: &str) -> String {
-    format!("{}.{}", get_save_path(name), TMP_SAVE_EXT)
+/// Gets the path to the temporary file a save file at the given path is
+/// written to before replacing it.
+pub(crate) fn get_tmp_path(path: &str) -> String {
+    format!("{}.{}", path, TMP_SAVE_EXT)
 }
 
-/// Reads the metadata section of a save file.
-async fn read_save_metadata(save_file: &mut File) -> io::Result<String> {
-    match read_section(save_file).await? {
-        Some(data) => {
-            String::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-        }
-        None => Ok("".to_owned()),
-    }
+/// Gets the path to a temporary database save file in the saves directory.
+pub(crate) fn get_tmp_save_path(id: &str) -> String {
+    get_tmp_path(&get_save_path(id))
 }
 
-/// Skips the metadata section of a save file.
-async fn skip_metadata(save_file: &mut File) -> io::Result<()> {
-    read_section(save_file).await?;
+/// Gets the name a save file at the given path goes by when its metadata
+/// does not say.
+fn fallback_save_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|file_stem| file_stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
 
-    Ok(())
+/// Gets the path to the directory holding the backups of a save.
+pub(crate) fn get_backups_path(save_id: &str) -> String {
+    format!("{}/{}/{}", get_saves_path(), BACKUPS_DIR, save_id)
 }
 
-/// A metadata parsing trait.
-trait Metadata {
-    /// Parse the metadata properties, using default values when necessary.
-    fn read(save_name: &str, metadata: &str) -> Self;
+/// Gets the path to a backup of a save.
+pub(crate) fn get_backup_path(save_id: &str, id: &str) -> String {
+    format!("{}/{}.{}", get_backups_path(save_id), id, SAVE_EXT)
+}
 
-    /// Output the metadata properties in save file format.
-    fn write(&self) -> String;
+/// Gets the path to the directory older versions of the application kept
+/// decrypted databases in.
+pub(crate) fn get_legacy_db_dir_path() -> String {
+    let legacy_db_dir_path = format!("{}/{}", cache_dir().display(), LEGACY_DB_DIR);
+    legacy_db_dir_path
 }
 
-impl Metadata for SaveMetadata {
-    fn read(save_name: &str, metadata: &str) -> Self {
-        let metadata_pairs = metadata
-            .split('\n')
-            .filter_map(|line| {
-                line.split_once('=')
-                    .map(|(key, value)| (key.to_owned(), value.to_owned()))
-            })
-            .collect::<HashMap<String, String>>();
-
-        let name = metadata_pairs
-            .get("name")
-            .unwrap_or(&save_name.to_owned())
-            .to_owned();
-        let description = metadata_pairs
-            .get("description")
-            .unwrap_or(&String::new())
-            .to_owned();
-        let created_at = NaiveDateTime::from_timestamp_opt(
-            metadata_pairs
-                .get("created_at")
-                .unwrap_or(&String::new())
-                .parse()
-                .unwrap_or(Utc::now().timestamp()),
-            0,
-        )
-        .unwrap_or(Utc::now().naive_utc());
-        let last_opened_at = NaiveDateTime::from_timestamp_opt(
-            metadata_pairs
-                .get("last_opened_at")
-                .unwrap_or(&String::new())
-                .parse()
-                .unwrap_or(Utc::now().timestamp()),
-            0,
-        )
-        .unwrap_or(Utc::now().naive_utc());
+/// Gets the path to a decrypted database left behind by an older version of
+/// the application.
+pub(crate) fn get_legacy_db_path(id: &str) -> String {
+    format!("{}/{}.{}", get_legacy_db_dir_path(), id, LEGACY_DB_EXT)
+}
 
-        Self {
-            name,
-            description,
-            created_at,
-            last_opened_at,
-        }
+/// Gets the path to the file holding leftover save data.
+pub(crate) fn get_leftover_path(leftover: &LeftoverSave) -> String {
+    match leftover.kind {
+        LeftoverKind::DecryptedDatabase => get_legacy_db_path(&leftover.id),
+        LeftoverKind::UnfinishedSave => get_tmp_path(&leftover.path),
     }
+}
 
-    fn write(&self) -> String {
-        let name = format!("name={}", self.name);
-        let description = format!("description={}", self.description);
-        let created_at = format!("created_at={}", self.created_at.timestamp());
-        let last_opened_at = format!("last_opened_at={}", self.last_opened_at.timestamp());
+/// Gets when a file was last modified.
+async fn file_modified_at(path: impl AsRef<Path>) -> io::Result<NaiveDateTime> {
+    let modified = fs::metadata(path).await?.modified()?;
+    Ok(DateTime::<Utc>::from(modified).naive_utc())
+}
 
-        [name, description, created_at, last_opened_at].join("\n")
+/// Replaces a file with a fully written temporary file. The temporary file is
+/// flushed to disk before being renamed over the original, and the rename is
+/// flushed too where the platform allows it, so that a crash leaves either
+/// the old or the new file in place.
+pub(crate) async fn replace_with_tmp_file(
+    tmp_file: File,
+    tmp_path: &str,
+    path: &str,
+) -> io::Result<()> {
+    tmp_file.sync_all().await?;
+    drop(tmp_file);
+
+    fs::rename(tmp_path, path).await?;
+
+    #[cfg(unix)]
+    if let Some(dir_path) = Path::new(path).parent() {
+        File::open(dir_path).await?.sync_all().await?;
     }
+
+    Ok(())
+}
+
+/// Creates a key slot holding the data encryption key, unlocked with the
+/// given secret.
+async fn new_key_slot(
+    id: u32,
+    kind: KeySlotKind,
+    secret: &str,
+    key: &[u8; AES_KEY_SIZE],
+) -> Result<SaveKeySlot> {
+    let kdf = KdfParams::generate();
+    let key_encryption_key = password_to_key(secret, &kdf).await?;
+    let wrapped_key = wrap_key(&key_encryption_key, key)?;
+
+    Ok(SaveKeySlot {
+        id,
+        kind,
+        kdf,
+        wrapped_key: Some(wrapped_key),
+    })
+}
+
+/// Gets the secret a key slot's key encryption key is derived from, given
+/// what the user entered. Returns `None` if the input could never unlock a
+/// slot of that kind.
+fn key_slot_secret(kind: KeySlotKind, secret: &str) -> Option<SecretString> {
+    match kind {
+        KeySlotKind::Password => Some(SecretString::from(secret)),
+        KeySlotKind::RecoveryPhrase => normalize_recovery_phrase(secret).map(SecretString::new),
+    }
+}
+
+/// Gets an identifier for a new key slot.
+fn next_key_slot_id(key_slots: &[SaveKeySlot]) -> u32 {
+    key_slots
+        .iter()
+        .map(|key_slot| key_slot.id)
+        .max()
+        .unwrap_or(0)
+        + 1
 }
 
 /// An encrypted database save file.
@@ -143,130 +185,455 @@ impl Metadata for SaveMetadata {
 pub struct Save {
     /// The decrypted database.
     db: DB,
-    /// The encryption key.
-    key: [u8; AES_KEY_SIZE],
+    /// The path to the save file.
+    path: String,
+    /// The data encryption key, wiped from memory when the save is closed
+    /// or dropped.
+    key: SecretKey,
+    /// The key slots, each holding the data encryption key wrapped with a
+    /// different secret.
+    key_slots: Vec<SaveKeySlot>,
     /// The save file's metadata.
     metadata: SaveMetadata,
+    /// When the save file was last written with the state of the database.
+    saved_at: NaiveDateTime,
+    /// The number of database changes that had been made when the save file
+    /// was last written.
+    saved_changes: u64,
+    /// How many backups of the save file are kept, and for how long.
+    backup_policy: BackupPolicy,
+    /// The compression applied to the data when the save file is written.
+    compression: SaveCompression,
+    /// Whether the save was opened read-only, as backups are.
+    read_only: bool,
+    /// The lock keeping the save file from being opened elsewhere. Saves
+    /// opened read-only are not locked.
+    lock: Option<SaveLock>,
 }
 
 impl Save {
-    /// Creates a new save file.
-    pub async fn create(name: &str, description: &str, password: &str) -> Result<Self> {
+    /// Creates a new save file in the saves directory, along with a recovery
+    /// phrase that can unlock it in place of the password. The recovery
+    /// phrase is returned here and cannot be retrieved again. The save is
+    /// stored under a newly generated identifier, so any number of saves can
+    /// share a name. The name and description can be kept encrypted, so that
+    /// they cannot be read until the save is unlocked.
+    pub async fn create(
+        name: &str,
+        description: &str,
+        password: &str,
+        encrypt_metadata: bool,
+    ) -> Result<(Self, String)> {
         init_saves_dir().await?;
 
-        Self::verify_does_not_exist(name)?;
+        let id = Self::new_save_id();
+        let save_path = get_save_path(&id);
 
-        let key = password_to_key(password).await;
+        Self::create_file(
+            &save_path,
+            &id,
+            name,
+            description,
+            password,
+            encrypt_metadata,
+        )
+        .await
+    }
+
+    /// Creates a new save file at the given path, along with a recovery
+    /// phrase that can unlock it in place of the password. The recovery
+    /// phrase is returned here and cannot be retrieved again. The save file
+    /// extension is added to the path if it does not already have it, and
+    /// an existing file is never replaced.
+    pub async fn create_at(
+        path: &str,
+        name: &str,
+        description: &str,
+        password: &str,
+        encrypt_metadata: bool,
+    ) -> Result<(Self, String)> {
+        let save_path = if Path::new(path).extension() == Some(SAVE_EXT.as_ref()) {
+            path.to_owned()
+        } else {
+            format!("{}.{}", path, SAVE_EXT)
+        };
+
+        if Path::new(&save_path).exists() {
+            Err(ExpectedCommandError::SaveAlreadyExists)?;
+        }
+
+        let id = new_id();
+
+        Self::create_file(
+            &save_path,
+            &id,
+            name,
+            description,
+            password,
+            encrypt_metadata,
+        )
+        .await
+    }
+
+    /// Generates an identifier for a new save in the saves directory.
+    fn new_save_id() -> String {
+        loop {
+            let id = new_id();
+
+            if !Self::exists(&id) {
+                return id;
+            }
+        }
+    }
+
+    /// Creates a new save file with the given identifier at the given path,
+    /// which must not already be taken.
+    async fn create_file(
+        path: &str,
+        id: &str,
+        name: &str,
+        description: &str,
+        password: &str,
+        encrypt_metadata: bool,
+    ) -> Result<(Self, String)> {
+        let lock = SaveLock::acquire(path).await?;
+        let key = generate_key();
+        let recovery_phrase = generate_recovery_phrase();
+        let key_slots = vec![
+            new_key_slot(1, KeySlotKind::Password, password, &key).await?,
+            new_key_slot(2, KeySlotKind::RecoveryPhrase, &recovery_phrase, &key).await?,
+        ];
         let now = Utc::now().naive_utc();
 
         let metadata = SaveMetadata {
+            id: id.to_owned(),
             name: name.to_owned(),
             description: description.to_owned(),
             created_at: now,
             last_opened_at: now,
+            encrypted: encrypt_metadata,
         };
 
-        let db = DB::create(name).await?;
-        let mut this = Self { db, key, metadata };
+        let db = DB::create(id).await?;
+        let mut this = Self {
+            db,
+            path: path.to_owned(),
+            key,
+            key_slots,
+            metadata,
+            saved_at: now,
+            saved_changes: 0,
+            backup_policy: BackupPolicy::default(),
+            compression: SaveCompression::CURRENT,
+            read_only: false,
+            lock: Some(lock),
+        };
         this.save().await?;
 
-        Ok(this)
+        Ok((this, recovery_phrase))
+    }
+
+    /// Decrypts a save file or backup, using either its password or any
+    /// other secret held by one of its key slots. Returns the file's header,
+    /// the index of the key slot that was unlocked, the key the data was
+    /// encrypted with, and the decrypted database.
+    async fn decrypt(
+        path: &str,
+        name: &str,
+        password: &str,
+    ) -> Result<(SaveHeader, usize, SecretKey, DB)> {
+        let mut save_file = File::open(path).await?;
+        let mut header = SaveHeader::read(&mut save_file, name).await?;
+        let (slot_index, file_key) = Self::unlock(&header, password).await?;
+        header.unseal_metadata(&file_key)?;
+
+        let data = header.decrypt_data(&mut save_file, &file_key).await?;
+        let mut db = DB::deserialize(name, &data).await?;
+        Self::verify_legacy_header(&header, &mut db).await?;
+
+        Ok((header, slot_index, file_key, db))
+    }
+
+    /// Checks that a legacy header was not made legacy to get around its
+    /// authentication. Databases that track their schema version were only
+    /// ever written with authenticated headers, so a legacy header in front
+    /// of one has been tampered with.
+    async fn verify_legacy_header(header: &SaveHeader, db: &mut DB) -> Result<()> {
+        if header.is_legacy() && db.schema_version().await? > 0 {
+            Err(ExpectedCommandError::SaveHeaderTampered)?;
+        }
+
+        Ok(())
     }
 
-    /// Opens and decrypts a save file.
-    pub async fn open(name: &str, password: &str) -> Result<Self> {
+    /// Opens and decrypts the save with the given identifier in the saves
+    /// directory, using either its password or any other secret held by one
+    /// of its key slots.
+    pub async fn open(id: &str, password: &str) -> Result<Self> {
         init_saves_dir().await?;
 
-        Self::verify_exists(name)?;
+        Self::verify_exists(id)?;
 
-        let key = password_to_key(password).await;
+        Self::open_file(&get_save_path(id), Some(id), password).await
+    }
 
-        let save_path = get_save_path(name);
-        let mut save_file = File::open(&save_path).await?;
-        let metadata_str = read_save_metadata(&mut save_file).await?;
-        let mut metadata = SaveMetadata::read(name, &metadata_str);
+    /// Opens and decrypts the save file at the given path, using either its
+    /// password or any other secret held by one of its key slots.
+    pub async fn open_at(path: &str, password: &str) -> Result<Self> {
+        if !Path::new(path).is_file() {
+            Err(ExpectedCommandError::SaveNotFound)?;
+        }
 
-        let maybe_db = DB::create_with(name, move |mut db_file| async move {
-            decrypt_file(&mut save_file, &mut db_file, &key).await
-        })
-        .await;
+        Self::open_file(path, None, password).await
+    }
 
-        let db = match maybe_db {
-            Ok(db) => Ok(db),
-            Err(e) => {
-                fs::remove_file(get_db_path(name)).await?;
-                Err(e)
+    /// Locks, opens and decrypts the save file at the given path. The lock is
+    /// released again if the save cannot be opened.
+    async fn open_file(path: &str, id: Option<&str>, password: &str) -> Result<Self> {
+        let lock = SaveLock::acquire(path).await?;
+
+        match Self::open_locked_file(path, id, password).await {
+            Ok(mut this) => {
+                this.lock = Some(lock);
+                Ok(this)
+            }
+            Err(err) => {
+                lock.release().await?;
+                Err(err)
             }
-        }?;
+        }
+    }
+
+    /// Opens and decrypts the save file at the given path, which must
+    /// already be locked. Saves in the saves directory go by the identifier
+    /// their file is named after, and other saves without an identifier are
+    /// given one.
+    async fn open_locked_file(path: &str, id: Option<&str>, password: &str) -> Result<Self> {
+        let name = fallback_save_name(path);
+        let (header, slot_index, file_key, mut db) = Self::decrypt(path, &name, password).await?;
+
+        // Saves without a data encryption key get one generated, and key
+        // slots using the legacy key derivation scheme get a fresh salt.
+        // These take effect the next time the save is written.
+        // Saves keep the compression they were written with, except those
+        // with legacy headers, which predate compression being optional
+        let compression = if header.is_legacy() {
+            SaveCompression::CURRENT
+        } else {
+            header.compression
+        };
+        let SaveHeader {
+            key_slots: file_key_slots,
+            mut metadata,
+            ..
+        } = header;
+        let unlocked_slot = &file_key_slots[slot_index];
+        let slot_id = unlocked_slot.id;
+        let slot_kind = unlocked_slot.kind;
+        let slot_secret = key_slot_secret(slot_kind, password).unwrap_or_default();
+        let (key, key_slots) = if unlocked_slot.wrapped_key.is_none() {
+            let key = generate_key();
+            let key_slot = new_key_slot(slot_id, slot_kind, &slot_secret, &key).await?;
+            (key, vec![key_slot])
+        } else if unlocked_slot.kdf.is_legacy() {
+            let key_slot = new_key_slot(slot_id, slot_kind, &slot_secret, &file_key).await?;
+            let mut key_slots = file_key_slots;
+            key_slots[slot_index] = key_slot;
+            (file_key.clone(), key_slots)
+        } else {
+            (file_key.clone(), file_key_slots)
+        };
+
+        match id {
+            Some(id) => metadata.id = id.to_owned(),
+            None if metadata.id.is_empty() => metadata.id = new_id(),
+            None => {}
+        }
+
+        // Saves from older versions of the application are backed up as they
+        // were before their schema is brought up to date
+        if db.needs_migration().await? {
+            Self::copy_to_backups(path, &metadata.id).await?;
+            db.migrate().await?;
+        }
 
         metadata.last_opened_at = Utc::now().naive_utc();
-        Self::save_metadata(name, &metadata).await?;
+        Self::save_metadata(path, &metadata, &file_key).await?;
+
+        let saved_changes = db.total_changes().await?;
+
+        Ok(Self {
+            db,
+            path: path.to_owned(),
+            key,
+            key_slots,
+            saved_at: metadata.last_opened_at,
+            saved_changes,
+            metadata,
+            backup_policy: BackupPolicy::default(),
+            compression,
+            read_only: false,
+            lock: None,
+        })
+    }
 
-        Ok(Self { db, key, metadata })
+    /// Opens and decrypts a backup of a save read-only, using any secret
+    /// that unlocked the save when the backup was taken. Backups from older
+    /// schema versions are brought up to date in memory only.
+    pub async fn open_backup(backup: &SaveBackup, password: &str) -> Result<Self> {
+        let backup_path = Self::verify_backup_exists(backup)?;
+        let (header, _, key, mut db) = Self::decrypt(&backup_path, &backup.name, password).await?;
+        db.migrate().await?;
+        let saved_changes = db.total_changes().await?;
+
+        Ok(Self {
+            db,
+            path: backup_path,
+            key,
+            key_slots: header.key_slots,
+            metadata: header.metadata,
+            saved_at: backup.created_at,
+            saved_changes,
+            backup_policy: BackupPolicy::default(),
+            compression: header.compression,
+            read_only: true,
+            lock: None,
+        })
     }
 
-    /// Saves the state of the database to the save file.
+    /// Saves the state of the database to the save file. The save file is
+    /// replaced atomically, so it holds either the old or the new state
+    /// should anything go wrong while writing it. The database is vacuumed
+    /// and compressed first, keeping the save file and its backups small.
     pub async fn save(&mut self) -> Result<()> {
-        let save_path = get_save_path(&self.metadata.name);
-        let tmp_save_path = get_tmp_save_path(&self.metadata.name);
+        if self.read_only {
+            Err(ExpectedCommandError::SaveReadOnly)?;
+        }
 
-        {
-            let mut tmp_save_file = File::create(&tmp_save_path).await?;
-            let metadata_str = self.metadata.write();
-            write_section(&mut tmp_save_file, metadata_str.as_bytes()).await?;
+        let tmp_save_path = get_tmp_path(&self.path);
 
-            let key = self.key;
+        let changes = self.db.total_changes().await?;
+        self.db.vacuum().await?;
+        let data = self.db.serialize().await?;
+        let header = self.header()?;
+        let data = header.compression.compress(data).await?;
 
-            self.db
-                .pause_with(move |mut db_file| async move {
-                    encrypt_file(&mut db_file, &mut tmp_save_file, &key).await
-                })
-                .await?;
-        }
+        let mut tmp_save_file = File::create(&tmp_save_path).await?;
+        header.write(&mut tmp_save_file, Some(&self.key)).await?;
+        encrypt_file(&mut data.as_slice(), &mut tmp_save_file, &self.key).await?;
+        replace_with_tmp_file(tmp_save_file, &tmp_save_path, &self.path).await?;
 
-        fs::rename(tmp_save_path, save_path).await?;
+        self.saved_at = Utc::now().naive_utc();
+        self.saved_changes = changes;
 
         Ok(())
     }
 
-    /// Saves and closes the database save file.
-    pub async fn close(self) -> Result<()> {
-        let save_path = get_save_path(&self.metadata.name);
-        let tmp_save_path = get_tmp_save_path(&self.metadata.name);
+    /// Sets how many backups of the save file are kept, and for how long.
+    pub fn set_backup_policy(&mut self, backup_policy: BackupPolicy) {
+        self.backup_policy = backup_policy;
+    }
 
-        {
-            let mut tmp_save_file = File::create(&tmp_save_path).await?;
-            let metadata_str = self.metadata.write();
-            write_section(&mut tmp_save_file, metadata_str.as_bytes()).await?;
+    /// Backs up the save file as it was last written, then removes backups
+    /// that the backup policy no longer allows to be kept.
+    pub async fn backup(&self) -> Result<()> {
+        if self.read_only {
+            Err(ExpectedCommandError::SaveReadOnly)?;
+        }
 
-            let key = self.key;
+        if self.backup_policy.max_backups > 0 {
+            Self::copy_to_backups(&self.path, &self.metadata.id).await?;
+        }
 
-            self.db
-                .delete_with(move |mut db_file| async move {
-                    encrypt_file(&mut db_file, &mut tmp_save_file, &key).await
-                })
-                .await?;
+        Self::prune_backups(&self.metadata.id, &self.backup_policy).await
+    }
+
+    /// Gets when the save file was last written with the state of the
+    /// database.
+    pub fn saved_at(&self) -> NaiveDateTime {
+        self.saved_at
+    }
+
+    /// Gets the number of database changes made since the save file was
+    /// last written.
+    pub async fn unsaved_changes(&mut self) -> Result<u64> {
+        let changes = self.db.total_changes().await?;
+
+        Ok(changes.saturating_sub(self.saved_changes))
+    }
+
+    /// Saves and closes the database save file, releasing its lock.
+    pub async fn close(mut self) -> Result<()> {
+        if !self.read_only {
+            self.save().await?;
+            self.backup().await?;
         }
 
-        fs::rename(tmp_save_path, save_path).await?;
+        self.db.delete().await?;
+
+        if let Some(lock) = self.lock.take() {
+            lock.release().await?;
+        }
 
         Ok(())
     }
 
-    /// Checks if a save with the given name exists.
-    pub fn exists(name: &str) -> bool {
-        Path::new(&get_save_path(name)).exists()
+    /// Checks if a save with the given identifier exists in the saves
+    /// directory.
+    pub fn exists(id: &str) -> bool {
+        Path::new(&get_save_path(id)).exists()
     }
 
     /// Gets the metadata of the save file without needing to decrypt the
     /// file.
-    pub async fn metadata(name: &str) -> Result<SaveMetadata> {
-        let save_path = get_save_path(name);
-        let mut save_file = File::open(&save_path).await?;
-        let metadata_str = read_save_metadata(&mut save_file).await?;
-        let metadata = SaveMetadata::read(name, &metadata_str);
+    pub async fn metadata(id: &str) -> Result<SaveMetadata> {
+        Ok(Self::read_header(id).await?.metadata)
+    }
+
+    /// Gets the metadata of the save file at the given path without needing
+    /// to decrypt the file.
+    pub async fn metadata_at(path: &str) -> Result<SaveMetadata> {
+        if !Path::new(path).is_file() {
+            Err(ExpectedCommandError::SaveNotFound)?;
+        }
+
+        let mut save_file = File::open(path).await?;
+        let header = SaveHeader::read(&mut save_file, &fallback_save_name(path)).await?;
 
-        Ok(metadata)
+        Ok(header.metadata)
+    }
+
+    /// Gets the key incorrect passwords for the save file at the given path
+    /// are throttled by, whether or not the file exists yet. The identifier
+    /// in a header cannot be trusted before the save is unlocked, so saves
+    /// in the saves directory are throttled by their file name there, which
+    /// is their identifier. Any other file is throttled by its canonical
+    /// path.
+    pub async fn password_throttle_key_at(path: &str) -> Result<String> {
+        init_saves_dir().await?;
+
+        let path = Path::new(path);
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let Some(file_name) = path.file_name() else {
+            Err(ExpectedCommandError::SaveNotFound)?
+        };
+        let dir = fs::canonicalize(dir).await?;
+
+        if dir == fs::canonicalize(get_saves_path()).await? {
+            let save_suffix = format!(".{SAVE_EXT}");
+
+            if let Some(id) = file_name
+                .to_str()
+                .and_then(|n| n.strip_suffix(&save_suffix))
+            {
+                return Ok(id.to_owned());
+            }
+        }
+
+        Ok(dir.join(file_name).display().to_string())
     }
 
     /// Gets the metadata of the currently open save file.
@@ -274,113 +641,381 @@ impl Save {
         self.metadata.clone()
     }
 
-    /// Save a save file's metadata.
-    async fn save_metadata(name: &str, metadata: &SaveMetadata) -> Result<()> {
-        let save_path = get_save_path(name);
-        let tmp_save_path = get_tmp_save_path(name);
+    /// Gets the path to the currently open save file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
 
-        {
-            let mut save_file = File::open(&save_path).await?;
-            skip_metadata(&mut save_file).await?;
+    /// Gets the header to write in front of the currently open save's data.
+    fn header(&self) -> Result<SaveHeader> {
+        let mut header = SaveHeader {
+            cipher: SaveCipher::CURRENT,
+            compression: self.compression,
+            key_slots: self.key_slots.clone(),
+            metadata: self.metadata.clone(),
+            sealed_metadata: None,
+            mac: None,
+        };
+        header.set_metadata(self.metadata.clone(), &self.key)?;
 
-            let mut tmp_save_file = File::create(&tmp_save_path).await?;
-            let metadata_str = metadata.write();
-            write_section(&mut tmp_save_file, metadata_str.as_bytes()).await?;
-            copy_file_in_chunks(&mut save_file, &mut tmp_save_file).await?;
+        Ok(header)
+    }
+
+    /// Sets whether the currently open save keeps its name and description
+    /// encrypted. The save is written out so that the change takes effect
+    /// straight away.
+    pub async fn set_metadata_encrypted(&mut self, encrypted: bool) -> Result<()> {
+        self.metadata.encrypted = encrypted;
+        self.save().await
+    }
+
+    /// Checks whether the currently open save compresses its data before
+    /// encrypting it.
+    pub fn is_compressed(&self) -> bool {
+        self.compression != SaveCompression::None
+    }
+
+    /// Sets whether the currently open save compresses its data before
+    /// encrypting it. The save is written out so that the change takes
+    /// effect straight away.
+    pub async fn set_compressed(&mut self, compressed: bool) -> Result<()> {
+        self.compression = if compressed {
+            SaveCompression::CURRENT
+        } else {
+            SaveCompression::None
+        };
+        self.save().await
+    }
+
+    /// Gets the key slots of the currently open save file.
+    pub fn key_slots(&self) -> Vec<KeySlot> {
+        self.key_slots.iter().map(SaveKeySlot::info).collect()
+    }
+
+    /// Adds a key slot to the currently open save file. The save is written
+    /// out so that the key slot can be used straight away.
+    async fn add_key_slot(&mut self, kind: KeySlotKind, secret: &str) -> Result<KeySlot> {
+        if self.key_slots.len() >= MAX_KEY_SLOTS {
+            Err(ExpectedCommandError::TooManyKeySlots)?;
         }
 
-        fs::rename(tmp_save_path, save_path).await?;
+        let id = next_key_slot_id(&self.key_slots);
+        let key_slot = new_key_slot(id, kind, secret, &self.key).await?;
+        let info = key_slot.info();
+        self.key_slots.push(key_slot);
+        self.save().await?;
+
+        Ok(info)
+    }
+
+    /// Adds a password that can unlock the currently open save file.
+    pub async fn add_password_key_slot(&mut self, password: &str) -> Result<KeySlot> {
+        self.add_key_slot(KeySlotKind::Password, password).await
+    }
+
+    /// Adds a generated recovery phrase that can unlock the currently open
+    /// save file. The recovery phrase is returned here and cannot be
+    /// retrieved again.
+    pub async fn add_recovery_key_slot(&mut self) -> Result<(KeySlot, String)> {
+        let recovery_phrase = generate_recovery_phrase();
+        let key_slot = self
+            .add_key_slot(KeySlotKind::RecoveryPhrase, &recovery_phrase)
+            .await?;
+
+        Ok((key_slot, recovery_phrase))
+    }
+
+    /// Revokes a key slot of the currently open save file, so that its
+    /// secret can no longer unlock the save. The last key slot cannot be
+    /// removed.
+    pub async fn remove_key_slot(&mut self, id: u32) -> Result<()> {
+        let index = self
+            .key_slots
+            .iter()
+            .position(|key_slot| key_slot.id == id)
+            .ok_or(ExpectedCommandError::KeySlotNotFound)?;
+
+        if self.key_slots.len() == 1 {
+            Err(ExpectedCommandError::LastKeySlot)?;
+        }
+
+        self.key_slots.remove(index);
+        self.save().await?;
 
         Ok(())
     }
 
-    /// Checks if a save with the provided name exists, returning an error if
-    /// it does not.
-    fn verify_exists(name: &str) -> Result<()> {
-        if Self::exists(name) {
-            Ok(())
-        } else {
-            Err(ExpectedCommandError::SaveNotFound)?
+    /// Reads the header of a save file in the saves directory. The save goes
+    /// by the identifier its file is named after.
+    async fn read_header(id: &str) -> Result<SaveHeader> {
+        let save_path = get_save_path(id);
+        let mut save_file = File::open(&save_path).await?;
+        let mut header = SaveHeader::read(&mut save_file, id).await?;
+        header.metadata.id = id.to_owned();
+
+        Ok(header)
+    }
+
+    /// Updates the header of the save file at the given path, copying the
+    /// encrypted data that follows it as-is. The header is written in the
+    /// current format version and authenticated if the data encryption key is
+    /// given, or as a legacy header otherwise.
+    async fn update_header<F>(save_path: &str, key: Option<&[u8; AES_KEY_SIZE]>, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut SaveHeader) -> Result<()>,
+    {
+        let tmp_save_path = get_tmp_path(save_path);
+
+        let mut save_file = File::open(save_path).await?;
+        let mut header = SaveHeader::read(&mut save_file, &fallback_save_name(save_path)).await?;
+        f(&mut header)?;
+
+        let mut tmp_save_file = File::create(&tmp_save_path).await?;
+        header.write(&mut tmp_save_file, key).await?;
+        copy_file_in_chunks(&mut save_file, &mut tmp_save_file).await?;
+        drop(save_file);
+        replace_with_tmp_file(tmp_save_file, &tmp_save_path, save_path).await?;
+
+        Ok(())
+    }
+
+    /// Save the metadata of the save file at the given path, encrypting it
+    /// with the key the file's data is encrypted with if the save keeps its
+    /// metadata encrypted. The rest of the header is kept as it is in the
+    /// file, as it describes how the existing data is encrypted.
+    async fn save_metadata(
+        save_path: &str,
+        metadata: &SaveMetadata,
+        key: &[u8; AES_KEY_SIZE],
+    ) -> Result<()> {
+        Self::update_header(save_path, Some(key), |header| {
+            header.set_metadata(metadata.clone(), key)
+        })
+        .await
+    }
+
+    /// Gets a save's data encryption key by trying a secret against each
+    /// key slot in turn, then checks the header has not been tampered with.
+    /// Returns the index of the key slot that was unlocked along with the
+    /// key.
+    async fn unlock(header: &SaveHeader, secret: &str) -> Result<(usize, SecretKey)> {
+        for (index, key_slot) in header.key_slots.iter().enumerate() {
+            let Some(slot_secret) = key_slot_secret(key_slot.kind, secret) else {
+                continue;
+            };
+            let key_encryption_key = password_to_key(&slot_secret, &key_slot.kdf).await?;
+
+            match &key_slot.wrapped_key {
+                Some(wrapped_key) => {
+                    if let Ok(key) = unwrap_key(&key_encryption_key, wrapped_key) {
+                        header.verify_mac(&key)?;
+                        return Ok((index, key));
+                    }
+                }
+                None => {
+                    header.verify_mac(&key_encryption_key)?;
+                    return Ok((index, key_encryption_key));
+                }
+            }
         }
+
+        Err(ExpectedCommandError::InvalidSavePassword)?
     }
 
-    /// Checks if a save with the provided name exists, returning an error if
-    /// it does.
-    fn verify_does_not_exist(name: &str) -> Result<()> {
-        if Self::exists(name) {
-            Err(ExpectedCommandError::SaveAlreadyExists)?
-        } else {
+    /// Checks if a save with the provided identifier exists, returning an
+    /// error if it does not.
+    fn verify_exists(id: &str) -> Result<()> {
+        if Self::exists(id) {
             Ok(())
+        } else {
+            Err(ExpectedCommandError::SaveNotFound)?
         }
     }
 
     /// Checks if the provided password can successfully decrypt a save,
     /// returning an error if it cannot.
-    async fn verify_password(name: &str, password: &str) -> Result<()> {
-        let key = password_to_key(password).await;
+    async fn verify_password(id: &str, password: &str) -> Result<()> {
+        Self::verify_exists(id)?;
 
-        let save_path = get_save_path(name);
-        let mut save_file = File::open(&save_path).await?;
-        skip_metadata(&mut save_file).await?;
+        Self::verify_file_password(&get_save_path(id), id, password).await
+    }
 
-        try_decrypt_file(&mut save_file, &key).await?;
+    /// Checks if the provided password can successfully decrypt a save file
+    /// or backup at the given path, returning an error if it cannot.
+    async fn verify_file_password(path: &str, name: &str, password: &str) -> Result<()> {
+        Self::unlock_file(path, name, password).await?;
 
         Ok(())
     }
 
-    /// Sets the name of a save. This should not be used while the save is
-    /// open.
-    pub async fn set_name(old_name: &str, new_name: &str, password: &str) -> Result<()> {
-        Self::verify_password(old_name, password).await?;
-        Self::verify_does_not_exist(new_name)?;
+    /// Checks that the provided password can successfully decrypt a save file
+    /// or backup at the given path, returning its header with the metadata
+    /// decrypted, along with the key its data is encrypted with.
+    async fn unlock_file(
+        path: &str,
+        name: &str,
+        password: &str,
+    ) -> Result<(SaveHeader, SecretKey)> {
+        let mut save_file = File::open(path).await?;
+        let mut header = SaveHeader::read(&mut save_file, name).await?;
+        let (_, key) = Self::unlock(&header, password).await?;
+
+        Self::verify_data(&header, &mut save_file, name, &key).await?;
+        header.unseal_metadata(&key)?;
+
+        Ok((header, key))
+    }
 
-        let mut metadata = Self::metadata(old_name).await?;
-        metadata.name = new_name.to_owned();
+    /// Checks that the data following a header can be decrypted with the
+    /// given key. The file must be positioned at the start of the encrypted
+    /// data. Legacy headers are authenticated when they are next written, so
+    /// they are only trusted once the data they precede has been checked.
+    async fn verify_data(
+        header: &SaveHeader,
+        save_file: &mut File,
+        name: &str,
+        key: &[u8; AES_KEY_SIZE],
+    ) -> Result<()> {
+        if header.is_legacy() {
+            let data = header.decrypt_data(save_file, key).await?;
+            let mut db = DB::deserialize(name, &data).await?;
+            Self::verify_legacy_header(header, &mut db).await?;
+            db.delete().await
+        } else {
+            header.cipher.try_decrypt_file(save_file, key).await
+        }
+    }
 
-        Self::save_metadata(old_name, &metadata).await?;
+    /// Updates the metadata of a save, which must be unlockable with the
+    /// given password. This should not be used while the save is open, and
+    /// fails if it is open elsewhere.
+    async fn update_metadata<F>(id: &str, password: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut SaveMetadata),
+    {
+        Self::verify_exists(id)?;
+
+        let save_path = get_save_path(id);
+        let lock = SaveLock::acquire(&save_path).await?;
+        let updated = Self::update_locked_metadata(&save_path, id, password, f).await;
+        lock.release().await?;
+
+        updated
+    }
 
-        let old_path = get_save_path(old_name);
-        let new_path = get_save_path(new_name);
-        fs::rename(old_path, new_path).await?;
+    /// Updates the metadata of a save, once the save file has been locked.
+    async fn update_locked_metadata<F>(
+        save_path: &str,
+        id: &str,
+        password: &str,
+        f: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(&mut SaveMetadata),
+    {
+        let (header, key) = Self::unlock_file(save_path, id, password).await?;
+        let mut metadata = header.metadata;
+        metadata.id = id.to_owned();
+        f(&mut metadata);
+
+        Self::save_metadata(save_path, &metadata, &key).await
+    }
 
-        Ok(())
+    /// Sets the name of a save. The name only lives in the save's metadata,
+    /// so its files stay where they are. This should not be used while the
+    /// save is open.
+    pub async fn set_name(id: &str, name: &str, password: &str) -> Result<()> {
+        Self::update_metadata(id, password, |metadata| metadata.name = name.to_owned()).await
     }
 
     /// Sets the description of a save. This should not be used while the save is open.
-    pub async fn set_description(name: &str, description: &str, password: &str) -> Result<()> {
-        Self::verify_password(name, password).await?;
+    pub async fn set_description(id: &str, description: &str, password: &str) -> Result<()> {
+        Self::update_metadata(id, password, |metadata| {
+            metadata.description = description.to_owned()
+        })
+        .await
+    }
 
-        let mut metadata = Self::metadata(name).await?;
-        metadata.description = description.to_owned();
+    /// Changes a save's password. The password key slot unlocked by the old
+    /// password is given the new one, while a recovery phrase is refused so
+    /// that it is never lost. Only that key slot is rewritten, so this takes
+    /// the same time however large the save is. This should not be used
+    /// while the save is open, and fails if it is open elsewhere.
+    pub async fn change_password(id: &str, old_password: &str, new_password: &str) -> Result<()> {
+        Self::verify_exists(id)?;
+
+        // Saves without a data encryption key are given one by writing them
+        // out again
+        if Self::read_header(id)
+            .await?
+            .key_slots
+            .iter()
+            .any(|key_slot| key_slot.wrapped_key.is_none())
+        {
+            Self::open(id, old_password).await?.close().await?;
+        }
 
-        Self::save_metadata(name, &metadata).await?;
+        let save_path = get_save_path(id);
+        let lock = SaveLock::acquire(&save_path).await?;
+        let changed =
+            Self::change_locked_password(&save_path, id, old_password, new_password).await;
+        lock.release().await?;
 
-        Ok(())
+        changed
     }
 
-    /// Changes a save's password by decrypting and re-encrypting the save
-    /// data. This should not be used while the save is open.
-    pub async fn change_password(name: &str, old_password: &str, new_password: &str) -> Result<()> {
-        let new_key = password_to_key(new_password).await;
+    /// Changes a save's password, once the save file has been locked.
+    async fn change_locked_password(
+        save_path: &str,
+        id: &str,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<()> {
+        let header = Self::read_header(id).await?;
+        let (slot_index, key) = Self::unlock(&header, old_password).await?;
+
+        if header.key_slots[slot_index].kind != KeySlotKind::Password {
+            Err(ExpectedCommandError::RecoveryPhraseNotPassword)?;
+        }
+
+        let slot_id = header.key_slots[slot_index].id;
+        let key_slot = new_key_slot(slot_id, KeySlotKind::Password, new_password, &key).await?;
 
-        let mut save = Self::open(name, old_password).await?;
-        save.key = new_key;
-        save.close().await
+        Self::update_header(save_path, Some(&key), |header| {
+            header.key_slots[slot_index] = key_slot;
+            Ok(())
+        })
+        .await
     }
 
-    /// Deletes a save. This should not be used while the save is open.
-    pub async fn delete(name: &str, password: &str) -> Result<()> {
-        Self::verify_password(name, password).await?;
+    /// Deletes a save. This should not be used while the save is open, and
+    /// fails if it is open elsewhere.
+    pub async fn delete(id: &str, password: &str) -> Result<()> {
+        Self::verify_password(id, password).await?;
 
-        let save_path = get_save_path(name);
+        let save_path = get_save_path(id);
+        let backups_path = get_backups_path(id);
 
+        let lock = SaveLock::acquire(&save_path).await?;
         fs::remove_file(save_path).await?;
+        lock.release().await?;
+
+        if Path::new(&backups_path).exists() {
+            fs::remove_dir_all(backups_path).await?;
+        }
 
         Ok(())
     }
 
     /// Lists metadata on all saves.
     pub async fn list() -> Result<Vec<SaveMetadata>> {
+        let saves = Self::list_files().await?;
+
+        Ok(saves.into_iter().map(|(_, metadata)| metadata).collect())
+    }
+
+    /// Lists the paths to and metadata of all saves in the saves directory.
+    pub(crate) async fn list_files() -> Result<Vec<(String, SaveMetadata)>> {
         init_saves_dir().await?;
 
         let saves_path = get_saves_path();
@@ -392,9 +1027,16 @@ impl Save {
             if let Some(file_name) = file.file_name().to_str() {
                 if let Some(file_ext) = Path::new(file_name).extension() {
                     if file_ext == SAVE_EXT {
-                        if let Some((name, _)) = file_name.rsplit_once('.') {
-                            let metadata = Self::metadata(name).await?;
-                            saves.push(metadata);
+                        if let Some((id, _)) = file_name.rsplit_once('.') {
+                            // Saves that cannot be read are left out rather
+                            // than hiding every other save
+                            match Self::metadata(id).await {
+                                Ok(metadata) => {
+                                    saves.push((format!("{saves_path}/{file_name}"), metadata))
+                                }
+                                Err(Error::Expected(_)) => {}
+                                Err(err) => Err(err)?,
+                            }
                         }
                     }
                 }
@@ -403,6 +1045,544 @@ impl Save {
 
         Ok(saves)
     }
+
+    /// Lists the backups of a save, most recent first.
+    pub async fn backups(id: &str) -> Result<Vec<SaveBackup>> {
+        Self::verify_exists(id)?;
+
+        let name = Self::metadata(id).await?.name;
+
+        Self::list_backups(id, &name).await
+    }
+
+    /// Lists the backups kept under the given save identifier, most recent
+    /// first, wherever the save file itself is kept.
+    async fn list_backups(save_id: &str, name: &str) -> Result<Vec<SaveBackup>> {
+        let backups_path = get_backups_path(save_id);
+
+        if !Path::new(&backups_path).exists() {
+            return Ok(Vec::new());
+        }
+
+        let backup_suffix = format!(".{SAVE_EXT}");
+        let mut files = fs::read_dir(&backups_path).await?;
+        let mut backups = Vec::new();
+
+        while let Some(file) = files.next_entry().await? {
+            if let Some(file_name) = file.file_name().to_str() {
+                if let Some(id) = file_name.strip_suffix(&backup_suffix) {
+                    if let Ok(created_at) = NaiveDateTime::parse_from_str(id, BACKUP_ID_FORMAT) {
+                        backups.push(SaveBackup {
+                            save_id: save_id.to_owned(),
+                            name: name.to_owned(),
+                            id: id.to_owned(),
+                            created_at,
+                        });
+                    }
+                }
+            }
+        }
+
+        backups.sort_by_key(|backup| Reverse(backup.created_at));
+
+        Ok(backups)
+    }
+
+    /// Copies the save file at the given path into the backups of the save
+    /// with the given identifier as it currently is.
+    async fn copy_to_backups(save_path: &str, save_id: &str) -> Result<()> {
+        let backups_path = get_backups_path(save_id);
+        fs::create_dir_all(&backups_path).await?;
+
+        let id = Utc::now().naive_utc().format(BACKUP_ID_FORMAT).to_string();
+        let backup_path = get_backup_path(save_id, &id);
+        let tmp_backup_path = format!("{backup_path}.{TMP_SAVE_EXT}");
+
+        {
+            let mut save_file = File::open(save_path).await?;
+            let mut tmp_backup_file = File::create(&tmp_backup_path).await?;
+            copy_file_in_chunks(&mut save_file, &mut tmp_backup_file).await?;
+            replace_with_tmp_file(tmp_backup_file, &tmp_backup_path, &backup_path).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the backups of a save that are beyond the most the backup
+    /// policy allows, or older than it allows. The most recent backup is
+    /// kept regardless of age.
+    async fn prune_backups(save_id: &str, backup_policy: &BackupPolicy) -> Result<()> {
+        let oldest_allowed = (backup_policy.max_age_days > 0)
+            .then(|| Utc::now().naive_utc() - Duration::days(backup_policy.max_age_days as i64));
+
+        for (index, backup) in Self::list_backups(save_id, save_id)
+            .await?
+            .into_iter()
+            .enumerate()
+        {
+            let too_many = index as u64 >= backup_policy.max_backups;
+            let too_old = index > 0
+                && oldest_allowed.is_some_and(|oldest_allowed| backup.created_at < oldest_allowed);
+
+            if too_many || too_old {
+                fs::remove_file(get_backup_path(save_id, &backup.id)).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks if a backup exists, returning its path if it does and an error
+    /// if it does not.
+    fn verify_backup_exists(backup: &SaveBackup) -> Result<String> {
+        let backup_path = get_backup_path(&backup.save_id, &backup.id);
+
+        if Path::new(&backup_path).exists() {
+            Ok(backup_path)
+        } else {
+            Err(ExpectedCommandError::BackupNotFound)?
+        }
+    }
+
+    /// Restores a save file from one of its backups. The backup must be
+    /// unlockable with the given password. The save file is backed up as it
+    /// was before being replaced, so that restoring can be undone. This
+    /// should not be used while the save is open, and fails if it is open
+    /// elsewhere.
+    pub async fn restore_backup(backup: &SaveBackup, password: &str) -> Result<()> {
+        Self::verify_exists(&backup.save_id)?;
+
+        let lock = SaveLock::acquire(&get_save_path(&backup.save_id)).await?;
+        let restored = Self::restore_locked_backup(backup, password).await;
+        lock.release().await?;
+
+        restored
+    }
+
+    /// Restores a save file from one of its backups, once the save file has
+    /// been locked.
+    async fn restore_locked_backup(backup: &SaveBackup, password: &str) -> Result<()> {
+        let backup_path = Self::verify_backup_exists(backup)?;
+        let (backup_header, key) = Self::unlock_file(&backup_path, &backup.name, password).await?;
+
+        // The save may have been renamed since the backup was taken, though
+        // an encrypted name can only be kept if the password unlocks it
+        let mut header = Self::read_header(&backup.save_id).await?;
+        let name = match header.sealed_metadata {
+            None => Some(header.metadata.name),
+            Some(_) => match Self::unlock(&header, password).await {
+                Ok((_, save_key)) => {
+                    header.unseal_metadata(&save_key)?;
+                    Some(header.metadata.name)
+                }
+                Err(Error::Expected(_)) => None,
+                Err(err) => Err(err)?,
+            },
+        };
+        let save_path = get_save_path(&backup.save_id);
+        let tmp_save_path = get_tmp_save_path(&backup.save_id);
+
+        {
+            let mut backup_file = File::open(&backup_path).await?;
+            let mut tmp_save_file = File::create(&tmp_save_path).await?;
+            copy_file_in_chunks(&mut backup_file, &mut tmp_save_file).await?;
+            Self::copy_to_backups(&save_path, &backup.save_id).await?;
+            replace_with_tmp_file(tmp_save_file, &tmp_save_path, &save_path).await?;
+        }
+
+        let mut metadata = backup_header.metadata;
+        metadata.id = backup.save_id.clone();
+        if let Some(name) = name {
+            metadata.name = name;
+        }
+
+        Self::save_metadata(&save_path, &metadata, &key).await
+    }
+
+    /// Compares the database with that of another save, typically a backup
+    /// opened with [`Save::open_backup`], table by table.
+    pub async fn compare_with(&mut self, other: &mut Save) -> Result<Vec<BackupComparison>> {
+        let other_data = other.db.serialize().await?;
+        self.db.attach(BACKUP_SCHEMA, &other_data).await?;
+
+        let mut comparisons = Vec::new();
+
+        for (table, _) in TABLES {
+            let comparison = sqlx::query_as::<_, (i64, i64, i64, i64)>(&format!(
+                "SELECT \
+                    (SELECT COUNT(*) FROM main.{table}), \
+                    (SELECT COUNT(*) FROM {BACKUP_SCHEMA}.{table}), \
+                    (SELECT COUNT(*) FROM (SELECT * FROM main.{table} EXCEPT SELECT * FROM {BACKUP_SCHEMA}.{table})), \
+                    (SELECT COUNT(*) FROM (SELECT * FROM {BACKUP_SCHEMA}.{table} EXCEPT SELECT * FROM main.{table}))"
+            ))
+            .fetch_one(&mut *self.db)
+            .await;
+
+            match comparison {
+                Ok((rows, backup_rows, rows_not_in_backup, backup_rows_not_in_save)) => {
+                    comparisons.push(BackupComparison {
+                        table: (*table).to_owned(),
+                        rows: rows as u64,
+                        backup_rows: backup_rows as u64,
+                        rows_not_in_backup: rows_not_in_backup as u64,
+                        backup_rows_not_in_save: backup_rows_not_in_save as u64,
+                    });
+                }
+                Err(err) => {
+                    self.db.detach(BACKUP_SCHEMA).await?;
+                    Err(err)?;
+                }
+            }
+        }
+
+        self.db.detach(BACKUP_SCHEMA).await?;
+
+        Ok(comparisons)
+    }
+
+    /// Finds data left behind by saves that were not closed cleanly: save
+    /// files that were never finished being written, and decrypted databases
+    /// left by older versions of the application. Saves can live anywhere,
+    /// so the directories of recently opened saves are searched along with
+    /// the saves directory.
+    pub async fn leftovers() -> Result<Vec<LeftoverSave>> {
+        init_saves_dir().await?;
+
+        let saves_dir = fs::canonicalize(get_saves_path()).await?;
+        let mut leftovers = Self::unfinished_saves_in(&saves_dir, true).await?;
+        let mut searched_dirs = vec![saves_dir];
+
+        for recent_path in Self::recent_paths().await? {
+            let Some(dir) = Path::new(&recent_path).parent() else {
+                continue;
+            };
+
+            // Directories that have gone or cannot be read are skipped, as
+            // the recently opened list is pruned of their saves elsewhere
+            if let Ok(dir) = fs::canonicalize(dir).await {
+                if !searched_dirs.contains(&dir) {
+                    if let Ok(unfinished_saves) = Self::unfinished_saves_in(&dir, false).await {
+                        leftovers.extend(unfinished_saves);
+                    }
+
+                    searched_dirs.push(dir);
+                }
+            }
+        }
+
+        let legacy_db_dir_path = get_legacy_db_dir_path();
+
+        if Path::new(&legacy_db_dir_path).exists() {
+            let legacy_db_suffix = format!(".{LEGACY_DB_EXT}");
+            let mut files = fs::read_dir(&legacy_db_dir_path).await?;
+
+            while let Some(file) = files.next_entry().await? {
+                if let Some(file_name) = file.file_name().to_str() {
+                    if let Some(id) = file_name.strip_suffix(&legacy_db_suffix) {
+                        // Decrypted databases can only be recovered into
+                        // their save, so those without one are left alone
+                        if Self::exists(id) {
+                            let leftover = Self::leftover(
+                                &get_save_path(id),
+                                Some(id),
+                                LeftoverKind::DecryptedDatabase,
+                            )
+                            .await?;
+                            leftovers.push(leftover);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(leftovers)
+    }
+
+    /// Finds the save files in a directory that were never finished being
+    /// written. Saves in the saves directory are identified by their file
+    /// name there.
+    async fn unfinished_saves_in(dir: &Path, is_saves_dir: bool) -> Result<Vec<LeftoverSave>> {
+        let save_suffix = format!(".{SAVE_EXT}");
+        let tmp_suffix = format!(".{TMP_SAVE_EXT}");
+        let mut leftovers = Vec::new();
+        let mut files = fs::read_dir(dir).await?;
+
+        while let Some(file) = files.next_entry().await? {
+            if let Some(file_name) = file.file_name().to_str() {
+                if let Some(save_file_name) = file_name.strip_suffix(&tmp_suffix) {
+                    if let Some(id) = save_file_name.strip_suffix(&save_suffix) {
+                        let save_path = dir.join(save_file_name).display().to_string();
+                        let id = is_saves_dir.then_some(id);
+                        let leftover =
+                            Self::leftover(&save_path, id, LeftoverKind::UnfinishedSave).await?;
+                        leftovers.push(leftover);
+                    }
+                }
+            }
+        }
+
+        Ok(leftovers)
+    }
+
+    /// Describes leftover save data, comparing it with the save file at the
+    /// given path if there is one. Saves outside the saves directory, which
+    /// are not given an identifier here, are identified by their header.
+    async fn leftover(
+        save_path: &str,
+        id: Option<&str>,
+        kind: LeftoverKind,
+    ) -> Result<LeftoverSave> {
+        let leftover_path = match (kind, id) {
+            (LeftoverKind::DecryptedDatabase, Some(id)) => get_legacy_db_path(id),
+            (LeftoverKind::DecryptedDatabase, None) => Err(ExpectedCommandError::SaveNotFound)?,
+            (LeftoverKind::UnfinishedSave, _) => get_tmp_path(save_path),
+        };
+        let modified_at = file_modified_at(&leftover_path).await?;
+        let fallback_name = fallback_save_name(save_path);
+
+        // The real name of the save is used where it can be found, falling
+        // back to its file name
+        let (header, save_modified_at) = if Path::new(save_path).is_file() {
+            let save_modified_at = file_modified_at(save_path).await?;
+            let mut save_file = File::open(save_path).await?;
+            let header = SaveHeader::read(&mut save_file, &fallback_name).await;
+            (Some(header), Some(save_modified_at))
+        } else if kind == LeftoverKind::UnfinishedSave {
+            let mut leftover_file = File::open(&leftover_path).await?;
+            let header = SaveHeader::read(&mut leftover_file, &fallback_name).await;
+            (Some(header), None)
+        } else {
+            (None, None)
+        };
+
+        let (name, header_id) = match header {
+            Some(Ok(header)) => (header.metadata.name, header.metadata.id),
+            Some(Err(Error::Expected(_))) | None => (fallback_name, String::new()),
+            Some(Err(err)) => Err(err)?,
+        };
+
+        Ok(LeftoverSave {
+            id: id.map(str::to_owned).unwrap_or(header_id),
+            name,
+            path: save_path.to_owned(),
+            kind,
+            modified_at,
+            save_modified_at,
+        })
+    }
+
+    /// Recovers leftover save data into its save file, replacing what the
+    /// save file held before. The save file is backed up as it was before
+    /// being replaced, so that recovering can be undone. Unfinished save
+    /// files must be unlockable with the given password, and decrypted
+    /// databases are encrypted again with the key of the save they belong
+    /// to, which the password must unlock.
+    pub async fn recover_leftover(leftover: &LeftoverSave, password: &str) -> Result<()> {
+        let leftover_path = get_leftover_path(leftover);
+
+        if !Path::new(&leftover_path).exists() {
+            Err(ExpectedCommandError::LeftoverNotFound)?;
+        }
+
+        match leftover.kind {
+            LeftoverKind::UnfinishedSave => {
+                {
+                    let mut leftover_file = File::open(&leftover_path).await?;
+                    let header = match SaveHeader::read(&mut leftover_file, &leftover.name).await {
+                        Ok(header) => header,
+                        Err(Error::Expected(_)) => {
+                            Err(ExpectedCommandError::LeftoverNotRecoverable)?
+                        }
+                        Err(err) => Err(err)?,
+                    };
+                    let (_, key) = Self::unlock(&header, password).await?;
+
+                    // The password and header are known to be right at this
+                    // point, so failing to decrypt means the file was cut
+                    // short, unless a legacy header has been tampered with
+                    match Self::verify_data(&header, &mut leftover_file, &leftover.name, &key).await
+                    {
+                        Ok(()) => {}
+                        Err(Error::Expected(err))
+                            if *err == ExpectedCommandError::SaveHeaderTampered =>
+                        {
+                            return Err(Error::Expected(err));
+                        }
+                        Err(_) => Err(ExpectedCommandError::LeftoverNotRecoverable)?,
+                    }
+                }
+
+                let save_path = &leftover.path;
+                let lock = SaveLock::acquire(save_path).await?;
+                let renamed =
+                    Self::replace_with_leftover(&leftover_path, save_path, &leftover.id).await;
+                lock.release().await?;
+                renamed?;
+            }
+            LeftoverKind::DecryptedDatabase => {
+                Self::verify_exists(&leftover.id)?;
+
+                let data = fs::read(&leftover_path).await?;
+                let mut db = DB::deserialize(&leftover.id, &data).await?;
+
+                let maybe_save = if db.is_intact().await {
+                    Self::open(&leftover.id, password).await
+                } else {
+                    Err(ExpectedCommandError::LeftoverNotRecoverable.into())
+                };
+                let mut save = match maybe_save {
+                    Ok(save) => save,
+                    Err(err) => {
+                        db.delete().await?;
+                        return Err(err);
+                    }
+                };
+                let old_db = mem::replace(&mut save.db, db);
+                old_db.delete().await?;
+                Self::copy_to_backups(&save.path, &leftover.id).await?;
+                save.close().await?;
+
+                secure_remove_file(&leftover_path).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces a save file with a leftover unfinished save, once the save
+    /// file has been locked. The save file is backed up first, if there is
+    /// one.
+    async fn replace_with_leftover(
+        leftover_path: &str,
+        save_path: &str,
+        save_id: &str,
+    ) -> Result<()> {
+        if Path::new(save_path).exists() {
+            Self::copy_to_backups(save_path, save_id).await?;
+        }
+
+        fs::rename(leftover_path, save_path).await?;
+
+        Ok(())
+    }
+
+    /// Discards leftover save data, overwriting it before it is removed.
+    pub async fn discard_leftover(leftover: &LeftoverSave) -> Result<()> {
+        let leftover_path = get_leftover_path(leftover);
+
+        if !Path::new(&leftover_path).exists() {
+            Err(ExpectedCommandError::LeftoverNotFound)?;
+        }
+
+        secure_remove_file(&leftover_path).await?;
+
+        Ok(())
+    }
+
+    /// Moves the saves and leftover decrypted databases that older versions
+    /// of the application kept under the given directory into the data and
+    /// cache directories. Decrypted databases are only moved along with the
+    /// save they belong to, since anything else cannot be recovered anyway.
+    /// Returns the new paths of the moved files.
+    pub fn migrate_legacy_dirs(legacy_root: &Path) -> Result<Vec<PathBuf>> {
+        let saves_path = PathBuf::from(get_saves_path());
+        let mut moved = migrate_legacy_dir(&legacy_root.join(SAVES_DIR), &saves_path, |_| true)?;
+
+        let is_leftover = |path: &Path| {
+            path.extension().is_some_and(|ext| ext == LEGACY_DB_EXT)
+                && path.file_stem().is_some_and(|file_name| {
+                    saves_path.join(file_name).with_extension(SAVE_EXT).exists()
+                })
+        };
+        moved.extend(migrate_legacy_dir(
+            &legacy_root.join(LEGACY_DB_DIR),
+            Path::new(&get_legacy_db_dir_path()),
+            is_leftover,
+        )?);
+
+        Ok(moved)
+    }
+
+    /// Moves saves in the saves directory that older versions of the
+    /// application named after the save's name over to files named after a
+    /// newly generated identifier, along with their backups, leftover data
+    /// and entries in the list of recently opened saves. Returns the
+    /// identifiers of the moved saves.
+    pub async fn migrate_legacy_names() -> Result<Vec<String>> {
+        init_saves_dir().await?;
+
+        let save_suffix = format!(".{SAVE_EXT}");
+        let mut file_names = Vec::new();
+        let mut files = fs::read_dir(get_saves_path()).await?;
+
+        while let Some(file) = files.next_entry().await? {
+            if let Some(file_name) = file.file_name().to_str() {
+                if let Some(file_name) = file_name.strip_suffix(&save_suffix) {
+                    file_names.push(file_name.to_owned());
+                }
+            }
+        }
+
+        let mut migrated = Vec::new();
+
+        for file_name in file_names {
+            let save_path = get_save_path(&file_name);
+            let mut save_file = File::open(&save_path).await?;
+            let metadata = match SaveHeader::read(&mut save_file, &file_name).await {
+                Ok(header) => header.metadata,
+                Err(Error::Expected(_)) => continue,
+                Err(err) => Err(err)?,
+            };
+            drop(save_file);
+
+            // Saves that were given an identifier before being moved are
+            // moved now
+            let has_id = !metadata.id.is_empty();
+            let id = if !has_id {
+                Self::new_save_id()
+            } else if metadata.id != file_name && !Self::exists(&metadata.id) {
+                metadata.id
+            } else {
+                continue;
+            };
+
+            // Updating the header writes to the temporary save path, so any
+            // unfinished save left there is moved out of the way first
+            Self::rename_if_exists(&get_tmp_save_path(&file_name), &get_tmp_save_path(&id)).await?;
+
+            // Only headers without an identifier need one written, and those
+            // were written before headers were authenticated, so they are
+            // kept as legacy headers without the data encryption key
+            if !has_id {
+                let new_id = id.clone();
+                Self::update_header(&save_path, None, |header| {
+                    header.metadata.id = new_id;
+                    Ok(())
+                })
+                .await?;
+            }
+            let new_save_path = get_save_path(&id);
+            fs::rename(&save_path, &new_save_path).await?;
+
+            Self::rename_if_exists(&get_backups_path(&file_name), &get_backups_path(&id)).await?;
+            Self::rename_if_exists(&get_legacy_db_path(&file_name), &get_legacy_db_path(&id))
+                .await?;
+            Self::move_recent(&save_path, &new_save_path).await?;
+
+            migrated.push(id);
+        }
+
+        Ok(migrated)
+    }
+
+    /// Renames a file or directory if it exists.
+    async fn rename_if_exists(from: &str, to: &str) -> io::Result<()> {
+        if Path::new(from).exists() {
+            fs::rename(from, to).await?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Deref for Save {
@@ -423,73 +1603,600 @@ impl DerefMut for Save {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::DBTag;
+    use crate::{
+        convert_file_name, new_id, DBAccount, DBAccountTransaction, DBAccountTransactionSplit,
+        DBAccountTransactionTag, DBBudget, DBInstitution, DBTag, SCHEMA_VERSION,
+    };
     use common::Tag;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
     const TEST_SAVE_NAMES: &[&str] = &["test", "My Test Save"];
 
+    /// Saves from every earlier schema version, by version, kept in
+    /// `db/fixtures`. Each holds one of everything, and unlocks with
+    /// [`FIXTURE_PASSWORD`].
+    const SCHEMA_FIXTURES: &[(u32, &str)] = &[
+        (0, "schema_v0"),
+        (1, "schema_v1"),
+        (2, "schema_v2"),
+        (3, "schema_v3"),
+        (4, "schema_v4"),
+    ];
+
+    /// The password to the fixture saves.
+    const FIXTURE_PASSWORD: &str = "password123";
+
+    /// Reads the encrypted data following a save file's header.
+    async fn read_save_data(id: &str) -> Vec<u8> {
+        let mut save_file = File::open(get_save_path(id)).await.unwrap();
+        SaveHeader::read(&mut save_file, id).await.unwrap();
+        let mut data = Vec::new();
+        save_file.read_to_end(&mut data).await.unwrap();
+        data
+    }
+
+    async fn find_leftover(id: &str, kind: LeftoverKind) -> Option<LeftoverSave> {
+        Save::leftovers()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|leftover| leftover.id == id && leftover.kind == kind)
+    }
+
     #[tokio::test]
     async fn test_save() {
+        set_data_dir(project_root::get_project_root().unwrap());
+
         let name = "Test save name";
         let description = "Test save description";
         let password = "password123";
 
         // Create/use/close
-        let mut save = Save::create(name, description, password).await.unwrap();
+        let (mut save, recovery_phrase) = Save::create(name, description, password, false)
+            .await
+            .unwrap();
+        let id = save.this_metadata().id;
+        assert_eq!(save.key_slots().len(), 2);
         let tag1 = Tag::create(&mut save, "Test tag", "").await.unwrap();
         save.close().await.unwrap();
 
         // Get metadata
-        let metadata = Save::metadata(name).await.unwrap();
+        let metadata = Save::metadata(&id).await.unwrap();
+        assert_eq!(metadata.id, id);
         assert_eq!(&metadata.name, name);
         assert_eq!(&metadata.description, description);
         assert_eq!(metadata.created_at, metadata.last_opened_at);
 
+        // Password throttle key, where a copy elsewhere cannot claim the
+        // save's own identifier
+        let save_path = get_save_path(&id);
+        assert_eq!(
+            Save::password_throttle_key_at(&save_path).await.unwrap(),
+            id
+        );
+        let copy_path = std::env::temp_dir().join(format!("{}.{SAVE_EXT}", new_id()));
+        fs::copy(&save_path, &copy_path).await.unwrap();
+        let copy_path = copy_path.display().to_string();
+        let copy_key = Save::password_throttle_key_at(&copy_path).await.unwrap();
+        assert_ne!(copy_key, id);
+        assert_eq!(
+            copy_key,
+            fs::canonicalize(&copy_path)
+                .await
+                .unwrap()
+                .display()
+                .to_string()
+        );
+        fs::remove_file(&copy_path).await.unwrap();
+
         // Open/use
-        let mut save = Save::open(name, password).await.unwrap();
+        let mut save = Save::open(&id, password).await.unwrap();
         let tag2 = Tag::get(&mut save, &tag1.id).await.unwrap().unwrap();
         assert_eq!(tag1, tag2);
 
         // Save/close
+        assert_eq!(save.unsaved_changes().await.unwrap(), 0);
+        Tag::create(&mut save, "Another test tag", "")
+            .await
+            .unwrap();
+        assert_eq!(save.unsaved_changes().await.unwrap(), 1);
+        let saved_at = save.saved_at();
         save.save().await.unwrap();
+        assert_eq!(save.unsaved_changes().await.unwrap(), 0);
+        assert!(save.saved_at() >= saved_at);
+        save.close().await.unwrap();
+
+        // Recovery phrase
+        let save = Save::open(&id, &recovery_phrase.to_lowercase().replace('-', " "))
+            .await
+            .unwrap();
+        save.close().await.unwrap();
+
+        // Key slots
+        let other_password = "Another password";
+        let mut save = Save::open(&id, password).await.unwrap();
+        let password_slot = save.add_password_key_slot(other_password).await.unwrap();
+        assert_eq!(password_slot.kind, KeySlotKind::Password);
+        let (recovery_slot, other_recovery_phrase) = save.add_recovery_key_slot().await.unwrap();
+        assert_eq!(recovery_slot.kind, KeySlotKind::RecoveryPhrase);
+        assert_ne!(recovery_phrase, other_recovery_phrase);
+        let key_slots = save.key_slots();
+        assert_eq!(key_slots.len(), 4);
+        assert!(key_slots.contains(&password_slot));
+        assert!(key_slots.contains(&recovery_slot));
+        save.close().await.unwrap();
+        let mut save = Save::open(&id, other_password).await.unwrap();
+        save.remove_key_slot(password_slot.id).await.unwrap();
+        let err = save.remove_key_slot(password_slot.id).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::KeySlotNotFound)
+        );
+        save.close().await.unwrap();
+        Save::open(&id, other_password).await.unwrap_err();
+        let mut save = Save::open(&id, &other_recovery_phrase).await.unwrap();
+        for key_slot in save.key_slots() {
+            if key_slot.id != recovery_slot.id {
+                save.remove_key_slot(key_slot.id).await.unwrap();
+            }
+        }
+        let err = save.remove_key_slot(recovery_slot.id).await.unwrap_err();
+        assert!(matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::LastKeySlot));
+        save.add_password_key_slot(password).await.unwrap();
+        save.close().await.unwrap();
+        Save::open(&id, &recovery_phrase).await.unwrap_err();
+        let save = Save::open(&id, password).await.unwrap();
         save.close().await.unwrap();
 
+        // Tampered header
+        let save_path = get_save_path(&id);
+        let save_data = fs::read(&save_path).await.unwrap();
+        let created_at = format!(
+            "\"created_at\":{}",
+            metadata.created_at.and_utc().timestamp()
+        );
+        let offset = save_data
+            .windows(created_at.len())
+            .position(|window| window == created_at.as_bytes())
+            .unwrap();
+        let mut tampered_save_data = save_data.clone();
+        tampered_save_data[offset + created_at.len() - 1] ^= 1;
+        fs::write(&save_path, &tampered_save_data).await.unwrap();
+        assert_ne!(
+            Save::metadata(&id).await.unwrap().created_at,
+            metadata.created_at
+        );
+        let err = Save::open(&id, password).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveHeaderTampered)
+        );
+        let err = Save::set_name(&id, "Tampered name", password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveHeaderTampered)
+        );
+
+        // Tampered header downgraded to before headers were authenticated
+        let mut offset = SAVE_MAGIC.len() + 2;
+        for _ in 0..2 {
+            let size =
+                decode_section_size(&save_data[offset..offset + LEN_SIZE].try_into().unwrap());
+            offset += LEN_SIZE + size;
+        }
+        let mac_size =
+            decode_section_size(&save_data[offset..offset + LEN_SIZE].try_into().unwrap());
+        let mut downgraded_save_data = tampered_save_data[..offset].to_vec();
+        downgraded_save_data[SAVE_MAGIC.len()..SAVE_MAGIC.len() + 2]
+            .copy_from_slice(&6u16.to_be_bytes());
+        downgraded_save_data.extend(&save_data[offset + LEN_SIZE + mac_size..]);
+        fs::write(&save_path, &downgraded_save_data).await.unwrap();
+        let err = Save::open(&id, password).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveHeaderTampered)
+        );
+        let err = Save::set_name(&id, "Tampered name", password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveHeaderTampered)
+        );
+        assert_eq!(fs::read(&save_path).await.unwrap(), downgraded_save_data);
+        fs::write(&save_path, &save_data).await.unwrap();
+        let save = Save::open(&id, password).await.unwrap();
+        save.close().await.unwrap();
+
+        // Names that map to the same file name
+        let (same_save1, _) = Save::create("Same name", "", password, false)
+            .await
+            .unwrap();
+        let (same_save2, _) = Save::create("Same_name", "", password, false)
+            .await
+            .unwrap();
+        let same_id1 = same_save1.this_metadata().id;
+        let same_id2 = same_save2.this_metadata().id;
+        assert_ne!(same_id1, same_id2);
+        same_save1.close().await.unwrap();
+        same_save2.close().await.unwrap();
+        assert_eq!(Save::metadata(&same_id1).await.unwrap().name, "Same name");
+        assert_eq!(Save::metadata(&same_id2).await.unwrap().name, "Same_name");
+        Save::delete(&same_id1, password).await.unwrap();
+        Save::delete(&same_id2, password).await.unwrap();
+
+        // Legacy format, key derivation, cipher and file name
+        let legacy_name = "Legacy save name";
+        let legacy_file_name = convert_file_name(legacy_name);
+        let mut legacy_db = DB::connect(legacy_name).await.unwrap();
+        legacy_db.init_tables().await.unwrap();
+        let tag3 = Tag::create(&mut legacy_db, "Legacy tag", "").await.unwrap();
+        let legacy_key = password_to_key(password, &KdfParams::LegacyBcrypt)
+            .await
+            .unwrap();
+        let mut legacy_save_file = File::create(get_save_path(&legacy_file_name))
+            .await
+            .unwrap();
+        let legacy_metadata = SaveMetadata {
+            id: String::new(),
+            name: legacy_name.to_owned(),
+            ..metadata.clone()
+        };
+        write_section(
+            &mut legacy_save_file,
+            write_legacy_metadata(&legacy_metadata).as_bytes(),
+        )
+        .await
+        .unwrap();
+        let legacy_data = legacy_db.serialize().await.unwrap();
+        legacy_db.delete().await.unwrap();
+        for chunk in legacy_data.chunks(READER_CAPACITY) {
+            let encrypted_data = aes_encrypt(&legacy_key, chunk).unwrap();
+            write_section(&mut legacy_save_file, &encrypted_data)
+                .await
+                .unwrap();
+        }
+        drop(legacy_save_file);
+        let legacy_backups_path = get_backups_path(&legacy_file_name);
+        fs::create_dir_all(&legacy_backups_path).await.unwrap();
+        fs::copy(
+            get_save_path(&legacy_file_name),
+            get_backup_path(&legacy_file_name, "20240101000000000000"),
+        )
+        .await
+        .unwrap();
+        let mut legacy_id = None;
+        for migrated_id in Save::migrate_legacy_names().await.unwrap() {
+            if Save::metadata(&migrated_id).await.unwrap().name == legacy_name {
+                legacy_id = Some(migrated_id);
+            }
+        }
+        let legacy_id = legacy_id.unwrap();
+        assert!(!Save::exists(&legacy_file_name));
+        assert!(!Path::new(&legacy_backups_path).exists());
+        assert_eq!(Save::backups(&legacy_id).await.unwrap().len(), 1);
+        assert!(!Save::migrate_legacy_names()
+            .await
+            .unwrap()
+            .contains(&legacy_id));
+        let mut save = Save::open(&legacy_id, password).await.unwrap();
+        assert!(!save.key_slots[0].kdf.is_legacy());
+        let tag4 = Tag::get(&mut save, &tag3.id).await.unwrap().unwrap();
+        assert_eq!(tag3, tag4);
+        save.close().await.unwrap();
+        let mut legacy_save_file = File::open(get_save_path(&legacy_id)).await.unwrap();
+        let mut magic = [0u8; SAVE_MAGIC.len()];
+        legacy_save_file.read_exact(&mut magic).await.unwrap();
+        assert_eq!(&magic, SAVE_MAGIC);
+        legacy_save_file.rewind().await.unwrap();
+        let legacy_header = SaveHeader::read(&mut legacy_save_file, &legacy_id)
+            .await
+            .unwrap();
+        assert_eq!(legacy_header.key_slots.len(), 1);
+        assert!(!legacy_header.key_slots[0].kdf.is_legacy());
+        assert!(legacy_header.key_slots[0].wrapped_key.is_some());
+        assert_eq!(legacy_header.cipher, SaveCipher::CURRENT);
+        assert_eq!(legacy_header.compression, SaveCompression::CURRENT);
+        assert_eq!(legacy_header.metadata.id, legacy_id);
+        assert_eq!(legacy_header.metadata.name, legacy_name);
+        let save = Save::open(&legacy_id, password).await.unwrap();
+        save.close().await.unwrap();
+        Save::delete(&legacy_id, password).await.unwrap();
+
+        // Leftover unfinished save
+        let save_path = get_save_path(&id);
+        let tmp_save_path = get_tmp_save_path(&id);
+        let old_save_data = fs::read(&save_path).await.unwrap();
+        let mut save = Save::open(&id, password).await.unwrap();
+        let tag5 = Tag::create(&mut save, "Unsaved tag", "").await.unwrap();
+        save.close().await.unwrap();
+        fs::rename(&save_path, &tmp_save_path).await.unwrap();
+        fs::write(&save_path, &old_save_data).await.unwrap();
+        let leftover = find_leftover(&id, LeftoverKind::UnfinishedSave)
+            .await
+            .unwrap();
+        assert!(leftover.save_modified_at.is_some());
+        let err = Save::recover_leftover(&leftover, "wrong password")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::InvalidSavePassword)
+        );
+        let leftover_data = fs::read(&tmp_save_path).await.unwrap();
+        let created_at = format!(
+            "\"created_at\":{}",
+            metadata.created_at.and_utc().timestamp()
+        );
+        let offset = leftover_data
+            .windows(created_at.len())
+            .position(|window| window == created_at.as_bytes())
+            .unwrap();
+        let mut tampered_leftover_data = leftover_data.clone();
+        tampered_leftover_data[offset + created_at.len() - 1] ^= 1;
+        fs::write(&tmp_save_path, &tampered_leftover_data)
+            .await
+            .unwrap();
+        let err = Save::recover_leftover(&leftover, password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveHeaderTampered)
+        );
+        assert_eq!(fs::read(&save_path).await.unwrap(), old_save_data);
+        fs::write(&tmp_save_path, &leftover_data).await.unwrap();
+        let backups_before = Save::backups(&id).await.unwrap();
+        Save::recover_leftover(&leftover, password).await.unwrap();
+        assert!(find_leftover(&id, LeftoverKind::UnfinishedSave)
+            .await
+            .is_none());
+        let new_backups = Save::backups(&id)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|backup| !backups_before.contains(backup))
+            .collect::<Vec<_>>();
+        assert_eq!(new_backups.len(), 1);
+        assert_eq!(
+            fs::read(get_backup_path(&id, &new_backups[0].id))
+                .await
+                .unwrap(),
+            old_save_data
+        );
+        let mut save = Save::open(&id, password).await.unwrap();
+        assert!(Tag::get(&mut save, &tag5.id).await.unwrap().is_some());
+        save.close().await.unwrap();
+
+        // Leftover unfinished save that was cut short
+        let save_data = fs::read(&save_path).await.unwrap();
+        fs::write(&tmp_save_path, &save_data[..save_data.len() - 1])
+            .await
+            .unwrap();
+        let leftover = find_leftover(&id, LeftoverKind::UnfinishedSave)
+            .await
+            .unwrap();
+        let err = Save::recover_leftover(&leftover, password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::LeftoverNotRecoverable)
+        );
+        Save::discard_leftover(&leftover).await.unwrap();
+        assert!(!Path::new(&tmp_save_path).exists());
+        let err = Save::discard_leftover(&leftover).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::LeftoverNotFound)
+        );
+
+        // Leftover decrypted database
+        fs::create_dir_all(get_legacy_db_dir_path()).await.unwrap();
+        let legacy_db_path = get_legacy_db_path(&id);
+        let mut leftover_db = DB::create(&id).await.unwrap();
+        let tag6 = Tag::create(&mut leftover_db, "Leftover tag", "")
+            .await
+            .unwrap();
+        let leftover_data = leftover_db.serialize().await.unwrap();
+        leftover_db.delete().await.unwrap();
+        fs::write(&legacy_db_path, &leftover_data).await.unwrap();
+        let leftover = find_leftover(&id, LeftoverKind::DecryptedDatabase)
+            .await
+            .unwrap();
+        let backups_before = Save::backups(&id).await.unwrap();
+        Save::recover_leftover(&leftover, password).await.unwrap();
+        assert!(!Path::new(&legacy_db_path).exists());
+        let new_backups = Save::backups(&id)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|backup| !backups_before.contains(backup))
+            .collect::<Vec<_>>();
+        assert_eq!(new_backups.len(), 2);
+        let mut backup = Save::open_backup(&new_backups[1], password).await.unwrap();
+        assert!(Tag::get(&mut backup, &tag5.id).await.unwrap().is_some());
+        assert!(Tag::get(&mut backup, &tag6.id).await.unwrap().is_none());
+        backup.close().await.unwrap();
+        let mut save = Save::open(&id, password).await.unwrap();
+        assert!(Tag::get(&mut save, &tag6.id).await.unwrap().is_some());
+        assert!(Tag::get(&mut save, &tag5.id).await.unwrap().is_none());
+        save.close().await.unwrap();
+
+        // Leftover decrypted database that is corrupted
+        fs::write(&legacy_db_path, b"not a database").await.unwrap();
+        let leftover = find_leftover(&id, LeftoverKind::DecryptedDatabase)
+            .await
+            .unwrap();
+        let err = Save::recover_leftover(&leftover, password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::LeftoverNotRecoverable)
+        );
+        Save::discard_leftover(&leftover).await.unwrap();
+        assert!(!Path::new(&legacy_db_path).exists());
+
+        // Migrate legacy directories
+        let legacy_root = std::env::temp_dir().join(format!("level_legacy_{}", new_id()));
+        let legacy_saves_path = legacy_root.join(SAVES_DIR);
+        let legacy_db_dir_path = legacy_root.join(LEGACY_DB_DIR);
+        fs::create_dir_all(&legacy_saves_path).await.unwrap();
+        fs::create_dir_all(&legacy_db_dir_path).await.unwrap();
+        let save_path = get_save_path(&id);
+        let save_file_name = Path::new(&save_path).file_name().unwrap();
+        fs::copy(&save_path, legacy_saves_path.join(save_file_name))
+            .await
+            .unwrap();
+        fs::remove_file(&save_path).await.unwrap();
+        let legacy_db_file_name = Path::new(&legacy_db_path).file_name().unwrap();
+        fs::write(legacy_db_dir_path.join(legacy_db_file_name), &leftover_data)
+            .await
+            .unwrap();
+        fs::write(legacy_db_dir_path.join("unrelated.db"), b"")
+            .await
+            .unwrap();
+        let moved = Save::migrate_legacy_dirs(&legacy_root).unwrap();
+        assert_eq!(moved.len(), 2);
+        assert!(Save::exists(&id));
+        assert!(Path::new(&legacy_db_path).exists());
+        assert!(!legacy_saves_path.exists());
+        assert!(legacy_db_dir_path.join("unrelated.db").exists());
+        let moved = Save::migrate_legacy_dirs(&legacy_root).unwrap();
+        assert!(moved.is_empty());
+        let leftover = find_leftover(&id, LeftoverKind::DecryptedDatabase)
+            .await
+            .unwrap();
+        Save::discard_leftover(&leftover).await.unwrap();
+        fs::remove_dir_all(&legacy_root).await.unwrap();
+
+        // Backups
+        let backups = Save::backups(&id).await.unwrap();
+        assert!(!backups.is_empty());
+        assert!(backups.len() as u64 <= BackupPolicy::default().max_backups);
+        assert!(backups
+            .windows(2)
+            .all(|pair| pair[0].created_at > pair[1].created_at));
+        let mut save = Save::open(&id, password).await.unwrap();
+        save.set_backup_policy(BackupPolicy {
+            max_backups: 2,
+            max_age_days: 0,
+        });
+        let tag7 = Tag::create(&mut save, "Backed up tag", "").await.unwrap();
+        save.save().await.unwrap();
+        save.backup().await.unwrap();
+        let backups = Save::backups(&id).await.unwrap();
+        assert_eq!(backups.len(), 2);
+        let backup = backups[0].clone();
+        let tag8 = Tag::create(&mut save, "Tag after backup", "")
+            .await
+            .unwrap();
+        let mut backup_save = Save::open_backup(&backup, password).await.unwrap();
+        let comparisons = save.compare_with(&mut backup_save).await.unwrap();
+        for comparison in comparisons {
+            let changed = if comparison.table == "tag" { 1 } else { 0 };
+            assert_eq!(comparison.rows, comparison.backup_rows + changed);
+            assert_eq!(comparison.rows_not_in_backup, changed);
+            assert_eq!(comparison.backup_rows_not_in_save, 0);
+        }
+        let err = backup_save.save().await.unwrap_err();
+        assert!(matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveReadOnly));
+        backup_save.close().await.unwrap();
+        save.close().await.unwrap();
+        let err = Save::restore_backup(&backup, "wrong password")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::InvalidSavePassword)
+        );
+        Save::restore_backup(&backup, password).await.unwrap();
+        assert_eq!(Save::backups(&id).await.unwrap().len(), 3);
+        let mut save = Save::open(&id, password).await.unwrap();
+        assert!(Tag::get(&mut save, &tag7.id).await.unwrap().is_some());
+        assert!(Tag::get(&mut save, &tag8.id).await.unwrap().is_none());
+        save.close().await.unwrap();
+        let missing_backup = SaveBackup {
+            id: "19700101000000000000".to_owned(),
+            ..backup
+        };
+        let err = Save::restore_backup(&missing_backup, password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::BackupNotFound)
+        );
+
         // Check existence
-        let exists = Save::exists(name);
+        let exists = Save::exists(&id);
         assert!(exists);
-        let exists = Save::exists("wrong name");
+        let exists = Save::exists("wrong id");
         assert!(!exists);
 
         // Wrong password
-        let failed_save = Save::open(name, "wrong password").await;
+        let failed_save = Save::open(&id, "wrong password").await;
         assert!(failed_save.is_err());
 
         // Set name
         let new_name = "New name";
-        Save::set_name(name, new_name, password).await.unwrap();
-        let failed_save = Save::open(name, password).await;
-        assert!(failed_save.is_err());
-        let save = Save::open(new_name, password).await.unwrap();
+        Save::set_name(&id, new_name, password).await.unwrap();
+        let save = Save::open(&id, password).await.unwrap();
+        assert_eq!(&save.this_metadata().name, new_name);
         save.close().await.unwrap();
-        let metadata = Save::metadata(new_name).await.unwrap();
+        let metadata = Save::metadata(&id).await.unwrap();
         assert_eq!(&metadata.name, new_name);
+        assert!(!Save::backups(&id).await.unwrap().is_empty());
 
         // Set description
         let new_description = "New description";
-        Save::set_description(new_name, new_description, password)
+        Save::set_description(&id, new_description, password)
             .await
             .unwrap();
-        let metadata = Save::metadata(new_name).await.unwrap();
+        let metadata = Save::metadata(&id).await.unwrap();
         assert_eq!(&metadata.description, new_description);
 
         // Change password
         let new_password = "New password";
-        Save::change_password(new_name, password, new_password)
+        let data_before = read_save_data(&id).await;
+        let header_before = Save::read_header(&id).await.unwrap();
+        Save::change_password(&id, password, new_password)
             .await
             .unwrap();
-        let failed_save = Save::open(new_name, password).await;
+        let data_after = read_save_data(&id).await;
+        let header_after = Save::read_header(&id).await.unwrap();
+        assert_eq!(data_before, data_after);
+        assert_ne!(header_before.key_slots, header_after.key_slots);
+        assert_eq!(header_before.key_slots.len(), header_after.key_slots.len());
+        Save::change_password(&id, password, new_password)
+            .await
+            .unwrap_err();
+        let failed_save = Save::open(&id, password).await;
         assert!(failed_save.is_err());
-        let save = Save::open(new_name, new_password).await.unwrap();
+        let header_before = Save::read_header(&id).await.unwrap();
+        let err = Save::change_password(&id, &other_recovery_phrase, password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::RecoveryPhraseNotPassword)
+        );
+        assert_eq!(Save::read_header(&id).await.unwrap(), header_before);
+        let save = Save::open(&id, &other_recovery_phrase).await.unwrap();
+        save.close().await.unwrap();
+
+        // Metadata and passwords cannot be changed while the save is open
+        let save = Save::open(&id, new_password).await.unwrap();
+        let header_before = Save::read_header(&id).await.unwrap();
+        let err = Save::set_name(&id, "Name while open", new_password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveOpenElsewhere)
+        );
+        let err = Save::set_description(&id, "Description while open", new_password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveOpenElsewhere)
+        );
+        let err = Save::change_password(&id, new_password, password)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Expected(ref e) if **e == ExpectedCommandError::SaveOpenElsewhere)
+        );
+        assert_eq!(Save::read_header(&id).await.unwrap(), header_before);
         save.close().await.unwrap();
 
         // List
@@ -499,12 +2206,16 @@ mod tests {
             .filter(|s| !TEST_SAVE_NAMES.contains(&s.name.as_str())) // ignore the test saves
             .collect::<Vec<_>>();
         assert_eq!(saves.len(), 1);
+        assert_eq!(saves[0].id, id);
         assert_eq!(&saves[0].name, new_name);
         let name2 = "Other save";
         let description2 = "Another save";
         let password2 = "A password even worse than 'password123'";
-        let other_save = Save::create(name2, description2, password2).await.unwrap();
+        let (other_save, _) = Save::create(name2, description2, password2, true)
+            .await
+            .unwrap();
         assert_eq!(&other_save.metadata.name, name2);
+        let id2 = other_save.this_metadata().id;
         other_save.close().await.unwrap();
         let saves = Save::list().await.unwrap();
         let saves = saves
@@ -512,20 +2223,54 @@ mod tests {
             .filter(|s| !TEST_SAVE_NAMES.contains(&s.name.as_str())) // ignore the test saves
             .collect::<Vec<_>>();
         assert_eq!(saves.len(), 2);
-        let save1 = saves.iter().find(|s| s.name == new_name).unwrap();
-        let save2 = saves.iter().find(|s| s.name == name2).unwrap();
+        let save1 = saves.iter().find(|s| s.id == id).unwrap();
+        let save2 = saves.iter().find(|s| s.id == id2).unwrap();
         assert_eq!(&save1.description, new_description);
-        assert_eq!(&save2.description, description2);
+        assert!(!save1.encrypted);
+        assert!(save2.encrypted);
+        assert!(save2.name.is_empty());
+        assert!(save2.description.is_empty());
+
+        // Encrypted metadata
+        let new_name2 = "Renamed other save";
+        Save::set_name(&id2, new_name2, password2).await.unwrap();
+        assert!(Save::metadata(&id2).await.unwrap().name.is_empty());
+        let mut other_save = Save::open(&id2, password2).await.unwrap();
+        let metadata2 = other_save.this_metadata();
+        assert_eq!(&metadata2.name, new_name2);
+        assert_eq!(&metadata2.description, description2);
+        other_save.set_metadata_encrypted(false).await.unwrap();
+        other_save.close().await.unwrap();
+        let metadata2 = Save::metadata(&id2).await.unwrap();
+        assert!(!metadata2.encrypted);
+        assert_eq!(&metadata2.name, new_name2);
+
+        // Optional compression
+        let mut other_save = Save::open(&id2, password2).await.unwrap();
+        assert!(other_save.is_compressed());
+        other_save.set_compressed(false).await.unwrap();
+        other_save.close().await.unwrap();
+        let mut other_save_file = File::open(get_save_path(&id2)).await.unwrap();
+        let header2 = SaveHeader::read(&mut other_save_file, &id2).await.unwrap();
+        assert_eq!(header2.compression, SaveCompression::None);
+        let mut other_save = Save::open(&id2, password2).await.unwrap();
+        assert!(!other_save.is_compressed());
+        other_save.set_compressed(true).await.unwrap();
+        other_save.close().await.unwrap();
+        let mut other_save_file = File::open(get_save_path(&id2)).await.unwrap();
+        let header2 = SaveHeader::read(&mut other_save_file, &id2).await.unwrap();
+        assert_eq!(header2.compression, SaveCompression::CURRENT);
 
         // Delete
-        Save::delete(new_name, new_password).await.unwrap();
+        Save::delete(&id, new_password).await.unwrap();
+        assert!(!Path::new(&get_backups_path(&id)).exists());
         let saves = Save::list().await.unwrap();
         let saves = saves
             .into_iter()
             .filter(|s| !TEST_SAVE_NAMES.contains(&s.name.as_str())) // ignore the test saves
             .collect::<Vec<_>>();
         assert_eq!(saves.len(), 1);
-        Save::delete(name2, password2).await.unwrap();
+        Save::delete(&id2, password2).await.unwrap();
         let saves = Save::list().await.unwrap();
         let saves = saves
             .into_iter()
@@ -533,4 +2278,102 @@ mod tests {
             .collect::<Vec<_>>();
         assert!(saves.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_schema_fixtures() {
+        set_data_dir(project_root::get_project_root().unwrap());
+
+        let fixtures_dir = project_root::get_project_root()
+            .unwrap()
+            .join("db/fixtures");
+        let dir = std::env::temp_dir().join(format!("level_fixtures_{}", new_id()));
+        fs::create_dir_all(&dir).await.unwrap();
+
+        for (version, fixture) in SCHEMA_FIXTURES {
+            let save_path = dir
+                .join(format!("{fixture}.{SAVE_EXT}"))
+                .display()
+                .to_string();
+            fs::copy(
+                fixtures_dir.join(format!("{fixture}.{SAVE_EXT}")),
+                &save_path,
+            )
+            .await
+            .unwrap();
+            let id = Save::metadata_at(&save_path).await.unwrap().id;
+            let backups_path = get_backups_path(&id);
+            if Path::new(&backups_path).exists() {
+                fs::remove_dir_all(&backups_path).await.unwrap();
+            }
+
+            // Opening the save brings its schema up to date, backing it up
+            // as it was first
+            let mut save = Save::open_at(&save_path, FIXTURE_PASSWORD).await.unwrap();
+            assert!(*version < SCHEMA_VERSION);
+            assert_eq!(save.schema_version().await.unwrap(), SCHEMA_VERSION);
+            let backups = Save::list_backups(&id, fixture).await.unwrap();
+            assert_eq!(backups.len(), 1);
+
+            // The data survives the migrations
+            let accounts = Account::list(&mut save).await.unwrap();
+            assert_eq!(accounts.len(), 1);
+            assert_eq!(accounts[0].get_currency(), Currency::default());
+            let transactions = AccountTransaction::list_within(&mut save, &accounts[0])
+                .await
+                .unwrap();
+            assert_eq!(transactions.len(), 1);
+            assert_eq!(transactions[0].name, "Fixture transaction");
+            let subcategory = transactions[0].get_subcategory(&mut save).await.unwrap();
+            assert_eq!(subcategory.unwrap().name, "Fixture subcategory");
+            let tags = AccountTransactionTag::list_by_transaction(&mut save, &transactions[0])
+                .await
+                .unwrap();
+            assert_eq!(tags.len(), 1);
+            assert_eq!(Institution::list(&mut save).await.unwrap().len(), 1);
+
+            // Fixtures from schema version 4 onwards also split the
+            // transaction into a single line
+            let splits = transactions[0].get_splits(&mut save).await.unwrap();
+            assert_eq!(splits.len(), usize::from(*version >= 4));
+            if let Some(split) = splits.first() {
+                assert_eq!(split.memo.as_deref(), Some("Fixture split"));
+                assert_eq!(split.get_tags(&mut save).await.unwrap().len(), 1);
+            }
+
+            // Amounts are exact once migrated. Fixtures from schema version 1
+            // onwards also hold a budget, its limit stored as 0.1 + 0.2
+            assert_eq!(transactions[0].amount, Money::from_minor_units(1234));
+            let budget = Budget::get(&mut save, &accounts[0]).await.unwrap();
+            assert_eq!(budget.is_some(), *version >= 1);
+            if let Some(budget) = budget {
+                assert_eq!(budget.total_limit, Money::from_minor_units(30));
+            }
+            save.close().await.unwrap();
+
+            // The backup is the save from before the migrations
+            let mut backup_save = Save::open_backup(&backups[0], FIXTURE_PASSWORD)
+                .await
+                .unwrap();
+            assert_eq!(backup_save.schema_version().await.unwrap(), SCHEMA_VERSION);
+            backup_save.close().await.unwrap();
+            let backup_path = get_backup_path(&id, &backups[0].id);
+            let mut backup_file = File::open(&backup_path).await.unwrap();
+            let header = SaveHeader::read(&mut backup_file, fixture).await.unwrap();
+            let (_, key) = Save::unlock(&header, FIXTURE_PASSWORD).await.unwrap();
+            let data = header.decrypt_data(&mut backup_file, &key).await.unwrap();
+            let mut backup_db = DB::deserialize(fixture, &data).await.unwrap();
+            assert_eq!(backup_db.schema_version().await.unwrap(), *version);
+            backup_db.delete().await.unwrap();
+
+            // Once migrated, the save is not backed up again on opening
+            let save = Save::open_at(&save_path, FIXTURE_PASSWORD).await.unwrap();
+            assert_eq!(Save::list_backups(&id, fixture).await.unwrap().len(), 2);
+            save.close().await.unwrap();
+
+            fs::remove_dir_all(&backups_path).await.unwrap();
+        }
+
+        // Clean up
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
 }
@@ -0,0 +1,213 @@
+use crate::save::*;
+use backend_common::*;
+use chrono::{Duration, NaiveDateTime, Utc};
+use common::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+
+/// The file in the data directory recording incorrect attempts to unlock
+/// saves.
+const PASSWORD_ATTEMPTS_FILE: &str = "password_attempts.json";
+
+/// How many incorrect passwords can be tried in a row before further
+/// attempts are throttled.
+const FREE_PASSWORD_ATTEMPTS: u32 = 3;
+
+/// How long attempts are throttled for once the free attempts have been
+/// used up, in seconds. This doubles with every further incorrect password.
+const BASE_LOCKOUT_SECS: i64 = 30;
+
+/// The longest attempts can be throttled for, in seconds.
+const MAX_LOCKOUT_SECS: i64 = 60 * 60;
+
+/// Gets the path to the file recording incorrect attempts to unlock saves.
+fn get_password_attempts_path() -> String {
+    format!("{}/{}", data_dir().display(), PASSWORD_ATTEMPTS_FILE)
+}
+
+/// Gets how long attempts are throttled for after the given number of
+/// incorrect passwords in a row, if they are throttled at all.
+fn lockout_duration(failures: u32) -> Option<Duration> {
+    let doublings = failures.checked_sub(FREE_PASSWORD_ATTEMPTS)?;
+    let lockout_secs = 2i64
+        .checked_pow(doublings)
+        .and_then(|factor| factor.checked_mul(BASE_LOCKOUT_SECS))
+        .map_or(MAX_LOCKOUT_SECS, |secs| secs.min(MAX_LOCKOUT_SECS));
+
+    Some(Duration::seconds(lockout_secs))
+}
+
+/// The incorrect attempts to unlock a save, as they are persisted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct PasswordAttempts {
+    /// How many incorrect passwords have been tried in a row.
+    failures: u32,
+    /// When the save can next be attempted, if attempts are throttled.
+    locked_until: Option<NaiveDateTime>,
+}
+
+/// Tracks incorrect attempts to unlock each save, backing off exponentially
+/// so that passwords cannot be guessed in a tight loop. Attempts are
+/// persisted so that restarting the application does not reset them.
+#[derive(Debug, Clone, Default)]
+pub struct PasswordThrottle {
+    /// The incorrect attempts, by save identifier, or by canonical path for
+    /// save files outside the saves directory.
+    attempts: HashMap<String, PasswordAttempts>,
+}
+
+impl PasswordThrottle {
+    /// Loads the persisted attempts. When there are none yet, or they cannot
+    /// be read, no attempts are throttled.
+    pub async fn load() -> Result<Self> {
+        let data = match fs::read(get_password_attempts_path()).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => Err(err)?,
+        };
+        let attempts = serde_json::from_slice(&data).unwrap_or_default();
+
+        Ok(Self { attempts })
+    }
+
+    /// Replaces the persisted attempts.
+    async fn persist(&self) -> Result<()> {
+        fs::create_dir_all(data_dir()).await?;
+
+        let attempts_path = get_password_attempts_path();
+        let tmp_attempts_path = get_tmp_path(&attempts_path);
+        let data = serde_json::to_vec_pretty(&self.attempts)?;
+
+        let mut tmp_file = File::create(&tmp_attempts_path).await?;
+        tmp_file.write_all(&data).await?;
+        replace_with_tmp_file(tmp_file, &tmp_attempts_path, &attempts_path).await?;
+
+        Ok(())
+    }
+
+    /// Checks whether the save with the given identifier can be attempted
+    /// now, failing with the remaining wait if attempts are throttled.
+    pub fn check(&self, save_id: &str) -> Result<()> {
+        let locked_until = self
+            .attempts
+            .get(save_id)
+            .and_then(|attempts| attempts.locked_until);
+
+        if let Some(locked_until) = locked_until {
+            let remaining = locked_until - Utc::now().naive_utc();
+
+            if remaining > Duration::zero() {
+                // Round up, so that a wait is never reported as over early
+                let remaining_secs = (remaining.num_milliseconds() + 999) / 1000;
+
+                Err(ExpectedCommandError::TooManyPasswordAttempts {
+                    remaining_secs: remaining_secs as u64,
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records an incorrect password for the save with the given identifier,
+    /// throttling further attempts once the free attempts are used up.
+    pub async fn record_failure(&mut self, save_id: &str) -> Result<()> {
+        let attempts = self
+            .attempts
+            .entry(save_id.to_owned())
+            .or_insert(PasswordAttempts {
+                failures: 0,
+                locked_until: None,
+            });
+        attempts.failures = attempts.failures.saturating_add(1);
+        attempts.locked_until =
+            lockout_duration(attempts.failures).map(|lockout| Utc::now().naive_utc() + lockout);
+
+        self.persist().await
+    }
+
+    /// Records that the save with the given identifier was unlocked,
+    /// forgetting its incorrect attempts.
+    pub async fn record_success(&mut self, save_id: &str) -> Result<()> {
+        if self.attempts.remove(save_id).is_some() {
+            self.persist().await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Password throttle tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_id;
+
+    #[test]
+    fn test_lockout_duration() {
+        assert_eq!(lockout_duration(0), None);
+        assert_eq!(lockout_duration(FREE_PASSWORD_ATTEMPTS - 1), None);
+        assert_eq!(
+            lockout_duration(FREE_PASSWORD_ATTEMPTS),
+            Some(Duration::seconds(BASE_LOCKOUT_SECS))
+        );
+        assert_eq!(
+            lockout_duration(FREE_PASSWORD_ATTEMPTS + 2),
+            Some(Duration::seconds(BASE_LOCKOUT_SECS * 4))
+        );
+        assert_eq!(
+            lockout_duration(FREE_PASSWORD_ATTEMPTS + 20),
+            Some(Duration::seconds(MAX_LOCKOUT_SECS))
+        );
+        assert_eq!(
+            lockout_duration(u32::MAX),
+            Some(Duration::seconds(MAX_LOCKOUT_SECS))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_password_throttle() {
+        set_data_dir(project_root::get_project_root().unwrap());
+
+        let save_id = new_id();
+        let other_save_id = new_id();
+        let mut throttle = PasswordThrottle::load().await.unwrap();
+
+        // The first few incorrect passwords are not throttled
+        for _ in 1..FREE_PASSWORD_ATTEMPTS {
+            throttle.check(&save_id).unwrap();
+            throttle.record_failure(&save_id).await.unwrap();
+        }
+        throttle.check(&save_id).unwrap();
+
+        // Once they are used up, attempts are refused for a while
+        throttle.record_failure(&save_id).await.unwrap();
+        let err = throttle.check(&save_id).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Expected(ref e) if matches!(
+                **e,
+                ExpectedCommandError::TooManyPasswordAttempts { remaining_secs }
+                    if remaining_secs > 0 && remaining_secs <= BASE_LOCKOUT_SECS as u64
+            )
+        ));
+        throttle.check(&other_save_id).unwrap();
+
+        // Attempts survive a restart
+        let reloaded_throttle = PasswordThrottle::load().await.unwrap();
+        assert!(reloaded_throttle.check(&save_id).is_err());
+        assert_eq!(
+            reloaded_throttle.attempts[&save_id].failures,
+            FREE_PASSWORD_ATTEMPTS
+        );
+
+        // Unlocking the save forgets its attempts
+        throttle.record_success(&save_id).await.unwrap();
+        throttle.check(&save_id).unwrap();
+        let reloaded_throttle = PasswordThrottle::load().await.unwrap();
+        assert!(!reloaded_throttle.attempts.contains_key(&save_id));
+    }
+}
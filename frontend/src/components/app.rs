@@ -17,6 +17,7 @@ pub fn App() -> Html {
     let subview = use_subview();
     let (alert, dispatch_alert) = use_store::<GlobalAlert>();
     let alert_state = use_state(|| false);
+    let unlock_count_state = use_state(|| 0usize);
 
     use_effect_once(move || {
         dispatch_theme.reduce_mut(|theme| theme.set_primary_color(APP_PRIMARY_COLOR));
@@ -40,6 +41,13 @@ pub fn App() -> Html {
         _ => {}
     }
 
+    // The view is mounted again once the save is unlocked, so that it reloads
+    // its data without leaving the view
+    let on_unlock = {
+        let unlock_count_state = unlock_count_state.clone();
+        move |_| unlock_count_state.set(*unlock_count_state + 1)
+    };
+
     let on_alert_close = {
         let dispatch_alert = dispatch_alert.clone();
         move |_| dispatch_alert.reduce_mut(|alert| alert.status = GlobalAlertStatus::Closing)
@@ -54,10 +62,11 @@ pub fn App() -> Html {
         UseDemoHandle::Resolved(true) => html! { <Demo /> },
         UseDemoHandle::Resolved(false) => html! {
             <div class="app">
-                <div class="main view-mount">
+                <div class="main view-mount" key={*unlock_count_state}>
                     {view.html_view()}
                     {subviews_html}
                 </div>
+                <LockScreen {on_unlock} />
                 <Alert
                     state={alert_state}
                     title={alert.title.clone()}
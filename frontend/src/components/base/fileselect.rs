@@ -31,6 +31,10 @@ pub struct FileSelectProps {
     /// Whether to allow selection of multiple files.
     #[prop_or(false)]
     pub multiple: bool,
+    /// Whether to choose where to save a new file rather than select an
+    /// existing one.
+    #[prop_or(false)]
+    pub save: bool,
     /// A list of acceptable file extensions. If empty, all files will be
     /// allowed.
     #[prop_or_default]
@@ -55,6 +59,7 @@ pub fn FileSelect(props: &FileSelectProps) -> Html {
         dialog_title,
         directory,
         multiple,
+        save,
         extensions,
         disabled,
         node,
@@ -70,6 +75,7 @@ pub fn FileSelect(props: &FileSelectProps) -> Html {
             .dialog_title(dialog_title.map(|s| s.to_string()))
             .directory(directory)
             .multiple(multiple)
+            .save(save)
             .extensions(extensions)
             .on_select(move |paths| {
                 if !paths.is_empty() && !disabled {
@@ -0,0 +1,153 @@
+use crate::components::base::*;
+use crate::components::misc::LoadingOverlay;
+use crate::hooks::*;
+use crate::util::*;
+use crate::view::View;
+use commands::FrontendCommands;
+use yew::prelude::*;
+use yew_hooks::prelude::*;
+
+/// How often to check whether the open save has been locked, in
+/// milliseconds.
+const SAVE_LOCKED_INTERVAL: u32 = 5000;
+
+/// Lock screen properties.
+#[derive(Properties, PartialEq, Clone)]
+pub struct LockScreenProps {
+    /// The callback called once the save has been unlocked.
+    #[prop_or_default]
+    pub on_unlock: Callback<()>,
+}
+
+/// A screen covering the current view while the open save is locked, asking
+/// for the save's password to unlock it again.
+#[function_component]
+pub fn LockScreen(props: &LockScreenProps) -> Html {
+    let LockScreenProps { on_unlock } = props.clone();
+
+    let view = use_view();
+    let (save_lock, dispatch_save_lock) = use_save_lock();
+
+    let save_password_state = use_state(String::new);
+    let unlock_save_error_state = use_state(|| None);
+    let loading_overlay_state = use_state(|| false);
+
+    let save_password = (*save_password_state).clone();
+    let unlock_save_error = (*unlock_save_error_state).clone();
+
+    let password_input_node = use_node_ref();
+    let password_input_focus = use_focus(password_input_node);
+
+    let get_save_locked = use_command_infallible(UseCommandInfallible::new({
+        clone_states!(dispatch_save_lock);
+        |backend| async move {
+            let locked = backend.save_locked().await;
+            dispatch_save_lock.reduce_mut(|save_lock| save_lock.locked = locked);
+        }
+    }));
+
+    use_interval(move || get_save_locked.run(), SAVE_LOCKED_INTERVAL);
+
+    use_effect_with(save_lock.locked, {
+        clone_states!(password_input_focus);
+        move |locked| {
+            if *locked {
+                password_input_focus.focus_late();
+            }
+        }
+    });
+
+    let try_unlock_save = use_command(
+        UseCommand::new(|backend| async move { backend.unlock_save(save_password).await })
+            .run_on_init(false)
+            .on_update({
+                clone_states!(
+                    save_password_state,
+                    unlock_save_error_state,
+                    loading_overlay_state,
+                    dispatch_save_lock
+                );
+                move |unlock_save_result| match unlock_save_result {
+                    UseCommandState::Init => {
+                        loading_overlay_state.set(false);
+                    }
+                    UseCommandState::Loading => {
+                        loading_overlay_state.set(true);
+                        unlock_save_error_state.set(None);
+                    }
+                    UseCommandState::Resolved(res) => match res {
+                        Ok(_) => {
+                            loading_overlay_state.set(false);
+                            save_password_state.set(String::new());
+                            dispatch_save_lock.reduce_mut(|save_lock| save_lock.locked = false);
+                            on_unlock.emit(());
+                        }
+                        Err(err) => {
+                            loading_overlay_state.set(false);
+                            unlock_save_error_state.set(Some(err.to_string()));
+                        }
+                    },
+                }
+            }),
+    );
+
+    let close_save = use_command(
+        UseCommand::new(|backend| async move { backend.close_save_file().await })
+            .run_on_init(false)
+            .on_update({
+                clone_states!(
+                    save_password_state,
+                    unlock_save_error_state,
+                    dispatch_save_lock
+                );
+                move |close_save_result| {
+                    if let UseCommandState::Resolved(_) = close_save_result {
+                        save_password_state.set(String::new());
+                        unlock_save_error_state.set(None);
+                        dispatch_save_lock.reduce_mut(|save_lock| save_lock.locked = false);
+                        view.set(View::Open);
+                    }
+                }
+            }),
+    );
+
+    let input_unlock_save = {
+        let try_unlock_save = try_unlock_save.clone();
+        move |_| try_unlock_save.run()
+    };
+    let click_unlock_save = move |_| try_unlock_save.run();
+    let click_close_save = move |_| close_save.run();
+
+    html! {
+        <div class={classes!("lock-screen", "bg-1", save_lock.locked.then_some("lock-screen-open"))}>
+            {save_lock.locked.then_some(html! {
+                <div class="lock-screen-inner">
+                    <img src="assets/svg/lock-solid.svg" class="lock-screen-icon" />
+                    <h2>{"Save locked"}</h2>
+                    <p>{"The save file was locked to keep it safe. Unlock it with its password or recovery phrase to pick up where you left off."}</p>
+                    <Input
+                        state={save_password_state}
+                        input_type={InputType::Password}
+                        label="Password or recovery phrase"
+                        required={true}
+                        on_submit={input_unlock_save}
+                        error={unlock_save_error}
+                        node={password_input_focus.node_ref()}
+                    />
+                    <div class="lock-screen-actions">
+                        <Button
+                            text="Unlock"
+                            on_click={click_unlock_save}
+                        />
+                        <Button
+                            text="Close save"
+                            on_click={click_close_save}
+                            style={ButtonStyle::Secondary}
+                        />
+                    </div>
+                </div>
+            })}
+            <LoadingOverlay state={loading_overlay_state} />
+        </div>
+    }
+}
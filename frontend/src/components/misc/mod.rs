@@ -1,9 +1,11 @@
 mod expandable_pane;
 mod loading;
 mod loading_overlay;
+mod lock_screen;
 mod save_icon;
 
 pub use expandable_pane::*;
 pub use loading::*;
 pub use loading_overlay::*;
+pub use lock_screen::*;
 pub use save_icon::*;
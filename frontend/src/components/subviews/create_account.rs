@@ -28,6 +28,8 @@ pub fn CreateAccount(props: &CreateAccountProps) -> Html {
     let account_name_error_state = use_state(|| None);
     let account_description_state = use_state(String::new);
     let account_description_error_state = use_state(|| None);
+    let account_currency_state = use_state(|| Currency::DEFAULT_CODE.to_owned());
+    let account_currency_error_state = use_state(|| None);
     let loading_state = use_state(|| false);
 
     let subview = use_subview();
@@ -41,9 +43,11 @@ pub fn CreateAccount(props: &CreateAccountProps) -> Html {
                 account_name_error_state,
                 account_description_state,
                 account_description_error_state,
+                account_currency_state,
+                account_currency_error_state,
             );
             |backend| async move {
-                if let Some((account_type, name, description)) = validate_all!(
+                if let Some((account_type, name, description, currency)) = validate_all!(
                     validate(
                         account_type_state,
                         account_type_error_state,
@@ -58,10 +62,15 @@ pub fn CreateAccount(props: &CreateAccountProps) -> Html {
                         account_description_state,
                         account_description_error_state,
                         validate_account_description
+                    ),
+                    validate(
+                        account_currency_state,
+                        account_currency_error_state,
+                        validate_account_currency
                     )
                 ) {
                     backend
-                        .create_account(account_type, name, description)
+                        .create_account(account_type, name, description, currency)
                         .await
                         .map(Some)
                 } else {
@@ -121,6 +130,13 @@ pub fn CreateAccount(props: &CreateAccountProps) -> Html {
                     label="Account description"
                     error={(*account_description_error_state).clone()}
                 />
+                <Input
+                    state={account_currency_state}
+                    label="Currency"
+                    on_submit={create_click.clone()}
+                    required={true}
+                    error={(*account_currency_error_state).clone()}
+                />
             </div>
             <div class="create-account-actions">
                 <Button
@@ -8,6 +8,9 @@ use commands::FrontendCommands;
 use yew::prelude::*;
 use yew_hooks::prelude::*;
 
+/// The file extension of save files.
+const SAVE_FILE_EXT: &str = "level";
+
 /// The page view to create a save.
 #[function_component]
 pub fn Create() -> Html {
@@ -20,8 +23,12 @@ pub fn Create() -> Html {
     let save_password_state = use_state(String::new);
     let save_password_error_state = use_state(|| None);
     let save_password_confirm_state = use_state(String::new);
+    let save_path_state = use_state(Vec::new);
+    let encrypt_metadata_state = use_state(|| false);
     let create_save_error_state = use_state(|| None);
     let loading_overlay_state = use_state(|| false);
+    let recovery_phrase_state = use_state(String::new);
+    let recovery_phrase_dialog_state = use_state(|| false);
 
     let save_name_node = use_node_ref();
     let save_name_focus = use_focus(save_name_node);
@@ -44,6 +51,8 @@ pub fn Create() -> Html {
                 save_password_state,
                 save_password_error_state,
                 save_password_confirm_state,
+                save_path_state,
+                encrypt_metadata_state,
             );
             |backend| async move {
                 if let Some((name, description, password)) = validate_all!(
@@ -60,18 +69,32 @@ pub fn Create() -> Html {
                         &*save_password_confirm_state
                     )
                 ) {
-                    backend
-                        .create_save_file(name, description, password)
+                    let save_path = save_path_state
+                        .first()
+                        .map(|save_path| save_path.display().to_string());
+                    let recovery_phrase = backend
+                        .create_save_file(
+                            save_path,
+                            name,
+                            description,
+                            password,
+                            *encrypt_metadata_state,
+                        )
                         .await?;
-                    Ok(true)
+                    Ok(Some(recovery_phrase))
                 } else {
-                    Ok(false)
+                    Ok(None)
                 }
             }
         })
         .run_on_init(false)
         .on_update({
-            clone_states!(view, create_save_error_state, loading_overlay_state);
+            clone_states!(
+                create_save_error_state,
+                loading_overlay_state,
+                recovery_phrase_state,
+                recovery_phrase_dialog_state
+            );
             move |create_save_result| match create_save_result {
                 UseCommandState::Init => {
                     loading_overlay_state.set(false);
@@ -81,11 +104,12 @@ pub fn Create() -> Html {
                     create_save_error_state.set(None);
                 }
                 UseCommandState::Resolved(res) => match res {
-                    Ok(success) => {
-                        if *success {
+                    Ok(recovery_phrase) => {
+                        if let Some(recovery_phrase) = recovery_phrase {
                             loading_overlay_state.set(false);
                             create_save_error_state.set(None);
-                            view.set(View::Save);
+                            recovery_phrase_state.set(recovery_phrase.clone());
+                            recovery_phrase_dialog_state.set(true);
                         }
                     }
                     Err(err) => {
@@ -97,7 +121,16 @@ pub fn Create() -> Html {
         }),
     );
 
+    let save_path_str = save_path_state
+        .first()
+        .map(|save_path| save_path.display().to_string())
+        .unwrap_or_else(|| "The default save folder".to_owned());
+
     let run_try_create_save = move |_| try_create_save.run();
+    let go_to_save = {
+        let view = view.clone();
+        move |_| view.set(View::Save)
+    };
     let go_back = move |_| view.set(View::Open);
 
     html! {
@@ -139,6 +172,21 @@ pub fn Create() -> Html {
                         required={true}
                     />
                 </div>
+                <div class="create-save-location">
+                    <FileSelect
+                        state={save_path_state}
+                        text="Choose location"
+                        style={FileSelectStyle::Secondary}
+                        dialog_title="Choose where to keep the save file"
+                        save={true}
+                        extensions={vec![SAVE_FILE_EXT.to_owned()]}
+                    />
+                    <span class="create-save-location-path">{save_path_str}</span>
+                </div>
+                <Checkbox
+                    state={encrypt_metadata_state}
+                    label="Hide the name and description until the save is unlocked"
+                />
                 <div class="create-save-error">
                     <Error message={create_save_error} size={ErrorSize::Small} />
                 </div>
@@ -154,6 +202,15 @@ pub fn Create() -> Html {
                     />
                 </div>
             </div>
+            <Dialog
+                state={recovery_phrase_dialog_state}
+                title="Recovery phrase"
+                ok_label="I have written it down"
+                on_close_request={go_to_save}
+            >
+                <p>{"If you ever forget the save's password, it can be unlocked with this recovery phrase instead. Write it down and keep it somewhere safe. It will not be shown again."}</p>
+                <p class="create-save-recovery-phrase">{(*recovery_phrase_state).clone()}</p>
+            </Dialog>
             <LoadingOverlay state={loading_overlay_state} />
         </div>
     }
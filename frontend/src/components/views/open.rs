@@ -6,38 +6,69 @@ use crate::view::View;
 use chrono::prelude::*;
 use commands::FrontendCommands;
 use common::*;
-use js_sys::{Date, Object};
-use wasm_bindgen::JsValue;
+use std::path::Path;
 use yew::prelude::*;
+use yew_hooks::prelude::*;
 
-/// Gets the user locale from the browser.
-fn user_locale() -> String {
-    let window = window();
-    let navigator = window.navigator();
-    let languages = navigator.languages();
-    let language = navigator.language();
-    let first_language = languages.get(0).as_string();
-
-    if let Some(lang) = first_language {
-        lang
-    } else if let Some(lang) = language {
-        lang
+/// The file extension of save files.
+const SAVE_FILE_EXT: &str = "level";
+
+/// How often the wait before a save can be unlocked again counts down, in
+/// milliseconds.
+const RETRY_COUNTDOWN_INTERVAL: u32 = 1000;
+
+/// The error shown when a save is already open in another instance of the
+/// application.
+const SAVE_OPEN_ELSEWHERE_ERROR: &str = "This save file is already open in another window or on another computer. Close it there, then try again.";
+
+/// The label shown in place of a save's name while it is encrypted.
+const ENCRYPTED_SAVE_LABEL: &str = "Locked save";
+
+/// Gets the name to show for a save before it is unlocked.
+fn save_display_name(metadata: &SaveMetadata) -> String {
+    if metadata.encrypted {
+        ENCRYPTED_SAVE_LABEL.to_owned()
     } else {
-        "en-US".to_owned()
+        metadata.name.clone()
     }
 }
 
-/// Formats a timestamp using the JS Date API's `.toLocaleString()` method.
-fn locale_timestamp_str(timestamp: &NaiveDateTime) -> String {
-    let datetime = timestamp.and_local_timezone(Utc).unwrap();
-    let js_millis = JsValue::from_f64(datetime.timestamp_millis() as f64);
-    let js_date = Date::new(&js_millis);
-    let locale = user_locale();
-    let options = Object::new();
-    js_date
-        .to_locale_string(&locale, &options)
-        .as_string()
-        .unwrap()
+/// Describes how long remains until a save can be unlocked again.
+fn retry_wait_description(remaining_secs: u64) -> String {
+    let wait = match remaining_secs {
+        0..=59 => format!("{remaining_secs} seconds"),
+        _ => format!("{}:{:02} minutes", remaining_secs / 60, remaining_secs % 60),
+    };
+
+    format!("Too many incorrect passwords were tried. Try again in {wait}.")
+}
+
+/// A save file selected to be unlocked.
+#[derive(Debug, Clone, PartialEq)]
+struct SelectedSave {
+    /// The path to the save file.
+    path: String,
+    /// The save's metadata, if it is known before the save is unlocked.
+    metadata: Option<SaveMetadata>,
+}
+
+/// Describes leftover save data and how it compares to its save file.
+fn leftover_description(leftover: &LeftoverSave) -> String {
+    let origin = match leftover.kind {
+        LeftoverKind::DecryptedDatabase => {
+            "An older version of the application left behind unencrypted data from this save file."
+        }
+        LeftoverKind::UnfinishedSave => {
+            "The application stopped while this save file was being written."
+        }
+    };
+    let comparison = if leftover.is_newer() {
+        "The leftover data is newer than the save file, and may hold changes that were never saved."
+    } else {
+        "The save file is newer than the leftover data."
+    };
+
+    format!("{origin} {comparison}")
 }
 
 /// The page view to open a save.
@@ -46,17 +77,39 @@ pub fn Open() -> Html {
     let view = use_view();
 
     let saves = use_command(UseCommand::new(|backend| async move {
-        backend.list_save_files().await
+        backend.recent_save_files().await
     }));
 
     let dialog_open_state = use_state(|| false);
-    let selected_save_state = use_state(|| None::<SaveMetadata>);
+    let selected_save_state = use_state(|| None::<SelectedSave>);
     let save_password_state = use_state(String::new);
     let unlock_save_error_state = use_state(|| None);
     let loading_overlay_state = use_state(|| false);
+    let retry_secs_state = use_state(|| 0u64);
 
     let save_password = (*save_password_state).clone();
-    let unlock_save_error = (*unlock_save_error_state).clone();
+    let unlock_save_error = match *retry_secs_state {
+        0 => (*unlock_save_error_state).clone(),
+        retry_secs => Some(retry_wait_description(retry_secs)),
+    };
+
+    use_interval(
+        {
+            clone_states!(retry_secs_state);
+            move || retry_secs_state.set(retry_secs_state.saturating_sub(1))
+        },
+        if *retry_secs_state > 0 {
+            RETRY_COUNTDOWN_INTERVAL
+        } else {
+            0
+        },
+    );
+
+    // Attempts are throttled per save, so the wait does not carry over
+    use_effect_with((*selected_save_state).clone(), {
+        clone_states!(retry_secs_state);
+        move |_| retry_secs_state.set(0)
+    });
 
     let password_input_node = use_node_ref();
     let password_input_focus = use_focus(password_input_node);
@@ -70,11 +123,11 @@ pub fn Open() -> Html {
         UseCommand::new({
             clone_states!(selected_save_state);
             move |backend| async move {
-                let save_name = selected_save_state
+                let save_path = selected_save_state
                     .as_ref()
-                    .map(|save| save.name.clone())
-                    .unwrap_or_else(|| "<SAVE NAME>".to_owned());
-                backend.open_save_file(save_name, save_password).await
+                    .map(|save| save.path.clone())
+                    .unwrap_or_else(|| "<SAVE PATH>".to_owned());
+                backend.open_save_file(save_path, save_password).await
             }
         })
         .run_on_init(false)
@@ -83,7 +136,8 @@ pub fn Open() -> Html {
                 view,
                 dialog_open_state,
                 loading_overlay_state,
-                unlock_save_error_state
+                unlock_save_error_state,
+                retry_secs_state
             );
             move |open_save_result| match open_save_result {
                 UseCommandState::Init => {
@@ -100,6 +154,15 @@ pub fn Open() -> Html {
                         unlock_save_error_state.set(None);
                         view.set(View::Save);
                     }
+                    Err(ExpectedCommandError::TooManyPasswordAttempts { remaining_secs }) => {
+                        loading_overlay_state.set(false);
+                        unlock_save_error_state.set(None);
+                        retry_secs_state.set(*remaining_secs);
+                    }
+                    Err(ExpectedCommandError::SaveOpenElsewhere) => {
+                        loading_overlay_state.set(false);
+                        unlock_save_error_state.set(Some(SAVE_OPEN_ELSEWHERE_ERROR.to_owned()));
+                    }
                     Err(err) => {
                         loading_overlay_state.set(false);
                         unlock_save_error_state.set(Some(err.to_string()));
@@ -109,19 +172,155 @@ pub fn Open() -> Html {
         }),
     );
 
+    let leftover_dialog_state = use_state(|| false);
+    let leftover_state = use_state(|| None::<LeftoverSave>);
+    let leftover_password_state = use_state(String::new);
+    let leftover_error_state = use_state(|| None);
+
+    let leftover_password = (*leftover_password_state).clone();
+    let leftover_error = (*leftover_error_state).clone();
+
+    let leftovers = use_command(
+        UseCommand::new(|backend| async move { backend.leftover_saves().await }).on_update({
+            clone_states!(
+                leftover_dialog_state,
+                leftover_state,
+                leftover_password_state,
+                leftover_error_state
+            );
+            move |leftovers_result| {
+                if let UseCommandState::Resolved(Ok(leftovers)) = leftovers_result {
+                    if let Some(leftover) = leftovers.first() {
+                        leftover_state.set(Some(leftover.clone()));
+                        leftover_password_state.set(String::new());
+                        leftover_error_state.set(None);
+                        leftover_dialog_state.set(true);
+                    }
+                }
+            }
+        }),
+    );
+
+    let try_recover_leftover = use_command(
+        UseCommand::new({
+            clone_states!(leftover_state);
+            move |backend| async move {
+                match (*leftover_state).clone() {
+                    Some(leftover) => {
+                        backend
+                            .recover_leftover_save(leftover, leftover_password)
+                            .await
+                    }
+                    None => Ok(()),
+                }
+            }
+        })
+        .run_on_init(false)
+        .on_update({
+            clone_states!(
+                saves,
+                leftovers,
+                leftover_dialog_state,
+                leftover_error_state,
+                loading_overlay_state
+            );
+            move |recover_result| match recover_result {
+                UseCommandState::Init => {
+                    loading_overlay_state.set(false);
+                }
+                UseCommandState::Loading => {
+                    loading_overlay_state.set(true);
+                    leftover_error_state.set(None);
+                }
+                UseCommandState::Resolved(res) => match res {
+                    Ok(_) => {
+                        leftover_dialog_state.set(false);
+                        loading_overlay_state.set(false);
+                        saves.run();
+                        leftovers.run();
+                    }
+                    Err(err) => {
+                        loading_overlay_state.set(false);
+                        leftover_error_state.set(Some(err.to_string()));
+                    }
+                },
+            }
+        }),
+    );
+
+    let try_discard_leftover = use_command(
+        UseCommand::new({
+            clone_states!(leftover_state);
+            move |backend| async move {
+                match (*leftover_state).clone() {
+                    Some(leftover) => backend.discard_leftover_save(leftover).await,
+                    None => Ok(()),
+                }
+            }
+        })
+        .run_on_init(false)
+        .on_update({
+            clone_states!(
+                saves,
+                leftovers,
+                leftover_dialog_state,
+                leftover_error_state,
+                loading_overlay_state
+            );
+            move |discard_result| match discard_result {
+                UseCommandState::Init => {
+                    loading_overlay_state.set(false);
+                }
+                UseCommandState::Loading => {
+                    loading_overlay_state.set(true);
+                    leftover_error_state.set(None);
+                }
+                UseCommandState::Resolved(res) => match res {
+                    Ok(_) => {
+                        leftover_dialog_state.set(false);
+                        loading_overlay_state.set(false);
+                        saves.run();
+                        leftovers.run();
+                    }
+                    Err(err) => {
+                        loading_overlay_state.set(false);
+                        leftover_error_state.set(Some(err.to_string()));
+                    }
+                },
+            }
+        }),
+    );
+
+    let select_save_file = use_file_select(
+        FileSelectConfig::new()
+            .dialog_title(Some("Open a save file".to_owned()))
+            .extensions(Some(vec![SAVE_FILE_EXT.to_owned()]))
+            .on_select({
+                clone_states!(dialog_open_state, selected_save_state, password_input_focus);
+                move |paths| {
+                    if let Some(path) = paths.first() {
+                        selected_save_state.set(Some(SelectedSave {
+                            path: path.display().to_string(),
+                            metadata: None,
+                        }));
+                        dialog_open_state.set(true);
+                        password_input_focus.focus_late();
+                    }
+                }
+            }),
+    );
+
     match &*saves {
         UseCommandState::Init | UseCommandState::Loading => html! { <Loading /> },
         UseCommandState::Resolved(saves) => match saves {
-            Err(_) => unreachable!("`list_save_files` throws no expected errors"),
+            Err(_) => unreachable!("`recent_save_files` throws no expected errors"),
             Ok(saves) => {
-                let mut saves = saves.clone();
-                saves.sort_by_key(|save| save.last_opened_at);
-                saves.reverse();
-
                 let save_buttons = saves
-                    .into_iter()
+                    .iter()
+                    .cloned()
                     .map(|save| {
-                        let save_name = save.name.clone();
+                        let save_name = save_display_name(&save.metadata);
+                        let opened_at_str = locale_timestamp_str(&save.opened_at);
                         let onclick = {
                             clone_states!(
                                 dialog_open_state,
@@ -129,7 +328,10 @@ pub fn Open() -> Html {
                                 password_input_focus
                             );
                             move |_| {
-                                selected_save_state.set(Some(save.clone()));
+                                selected_save_state.set(Some(SelectedSave {
+                                    path: save.path.clone(),
+                                    metadata: Some(save.metadata.clone()),
+                                }));
                                 dialog_open_state.set(true);
                                 password_input_focus.focus_late();
                             }
@@ -140,46 +342,88 @@ pub fn Open() -> Html {
                                 <div class="open-save-button hoverable" {onclick}>
                                     <SaveIcon open={false} size={48} />
                                     <span class="open-save-name clamp-3">{save_name}</span>
+                                    <span class="open-save-opened-at">{opened_at_str}</span>
                                 </div>
                             </div>
                         }
                     })
                     .collect::<Html>();
 
-                let (save_name, save_description, save_created_at, save_last_opened_at) =
-                    selected_save_state
+                let (save_path, save_metadata) = selected_save_state
+                    .as_ref()
+                    .map(|save| (save.path.clone(), save.metadata.clone()))
+                    .unwrap_or_else(|| ("<SAVE PATH>".to_owned(), None));
+                let save_name = match &save_metadata {
+                    Some(metadata) => save_display_name(metadata),
+                    None => Path::new(&save_path)
+                        .file_stem()
+                        .map(|file_stem| file_stem.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| save_path.clone()),
+                };
+                let save_details = match &save_metadata {
+                    Some(metadata) => html! {
+                        <>
+                            if !metadata.encrypted {
+                                <p>{metadata.description.clone()}</p>
+                            }
+                            <p>{"Created at: "}{locale_timestamp_str(&metadata.created_at)}</p>
+                            <p>{"Last opened: "}{locale_timestamp_str(&metadata.last_opened_at)}</p>
+                        </>
+                    },
+                    None => html! {},
+                };
+
+                let input_open_save = move |_| try_open_save.run();
+                let dialog_open_save = {
+                    clone_states!(input_open_save);
+                    move |unlock| {
+                        if unlock {
+                            input_open_save(());
+                        }
+                    }
+                };
+
+                let create_on_click = move |_| view.set(View::Create);
+                let open_file_on_click = move |_| select_save_file.open();
+
+                let (leftover_name, leftover_description, leftover_modified_at, save_modified_at) =
+                    leftover_state
                         .as_ref()
-                        .map(|save| {
+                        .map(|leftover| {
                             (
-                                save.name.clone(),
-                                save.description.clone(),
-                                save.created_at,
-                                save.last_opened_at,
+                                if leftover.name.is_empty() {
+                                    ENCRYPTED_SAVE_LABEL.to_owned()
+                                } else {
+                                    leftover.name.clone()
+                                },
+                                leftover_description(leftover),
+                                locale_timestamp_str(&leftover.modified_at),
+                                leftover
+                                    .save_modified_at
+                                    .as_ref()
+                                    .map(locale_timestamp_str)
+                                    .unwrap_or_else(|| "Never".to_owned()),
                             )
                         })
                         .unwrap_or_else(|| {
                             (
                                 "<SAVE NAME>".to_owned(),
-                                "<SAVE DESCRIPTION>".to_owned(),
-                                default_timestamp,
-                                default_timestamp,
+                                String::new(),
+                                locale_timestamp_str(&default_timestamp),
+                                locale_timestamp_str(&default_timestamp),
                             )
                         });
 
-                let created_at_str = locale_timestamp_str(&save_created_at);
-                let last_opened_at_str = locale_timestamp_str(&save_last_opened_at);
-
-                let input_open_save = move |_| try_open_save.run();
-                let dialog_open_save = {
-                    clone_states!(input_open_save);
-                    move |unlock| {
-                        if unlock {
-                            input_open_save(());
+                let input_recover_leftover = move |_| try_recover_leftover.run();
+                let dialog_recover_leftover = {
+                    clone_states!(input_recover_leftover);
+                    move |recover| {
+                        if recover {
+                            input_recover_leftover(());
                         }
                     }
                 };
-
-                let create_on_click = move |_| view.set(View::Create);
+                let discard_leftover = move |_| try_discard_leftover.run();
 
                 html! {
                     <div class="view open">
@@ -201,6 +445,20 @@ pub fn Open() -> Html {
                                         <span class="open-save-create-name">{"New save"}</span>
                                     </div>
                                 </div>
+                                <div class="open-save-create">
+                                    <div
+                                        class="open-save-create-button hoverable"
+                                        onclick={open_file_on_click}
+                                    >
+                                        <div class="save-icon-create">
+                                            <img
+                                                src="assets/svg/file-solid.svg"
+                                                class="save-icon-plus"
+                                            />
+                                        </div>
+                                        <span class="open-save-create-name">{"Open file"}</span>
+                                    </div>
+                                </div>
                             </div>
                         </Frame>
                         <Dialog
@@ -211,20 +469,46 @@ pub fn Open() -> Html {
                             on_close_request={dialog_open_save}
                             close_on_ok={false}
                         >
-                            <p>{save_description}</p>
-                            <p>{"Created at: "}{created_at_str}</p>
-                            <p>{"Last opened: "}{last_opened_at_str}</p>
-                            <p>{"The save file can be unlocked by providing the save's password below."}</p>
+                            {save_details}
+                            <p class="open-save-path">{save_path}</p>
+                            <p>{"The save file can be unlocked by providing the save's password or recovery phrase below."}</p>
                             <Input
                                 state={save_password_state}
                                 input_type={InputType::Password}
-                                label="Password"
+                                label="Password or recovery phrase"
                                 required={true}
                                 on_submit={input_open_save}
                                 error={unlock_save_error}
                                 node={password_input_focus.node_ref()}
                             />
                         </Dialog>
+                        <Dialog
+                            state={leftover_dialog_state}
+                            title={format!("Recover {leftover_name}?")}
+                            ok_label="Recover"
+                            cancel_label="Decide later"
+                            on_close_request={dialog_recover_leftover}
+                            close_on_ok={false}
+                        >
+                            <p>{leftover_description}</p>
+                            <p>{"Leftover data last written: "}{leftover_modified_at}</p>
+                            <p>{"Save file last written: "}{save_modified_at}</p>
+                            <p>{"Recovering replaces the contents of the save file with the leftover data, and requires the save's password or recovery phrase. Discarding erases the leftover data for good."}</p>
+                            <Input
+                                state={leftover_password_state}
+                                input_type={InputType::Password}
+                                label="Password or recovery phrase"
+                                on_submit={input_recover_leftover}
+                                error={leftover_error}
+                            />
+                            <div class="open-leftover-actions">
+                                <Button
+                                    text="Discard"
+                                    style={ButtonStyle::Danger}
+                                    on_click={discard_leftover}
+                                />
+                            </div>
+                        </Dialog>
                         <LoadingOverlay state={loading_overlay_state} />
                     </div>
                 }
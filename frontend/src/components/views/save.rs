@@ -9,14 +9,19 @@ use commands::FrontendCommands;
 use common::*;
 use std::collections::HashMap;
 use yew::prelude::*;
+use yew_hooks::prelude::*;
 
 /// The number of transactions to request in one batch.
 const TRANSACTION_BATCH_LIMIT: usize = 100;
 
+/// How often to refresh the autosave status, in milliseconds.
+const AUTOSAVE_STATUS_INTERVAL: u32 = 5000;
+
 /// The open save page view.
 #[function_component]
 pub fn Save() -> Html {
     let save_info_state = use_state(|| None);
+    let autosave_status_state = use_state(|| None::<AutosaveStatus>);
     let accounts_state = use_state(|| None);
     let selected_account_index_state = use_state(|| None);
     let loaded_transactions_state = use_state(Vec::new);
@@ -35,7 +40,8 @@ pub fn Save() -> Html {
     let transaction_name_error_state = use_state(|| None::<String>);
     let transaction_description_state = use_state(String::new);
     let transaction_description_error_state = use_state(|| None::<String>);
-    let transaction_amount_state = use_state(|| NumberState::new(0.0).decimals(2));
+    let transaction_amount_state =
+        use_state(|| NumberState::new(Money::ZERO).decimals(Money::DECIMALS));
     let transaction_type_state = use_state(|| None);
     let transaction_type_error_state = use_state(|| None::<String>);
     let transaction_institution_state = use_state(|| None::<usize>);
@@ -80,6 +86,28 @@ pub fn Save() -> Html {
         }
     }));
 
+    let get_autosave_status = use_command(UseCommand::new({
+        clone_states!(autosave_status_state);
+        |backend| async move {
+            let autosave_status = backend.autosave_status().await?;
+            autosave_status_state.set(Some(autosave_status));
+            Ok(())
+        }
+    }));
+
+    use_interval(move || get_autosave_status.run(), AUTOSAVE_STATUS_INTERVAL);
+
+    let (_, dispatch_save_lock) = use_save_lock();
+    let lock_save = use_command(
+        UseCommand::new(|backend| async move { backend.lock_save().await })
+            .run_on_init(false)
+            .on_update(move |lock_save_result| {
+                if let UseCommandState::Resolved(Ok(())) = lock_save_result {
+                    dispatch_save_lock.reduce_mut(|save_lock| save_lock.locked = true);
+                }
+            }),
+    );
+
     let get_accounts = use_command(UseCommand::new({
         clone_states!(accounts_state, selected_account_index_state);
         |backend| async move {
@@ -294,15 +322,18 @@ pub fn Save() -> Html {
                                 backend
                                     .create_transaction(
                                         account.clone(),
-                                        name,
-                                        description,
-                                        amount,
-                                        transaction_type,
-                                        institution,
-                                        date,
-                                        category,
-                                        subcategory,
-                                        tags,
+                                        NewAccountTransaction {
+                                            name,
+                                            description,
+                                            amount,
+                                            transaction_type,
+                                            institution,
+                                            date,
+                                            category,
+                                            subcategory,
+                                            tags,
+                                            splits: Vec::new(),
+                                        },
                                     )
                                     .await
                                     .map(Some)
@@ -366,7 +397,8 @@ pub fn Save() -> Html {
                         transaction_name_error_state.set(None);
                         transaction_description_state.set(String::new());
                         transaction_description_error_state.set(None);
-                        transaction_amount_state.set(NumberState::new(0.0).decimals(2));
+                        transaction_amount_state
+                            .set(NumberState::new(Money::ZERO).decimals(Money::DECIMALS));
                         transaction_type_state.set(None);
                         transaction_type_error_state.set(None);
                         transaction_institution_state.set(None);
@@ -522,7 +554,7 @@ pub fn Save() -> Html {
                                 <span>{&transaction.description}</span>
                             </div>
                             <div class="account-transaction-field">
-                                <span>{&transaction.amount}</span>
+                                <span>{transaction.amount.to_string()}</span>
                             </div>
                             <div class="account-transaction-field">
                                 <span>{&transaction_type}</span>
@@ -666,6 +698,24 @@ pub fn Save() -> Html {
                 }
             };
 
+            let click_lock_save = {
+                clone_states!(lock_save);
+                move |_| lock_save.run()
+            };
+
+            let autosave_status = autosave_status_state
+                .as_ref()
+                .map(|autosave_status| {
+                    let saved_at = locale_timestamp_str(&autosave_status.saved_at);
+
+                    match autosave_status.unsaved_changes {
+                        0 => format!("All changes saved at {saved_at}"),
+                        1 => format!("1 unsaved change, last saved at {saved_at}"),
+                        n => format!("{n} unsaved changes, last saved at {saved_at}"),
+                    }
+                })
+                .unwrap_or_default();
+
             html! {
                 <div class="view save">
                     <div class="save-header bg-4">
@@ -673,6 +723,14 @@ pub fn Save() -> Html {
                             <span>{&save_info.name}</span>
                         </div>
                         <div class="save-actions">
+                            <span class="save-autosave-status">{autosave_status}</span>
+                            <Tooltip text="Lock save">
+                                <IconButton
+                                    name="lock-solid"
+                                    size={IconButtonSize::Small}
+                                    on_click={click_lock_save}
+                                />
+                            </Tooltip>
                             // TODO: save actions
                         </div>
                     </div>
@@ -768,7 +826,7 @@ pub fn Save() -> Html {
                                         />
                                     </div>
                                     <div class="account-transactions-new-input">
-                                        <NumberInput<f64>
+                                        <NumberInput<Money>
                                             state={transaction_amount_state}
                                             label="Amount"
                                             required={true}
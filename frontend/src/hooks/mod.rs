@@ -7,6 +7,7 @@ mod use_focus;
 mod use_id;
 mod use_popup;
 mod use_result;
+mod use_save_lock;
 mod use_subview;
 mod use_theme;
 mod use_view;
@@ -20,6 +21,7 @@ pub use use_focus::*;
 pub use use_id::*;
 pub use use_popup::*;
 pub use use_result::*;
+pub use use_save_lock::*;
 pub use use_subview::*;
 pub use use_theme::*;
 pub use use_view::*;
@@ -45,3 +47,9 @@ store_hook!(
     BackendHandle,
     "Gets a handle to the backend of the application."
 );
+
+store_hook!(
+    use_save_lock,
+    SaveLock,
+    "Gets a handle to whether the open save file is locked."
+);
@@ -43,6 +43,9 @@ pub struct FileSelectConfig {
     directory: bool,
     /// Whether to allow selection of multiple files.
     multiple: bool,
+    /// Whether to choose where to save a new file rather than select an
+    /// existing one.
+    save: bool,
     /// A list of acceptable file extensions. If empty, all files will be
     /// allowed.
     extensions: Option<Vec<String>>,
@@ -80,6 +83,12 @@ impl FileSelectConfig {
         self
     }
 
+    /// Enables or disables choosing where to save a new file.
+    pub fn save(mut self, save: bool) -> Self {
+        self.save = save;
+        self
+    }
+
     /// Sets the allowed file extensions. If not specified, all file
     /// extensions will be allowed.
     pub fn extensions(mut self, exts: Option<Vec<String>>) -> Self {
@@ -97,7 +106,7 @@ impl FileSelectConfig {
     }
 
     /// Convert the configuration into the arguments for the the file open
-    /// API.
+    /// or save API.
     pub fn to_args(&self) -> FileSelectArgs {
         FileSelectArgs {
             default_path: self.start_path.clone(),
@@ -125,13 +134,14 @@ impl UseFileSelect {
     pub fn open(&self) {
         let tauri = web_sys::window().unwrap().get("__TAURI__").unwrap();
         let dialog = Reflect::get(&tauri.into(), &"dialog".into()).unwrap();
-        let open = Reflect::get(&dialog, &"open".into()).unwrap();
-        let open_function = open.dyn_ref::<Function>().unwrap();
+        let method = if self.config.save { "save" } else { "open" };
+        let dialog_method = Reflect::get(&dialog, &method.into()).unwrap();
+        let dialog_function = dialog_method.dyn_ref::<Function>().unwrap();
 
         let args = self.config.to_args();
         let js_args = serde_wasm_bindgen::to_value(&args).unwrap();
 
-        let response = open_function.call1(open_function, &js_args).unwrap();
+        let response = dialog_function.call1(dialog_function, &js_args).unwrap();
         let response_promise = response.dyn_into::<Promise>().unwrap();
         let response_future = JsFuture::from(response_promise);
 
@@ -0,0 +1,8 @@
+use yewdux::prelude::*;
+
+/// Whether the open save file is locked, as last reported by the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Store)]
+pub struct SaveLock {
+    /// Whether the open save file is locked.
+    pub locked: bool,
+}
@@ -1,10 +1,11 @@
 #![allow(dead_code)]
 
-use js_sys::Math;
+use chrono::{NaiveDateTime, Utc};
+use common::Money;
+use js_sys::{Date, Math, Object};
 use std::fmt::{Debug, Display};
-use std::ops::{Add, Div, Mul, Sub};
 use std::str::FromStr;
-use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use wasm_bindgen::{JsCast, JsValue, UnwrapThrowExt};
 use web_sys::{
     Document, Event, HtmlElement, HtmlInputElement, HtmlTextAreaElement, InputEvent, MouseEvent,
     Window,
@@ -21,6 +22,36 @@ pub fn document() -> Document {
     window().document().expect_throw("document does not exist")
 }
 
+/// Gets the user locale from the browser.
+pub fn user_locale() -> String {
+    let window = window();
+    let navigator = window.navigator();
+    let languages = navigator.languages();
+    let language = navigator.language();
+    let first_language = languages.get(0).as_string();
+
+    if let Some(lang) = first_language {
+        lang
+    } else if let Some(lang) = language {
+        lang
+    } else {
+        "en-US".to_owned()
+    }
+}
+
+/// Formats a timestamp using the JS Date API's `.toLocaleString()` method.
+pub fn locale_timestamp_str(timestamp: &NaiveDateTime) -> String {
+    let datetime = timestamp.and_local_timezone(Utc).unwrap();
+    let js_millis = JsValue::from_f64(datetime.timestamp_millis() as f64);
+    let js_date = Date::new(&js_millis);
+    let locale = user_locale();
+    let options = Object::new();
+    js_date
+        .to_locale_string(&locale, &options)
+        .as_string()
+        .unwrap()
+}
+
 /// Gets the value of an input element from an event.
 pub fn input_event_value(e: InputEvent) -> String {
     let event: Event = e.dyn_into().unwrap_throw();
@@ -148,19 +179,7 @@ pub(crate) use clone_states;
 
 /// A trait for numeric values.
 pub trait Number:
-    PartialEq
-    + PartialOrd
-    + FromStr
-    + ToString
-    + Default
-    + Clone
-    + Copy
-    + Display
-    + Debug
-    + Add<Self, Output = Self>
-    + Sub<Self, Output = Self>
-    + Mul<Self, Output = Self>
-    + Div<Self, Output = Self>
+    PartialEq + PartialOrd + FromStr + ToString + Default + Clone + Copy + Display + Debug
 {
     const NUMBER_MIN: Self;
     const NUMBER_MAX: Self;
@@ -209,3 +228,14 @@ macro_rules! impl_number_float {
 impl_number_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
 
 impl_number_float!(f32, f64);
+
+impl Number for Money {
+    const NUMBER_MIN: Self = Self::MIN;
+    const NUMBER_MAX: Self = Self::MAX;
+    const NUMBER_STEP: Self = Self::from_minor_units(Self::MINOR_UNITS);
+    const DECIMAL: bool = true;
+
+    fn as_f64(self) -> f64 {
+        self.to_f64()
+    }
+}
@@ -190,6 +190,15 @@ pub fn validate_account_name(name: &str) -> Result<String, String> {
     }
 }
 
+pub fn validate_account_currency(currency: &str) -> Result<Currency, String> {
+    Currency::new(currency).map_err(|err| match err {
+        ExpectedCommandError::UnsupportedCurrency => {
+            "Currency must have two decimal places, unlike JPY or KWD".to_owned()
+        }
+        _ => "Currency must be a three letter code, such as USD".to_owned(),
+    })
+}
+
 pub fn validate_account_description(description: &str) -> Result<String, String> {
     if description.len() > ACCOUNT_DESCRIPTION_MAX_LENGTH {
         Err(format!(